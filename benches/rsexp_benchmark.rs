@@ -3,7 +3,7 @@ use rand::seq::SliceRandom;
 use rand::SeedableRng;
 use rand_pcg::Lcg128Xsl64;
 
-fn make_n_random_characters(n: i64, alphabet: &Vec<char>, rng: &mut Lcg128Xsl64) -> String {
+fn make_n_random_characters(n: i64, alphabet: &[char], rng: &mut Lcg128Xsl64) -> String {
     (0..n).map(|_| alphabet.choose(rng).unwrap()).collect()
 }
 
@@ -11,22 +11,12 @@ fn make_benchmark_string(
     num_repetitions: &[i64],
     str_len: i64,
     quoted: bool,
-    alphabet: &Vec<char>,
+    alphabet: &[char],
     rng: &mut Lcg128Xsl64,
 ) -> String {
-    if let Some(len) = num_repetitions.get(0) {
-        format!(
-            "({})",
-            std::iter::repeat(make_benchmark_string(
-                &num_repetitions[1..],
-                str_len,
-                quoted,
-                alphabet,
-                rng
-            ))
-            .take(*len as usize)
-            .collect::<String>()
-        )
+    if let Some(len) = num_repetitions.first() {
+        let inner = make_benchmark_string(&num_repetitions[1..], str_len, quoted, alphabet, rng);
+        format!("({})", inner.repeat(*len as usize))
     } else {
         let chars = make_n_random_characters(str_len, alphabet, rng);
         if quoted {
@@ -41,6 +31,10 @@ fn parse_sexp(contents: &[u8]) {
     let _sexp = rsexp::from_slice(&contents).unwrap();
 }
 
+fn parse_sexp_ref(contents: &[u8]) {
+    let _sexp = rsexp::from_slice_ref(&contents).unwrap();
+}
+
 pub fn criterion_benchmark(c: &mut Criterion) {
     let mut rng: Lcg128Xsl64 = Lcg128Xsl64::seed_from_u64(54321);
     // TODO: Include some special characters to test escape sequences in strings.
@@ -57,11 +51,13 @@ pub fn criterion_benchmark(c: &mut Criterion) {
                 str_len = str_len,
                 quoted = (if quoted { "quoted" } else { "unquoted" })
             );
-            let num_repetitions: Vec<i64> =
-                std::iter::repeat(repetitions as i64).take(depth).collect();
+            let num_repetitions: Vec<i64> = vec![repetitions as i64; depth];
             let sexp =
                 make_benchmark_string(&num_repetitions, str_len, quoted, &alphabet, &mut rng);
             c.bench_function(&bench_name, |b| b.iter(|| parse_sexp(black_box(sexp.as_bytes()))));
+            c.bench_function(&format!("{bench_name}_ref"), |b| {
+                b.iter(|| parse_sexp_ref(black_box(sexp.as_bytes())))
+            });
         }
     }
 }
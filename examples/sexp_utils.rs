@@ -50,29 +50,21 @@ impl Print {
         let contents = std::fs::read(&self.input_filename)?;
         let sexp = rsexp::from_slice(&contents).unwrap();
         if self.mach {
-            sexp.write_mach(&mut std::io::stdout())?;
+            let cfg = rsexp::MachConfig { trailing_newline: true, ..Default::default() };
+            sexp.write_mach_with_config(&cfg, &mut std::io::stdout())?;
         } else {
-            sexp.write_hum(&mut std::io::stdout())?;
+            let cfg = rsexp::HumConfig { trailing_newline: true, ..Default::default() };
+            sexp.write_hum_with_config(&cfg, &mut std::io::stdout())?;
         }
-        println!();
         Ok(())
     }
 }
 
 fn cnt_loop(s: &Sexp) -> (usize, usize) {
-    match s {
-        Sexp::Atom(atom) => (1, atom.len()),
-        Sexp::List(list) => {
-            let mut cnt_atoms = 0;
-            let mut cnt_bytes = 0;
-            for elem in list.iter() {
-                let (tmp_atoms, tmp_bytes) = cnt_loop(elem);
-                cnt_atoms += tmp_atoms;
-                cnt_bytes += tmp_bytes;
-            }
-            (cnt_atoms, cnt_bytes)
-        }
-    }
+    s.fold((0, 0), |(cnt_atoms, cnt_bytes), s| match s {
+        Sexp::Atom(atom) => (cnt_atoms + 1, cnt_bytes + atom.len()),
+        Sexp::List(_) => (cnt_atoms, cnt_bytes),
+    })
 }
 
 impl Bench {
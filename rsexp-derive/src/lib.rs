@@ -2,91 +2,616 @@
 // It might be more efficient to write a direct serialization/deserialization deriver,
 // directly or via serde.
 //
-// TODO: support sexp.option, default values, allow extra fields, etc.
+// The behavior can be adjusted via #[sexp(...)] attributes on the container,
+// variants and fields, see ContainerAttrs, VariantAttrs and FieldAttrs below.
+//
+// TODO: support default values for missing named fields, similar to [@default]
+// in ppx_sexp_conv, these are only supported on positional fields for now.
 extern crate proc_macro;
 
 use proc_macro::TokenStream;
 use quote::{format_ident, quote};
-use syn::{
-    parse_quote, DataEnum, DataUnion, DeriveInput, FieldsNamed, FieldsUnnamed, GenericParam,
-};
+use syn::{parse_quote, DataEnum, DataUnion, DeriveInput, FieldsUnnamed, GenericParam};
+
+// Attributes that can be set on a field via #[sexp(...)].
+#[derive(Default)]
+struct FieldAttrs {
+    // The field is a map collecting all the (key value) pairs that do not
+    // correspond to another field.
+    collect_extra: bool,
+    // The boolean field is represented by the presence of the (key) list.
+    flag: bool,
+    // The option field is omitted when None and represented as (key value)
+    // when Some, similar to [@sexp.option] in ppx_sexp_conv.
+    option: bool,
+    // The vector elements are spliced in the (key elem1 elem2 ...) list rather
+    // than nested, similar to [@sexp.list] in ppx_sexp_conv. The field is
+    // omitted when empty and defaults to an empty vector when missing.
+    flatten_list: bool,
+    // Set via validate = "path::to::fn", the function is called with a
+    // reference to the field value once converted and returns Err(msg) if
+    // the value is invalid.
+    validate: Option<syn::Path>,
+    // The field of a positional struct, tuple struct or tuple variant takes
+    // its default value when the list is too short to contain it, this can
+    // only be used on trailing fields.
+    default: bool,
+    // Set via serialize_with = "path::to::fn", or with = "path::to::module"
+    // which uses module::sexp_of, the function converts a reference to the
+    // field value to a Sexp instead of SexpOf.
+    serialize_with: Option<syn::Path>,
+    // Set via deserialize_with = "path::to::fn", or with = "path::to::module"
+    // which uses module::of_sexp, the function converts a &Sexp to the field
+    // value instead of OfSexp.
+    deserialize_with: Option<syn::Path>,
+}
+
+impl FieldAttrs {
+    fn of_field(field: &syn::Field) -> syn::Result<Self> {
+        let mut attrs = FieldAttrs::default();
+        for meta in sexp_attrs(&field.attrs)? {
+            match &meta {
+                syn::NestedMeta::Meta(syn::Meta::Path(path)) if path.is_ident("collect_extra") => {
+                    attrs.collect_extra = true
+                }
+                syn::NestedMeta::Meta(syn::Meta::Path(path)) if path.is_ident("flag") => {
+                    attrs.flag = true
+                }
+                syn::NestedMeta::Meta(syn::Meta::Path(path)) if path.is_ident("option") => {
+                    attrs.option = true
+                }
+                syn::NestedMeta::Meta(syn::Meta::Path(path)) if path.is_ident("flatten_list") => {
+                    attrs.flatten_list = true
+                }
+                syn::NestedMeta::Meta(syn::Meta::Path(path)) if path.is_ident("default") => {
+                    attrs.default = true
+                }
+                syn::NestedMeta::Meta(syn::Meta::NameValue(nv))
+                    if nv.path.is_ident("with")
+                        || nv.path.is_ident("serialize_with")
+                        || nv.path.is_ident("deserialize_with") =>
+                {
+                    let path: syn::Path = match &nv.lit {
+                        syn::Lit::Str(s) => s.parse()?,
+                        lit => {
+                            let msg = "expected a path in a string literal";
+                            return Err(syn::Error::new_spanned(lit, msg));
+                        }
+                    };
+                    let with_fn = |name: &str| {
+                        let mut path = path.clone();
+                        path.segments.push(format_ident!("{}", name).into());
+                        path
+                    };
+                    let (serialize_with, deserialize_with) = if nv.path.is_ident("with") {
+                        (Some(with_fn("sexp_of")), Some(with_fn("of_sexp")))
+                    } else if nv.path.is_ident("serialize_with") {
+                        (Some(path), None)
+                    } else {
+                        (None, Some(path))
+                    };
+                    if (serialize_with.is_some() && attrs.serialize_with.is_some())
+                        || (deserialize_with.is_some() && attrs.deserialize_with.is_some())
+                    {
+                        let msg = "with, serialize_with, and deserialize_with overlap";
+                        return Err(syn::Error::new_spanned(nv, msg));
+                    }
+                    attrs.serialize_with = attrs.serialize_with.take().or(serialize_with);
+                    attrs.deserialize_with = attrs.deserialize_with.take().or(deserialize_with);
+                }
+                syn::NestedMeta::Meta(syn::Meta::NameValue(nv)) if nv.path.is_ident("validate") => {
+                    attrs.validate = match &nv.lit {
+                        syn::Lit::Str(s) => Some(s.parse()?),
+                        lit => {
+                            let msg = "expected validate = \"path::to::fn\"";
+                            return Err(syn::Error::new_spanned(lit, msg));
+                        }
+                    }
+                }
+                _ => return Err(syn::Error::new_spanned(meta, "unknown sexp field attribute")),
+            }
+        }
+        if attrs.flatten_list && !is_vec(&field.ty) {
+            let msg = "flatten_list can only be used on Vec fields";
+            return Err(syn::Error::new_spanned(&field.ty, msg));
+        }
+        let custom = attrs.serialize_with.is_some() || attrs.deserialize_with.is_some();
+        if custom && (attrs.collect_extra || attrs.flag || attrs.option || attrs.flatten_list) {
+            let msg = "with cannot be combined with collect_extra, flag, option, or flatten_list";
+            return Err(syn::Error::new_spanned(field, msg));
+        }
+        Ok(attrs)
+    }
+
+    // Converts a reference to the field value to a Sexp.
+    fn sexp_of(&self, value: proc_macro2::TokenStream) -> proc_macro2::TokenStream {
+        match &self.serialize_with {
+            Some(f) => quote! { #f(#value) },
+            None => quote! { (#value).sexp_of() },
+        }
+    }
+
+    // Converts a &Sexp to the field value, returning a Result.
+    fn of_sexp(&self, sexp: proc_macro2::TokenStream) -> proc_macro2::TokenStream {
+        match &self.deserialize_with {
+            Some(f) => quote! { #f(#sexp) },
+            None => quote! { rsexp::OfSexp::of_sexp(#sexp) },
+        }
+    }
+}
+
+// Attributes that can be set on an enum variant via #[sexp(...)].
+#[derive(Default)]
+struct VariantAttrs {
+    // Set via alias = "OldName", possibly multiple times, the other
+    // constructors accepted when converting from a sexp.
+    aliases: Vec<syn::LitStr>,
+    // Set via rename = "new-name", the constructor used instead of the
+    // variant name.
+    rename: Option<syn::LitStr>,
+}
+
+impl VariantAttrs {
+    fn of_variant(variant: &syn::Variant) -> syn::Result<Self> {
+        let mut attrs = VariantAttrs::default();
+        for meta in sexp_attrs(&variant.attrs)? {
+            match &meta {
+                syn::NestedMeta::Meta(syn::Meta::NameValue(nv)) if nv.path.is_ident("alias") => {
+                    match &nv.lit {
+                        syn::Lit::Str(s) => attrs.aliases.push(s.clone()),
+                        lit => {
+                            let msg = "expected alias = \"OldName\"";
+                            return Err(syn::Error::new_spanned(lit, msg));
+                        }
+                    }
+                }
+                syn::NestedMeta::Meta(syn::Meta::NameValue(nv)) if nv.path.is_ident("rename") => {
+                    match &nv.lit {
+                        syn::Lit::Str(s) if attrs.rename.is_none() => {
+                            attrs.rename = Some(s.clone())
+                        }
+                        syn::Lit::Str(s) => {
+                            return Err(syn::Error::new_spanned(s, "duplicate rename"));
+                        }
+                        lit => {
+                            let msg = "expected rename = \"new-name\"";
+                            return Err(syn::Error::new_spanned(lit, msg));
+                        }
+                    }
+                }
+                _ => return Err(syn::Error::new_spanned(meta, "unknown sexp variant attribute")),
+            }
+        }
+        Ok(attrs)
+    }
+
+    // The constructor used for the variant.
+    fn constructor(&self, variant: &syn::Variant) -> syn::LitByteStr {
+        match &self.rename {
+            Some(rename) => syn::LitByteStr::new(rename.value().as_bytes(), rename.span()),
+            None => {
+                let ident = &variant.ident;
+                syn::LitByteStr::new(ident.to_string().as_bytes(), ident.span())
+            }
+        }
+    }
+}
+
+// Attributes that can be set on a struct or enum via #[sexp(...)].
+#[derive(Default)]
+struct ContainerAttrs {
+    // The struct is represented as a flat list alternating keys and values,
+    // (key1 value1 key2 value2), rather than as a list of pairs.
+    flat_kv: bool,
+    // Set via variant_style = "wrapped", the enum constructors are wrapped in
+    // a list, ((C) 42) rather than (C 42), and (C) rather than C.
+    wrapped_variants: bool,
+    // The variants of a fieldless enum are represented by their position,
+    // e.g. 1 for the second variant.
+    by_index: bool,
+    // The struct with named fields is represented as the list of its field
+    // values in order, (value1 value2) rather than ((key1 value1) (key2 value2)).
+    positional: bool,
+    // The struct with a single field is represented as the field value, e.g.
+    // 42 rather than (42) for struct UserId(u64).
+    transparent: bool,
+    // An of_sexp_all_errors function is generated for the struct with named
+    // fields, returning all the errors related to the fields rather than only
+    // the first one.
+    all_errors: bool,
+}
+
+impl ContainerAttrs {
+    fn of_input(ast: &DeriveInput) -> syn::Result<Self> {
+        let mut attrs = ContainerAttrs::default();
+        for meta in sexp_attrs(&ast.attrs)? {
+            match &meta {
+                syn::NestedMeta::Meta(syn::Meta::Path(path)) if path.is_ident("flat_kv") => {
+                    attrs.flat_kv = true
+                }
+                syn::NestedMeta::Meta(syn::Meta::Path(path)) if path.is_ident("positional") => {
+                    if !matches!(
+                        ast.data,
+                        syn::Data::Struct(syn::DataStruct { fields: syn::Fields::Named(_), .. })
+                    ) {
+                        let msg = "positional is only supported on structs with named fields";
+                        return Err(syn::Error::new_spanned(path, msg));
+                    }
+                    attrs.positional = true
+                }
+                syn::NestedMeta::Meta(syn::Meta::Path(path)) if path.is_ident("transparent") => {
+                    let num_fields = match &ast.data {
+                        syn::Data::Struct(s) => s.fields.len(),
+                        _ => 0,
+                    };
+                    if num_fields != 1 {
+                        let msg = "transparent is only supported on structs with a single field";
+                        return Err(syn::Error::new_spanned(path, msg));
+                    }
+                    attrs.transparent = true
+                }
+                syn::NestedMeta::Meta(syn::Meta::Path(path)) if path.is_ident("by_index") => {
+                    let is_fieldless_enum = match &ast.data {
+                        syn::Data::Enum(e) => {
+                            e.variants.iter().all(|v| matches!(v.fields, syn::Fields::Unit))
+                        }
+                        _ => false,
+                    };
+                    if !is_fieldless_enum {
+                        let msg = "by_index is only supported on enums without fields";
+                        return Err(syn::Error::new_spanned(path, msg));
+                    }
+                    if let syn::Data::Enum(e) = &ast.data {
+                        if let Some((_, discriminant)) =
+                            e.variants.iter().find_map(|v| v.discriminant.as_ref())
+                        {
+                            let msg = "by_index uses the variant positions, explicit \
+                                       discriminants are not supported";
+                            return Err(syn::Error::new_spanned(discriminant, msg));
+                        }
+                    }
+                    attrs.by_index = true
+                }
+                syn::NestedMeta::Meta(syn::Meta::Path(path)) if path.is_ident("all_errors") => {
+                    attrs.all_errors = true
+                }
+                syn::NestedMeta::Meta(syn::Meta::NameValue(nv))
+                    if nv.path.is_ident("variant_style") =>
+                {
+                    attrs.wrapped_variants = match &nv.lit {
+                        syn::Lit::Str(s) if s.value() == "inline" => false,
+                        syn::Lit::Str(s) if s.value() == "wrapped" => true,
+                        lit => {
+                            let msg = "expected variant_style = \"inline\" or \"wrapped\"";
+                            return Err(syn::Error::new_spanned(lit, msg));
+                        }
+                    };
+                    if !matches!(ast.data, syn::Data::Enum(_)) {
+                        let msg = "variant_style is only supported on enums";
+                        return Err(syn::Error::new_spanned(nv, msg));
+                    }
+                }
+                _ => return Err(syn::Error::new_spanned(meta, "unknown sexp attribute")),
+            }
+        }
+        if attrs.by_index && attrs.wrapped_variants {
+            let msg = "by_index and variant_style cannot be used together";
+            return Err(syn::Error::new_spanned(&ast.ident, msg));
+        }
+        if attrs.positional && attrs.flat_kv {
+            let msg = "positional and flat_kv cannot be used together";
+            return Err(syn::Error::new_spanned(&ast.ident, msg));
+        }
+        if attrs.transparent && (attrs.positional || attrs.flat_kv) {
+            let msg = "transparent cannot be used with positional or flat_kv";
+            return Err(syn::Error::new_spanned(&ast.ident, msg));
+        }
+        if attrs.all_errors
+            && (attrs.positional
+                || attrs.transparent
+                || !matches!(
+                    ast.data,
+                    syn::Data::Struct(syn::DataStruct { fields: syn::Fields::Named(_), .. })
+                ))
+        {
+            let msg = "all_errors is only supported on structs with named fields";
+            return Err(syn::Error::new_spanned(&ast.ident, msg));
+        }
+        if attrs.flat_kv {
+            let fields_named = match &ast.data {
+                syn::Data::Struct(syn::DataStruct { fields: syn::Fields::Named(f), .. }) => f,
+                _ => {
+                    let msg = "flat_kv is only supported on structs with named fields";
+                    return Err(syn::Error::new_spanned(&ast.ident, msg));
+                }
+            };
+            for (name, field_attrs) in named_fields_attrs(fields_named)? {
+                if field_attrs.flag || field_attrs.flatten_list {
+                    let msg = "flag and flatten_list cannot be used in a flat_kv struct";
+                    return Err(syn::Error::new_spanned(name, msg));
+                }
+            }
+        }
+        Ok(attrs)
+    }
+}
+
+fn is_vec(ty: &syn::Type) -> bool {
+    match ty {
+        syn::Type::Path(path) => path.path.segments.last().is_some_and(|s| s.ident == "Vec"),
+        _ => false,
+    }
+}
+
+// Returns the items of all the #[sexp(...)] attributes.
+fn sexp_attrs(attrs: &[syn::Attribute]) -> syn::Result<Vec<syn::NestedMeta>> {
+    let mut res = vec![];
+    for attr in attrs.iter().filter(|attr| attr.path.is_ident("sexp")) {
+        match attr.parse_meta()? {
+            syn::Meta::List(list) => res.extend(list.nested),
+            meta => return Err(syn::Error::new_spanned(meta, "expected #[sexp(...)]")),
+        }
+    }
+    Ok(res)
+}
+
+// Returns the names and attributes of named fields, checking that the
+// attributes are consistent.
+fn named_fields_attrs(
+    fields_named: &syn::FieldsNamed,
+) -> syn::Result<Vec<(&syn::Ident, FieldAttrs)>> {
+    let mut res = vec![];
+    let mut has_collect_extra = false;
+    for field in fields_named.named.iter() {
+        let attrs = FieldAttrs::of_field(field)?;
+        if attrs.default {
+            let msg = "default can only be used on positional fields";
+            return Err(syn::Error::new_spanned(field, msg));
+        }
+        let exclusive = [attrs.collect_extra, attrs.flag, attrs.option, attrs.flatten_list];
+        if exclusive.iter().filter(|b| **b).count() > 1 {
+            let msg =
+                "only one of collect_extra, flag, option, and flatten_list can be used on a field";
+            return Err(syn::Error::new_spanned(field, msg));
+        }
+        if attrs.collect_extra {
+            if has_collect_extra {
+                let msg = "collect_extra can only be used on a single field";
+                return Err(syn::Error::new_spanned(field, msg));
+            }
+            has_collect_extra = true
+        }
+        res.push((field.ident.as_ref().unwrap(), attrs))
+    }
+    Ok(res)
+}
+
+// Returns the attributes of the fields of a positional struct, tuple struct
+// or tuple variant, checking that the fields with a default are trailing.
+fn positional_fields_attrs<'a>(
+    fields: impl Iterator<Item = &'a syn::Field>,
+) -> syn::Result<Vec<FieldAttrs>> {
+    let mut res: Vec<FieldAttrs> = vec![];
+    for field in fields {
+        let attrs = FieldAttrs::of_field(field)?;
+        let others = [attrs.collect_extra, attrs.flag, attrs.option, attrs.flatten_list];
+        if others.iter().any(|b| *b) || attrs.validate.is_some() {
+            let msg = "only default and with can be used on positional fields";
+            return Err(syn::Error::new_spanned(field, msg));
+        }
+        if !attrs.default && res.last().is_some_and(|attrs| attrs.default) {
+            let msg = "default fields must come after the other fields";
+            return Err(syn::Error::new_spanned(field, msg));
+        }
+        res.push(attrs)
+    }
+    Ok(res)
+}
 
-#[proc_macro_derive(SexpOf)]
+// Builds the list of (key value) pairs for named fields, preceded by the
+// constructor if any. `value` returns a reference to the field value. When
+// `flat_kv` is set, the keys and values are pushed directly to the list.
+fn named_fields_sexp_of(
+    fields_named: &syn::FieldsNamed,
+    cstor: Option<proc_macro2::TokenStream>,
+    flat_kv: bool,
+    value: impl Fn(&syn::Ident) -> proc_macro2::TokenStream,
+) -> syn::Result<proc_macro2::TokenStream> {
+    let fields_attrs = named_fields_attrs(fields_named)?;
+    let push_kv = |key: proc_macro2::TokenStream, value: proc_macro2::TokenStream| {
+        if flat_kv {
+            quote! {
+                __list.push(#key);
+                __list.push(#value);
+            }
+        } else {
+            quote! { __list.push(rsexp::list(&[#key, #value])); }
+        }
+    };
+    let cstor = cstor.map(|cstor| quote! { __list.push(#cstor); });
+    let fields = fields_attrs.iter().map(|(name, attrs)| {
+        let value = value(name);
+        let name_str = name.to_string();
+        let key = quote! { rsexp::atom(#name_str.as_bytes()) };
+        if attrs.collect_extra {
+            let push = push_kv(quote! { __key.sexp_of() }, quote! { __value.sexp_of() });
+            quote! {
+                for (__key, __value) in (#value).iter() {
+                    #push
+                }
+            }
+        } else if attrs.flag {
+            quote! {
+                if *(#value) {
+                    __list.push(rsexp::list(&[rsexp::atom(#name_str.as_bytes())]));
+                }
+            }
+        } else if attrs.flatten_list {
+            quote! {
+                if !(#value).is_empty() {
+                    let mut __entry = vec![#key];
+                    __entry.extend((#value).iter().map(|__value| __value.sexp_of()));
+                    __list.push(rsexp::Sexp::List(__entry));
+                }
+            }
+        } else if attrs.option {
+            let push = push_kv(key, quote! { __value.sexp_of() });
+            quote! {
+                if let Some(__value) = (#value).as_ref() {
+                    #push
+                }
+            }
+        } else {
+            push_kv(key, attrs.sexp_of(value))
+        }
+    });
+    Ok(quote! {
+        {
+            let mut __list: Vec<rsexp::Sexp> = Vec::new();
+            #cstor
+            #(#fields)*
+            rsexp::Sexp::List(__list)
+        }
+    })
+}
+
+fn unit_struct_error(ident: &syn::Ident) -> TokenStream {
+    let msg = "unit structs are not supported, use a struct with no fields `struct S {}` instead";
+    syn::Error::new_spanned(ident, msg).to_compile_error().into()
+}
+
+#[proc_macro_derive(SexpOf, attributes(sexp))]
 pub fn sexp_of_derive(input: TokenStream) -> TokenStream {
-    let ast = syn::parse(input).unwrap();
+    let ast = syn::parse_macro_input!(input as DeriveInput);
     impl_sexp_of(&ast)
 }
 
 fn impl_sexp_of(ast: &DeriveInput) -> TokenStream {
     let DeriveInput { ident, data, generics, .. } = ast;
+    let container_attrs = match ContainerAttrs::of_input(ast) {
+        Ok(attrs) => attrs,
+        Err(err) => return err.to_compile_error().into(),
+    };
+    // Only the type parameters get a bound, the lifetime and const parameters
+    // are forwarded unchanged.
     let mut generics = generics.clone();
     for param in &mut generics.params {
         if let GenericParam::Type(type_param) = param {
-            type_param.bounds.push(parse_quote!(SexpOf))
+            type_param.bounds.push(parse_quote!(rsexp::SexpOf))
         }
     }
     let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
     let impl_fn = match data {
+        syn::Data::Struct(s) if container_attrs.transparent => {
+            let field = match s.fields.iter().next().unwrap().ident.as_ref() {
+                Some(name) => quote! { #name },
+                None => quote! { 0 },
+            };
+            quote! { self.#field.sexp_of() }
+        }
         syn::Data::Struct(s) => match &s.fields {
-            syn::Fields::Named(FieldsNamed { named, .. }) => {
-                let fields = named.iter().map(|field| {
+            syn::Fields::Named(f) if container_attrs.positional => {
+                let fields_attrs = match positional_fields_attrs(f.named.iter()) {
+                    Ok(fields_attrs) => fields_attrs,
+                    Err(err) => return err.to_compile_error().into(),
+                };
+                let fields = f.named.iter().zip(fields_attrs.iter()).map(|(field, attrs)| {
                     let name = field.ident.as_ref().unwrap();
-                    let name_str = name.to_string();
-                    quote! { rsexp::list(&[rsexp::atom(#name_str.as_bytes()), self.#name.sexp_of()]) }
+                    attrs.sexp_of(quote! { &self.#name })
                 });
                 quote! {rsexp::list(&[#(#fields),*])}
             }
+            syn::Fields::Named(f) => {
+                match named_fields_sexp_of(
+                    f,
+                    None,
+                    container_attrs.flat_kv,
+                    |name| quote! { &self.#name },
+                ) {
+                    Ok(sexp) => sexp,
+                    Err(err) => return err.to_compile_error().into(),
+                }
+            }
             syn::Fields::Unnamed(FieldsUnnamed { unnamed, .. }) => {
-                let num_fields = unnamed.len();
-                let fields = (0..num_fields).map(|index| {
+                let fields_attrs = match positional_fields_attrs(unnamed.iter()) {
+                    Ok(fields_attrs) => fields_attrs,
+                    Err(err) => return err.to_compile_error().into(),
+                };
+                let fields = fields_attrs.iter().enumerate().map(|(index, attrs)| {
                     let index = syn::Index::from(index);
-                    quote! { self.#index.sexp_of() }
+                    attrs.sexp_of(quote! { &self.#index })
                 });
                 quote! {rsexp::list(&[#(#fields),*])}
             }
-            syn::Fields::Unit => {
-                unimplemented!()
-            }
+            syn::Fields::Unit => return unit_struct_error(ident),
         },
+        syn::Data::Enum(DataEnum { variants, .. }) if container_attrs.by_index => {
+            let cases = variants.iter().enumerate().map(|(index, variant)| {
+                let variant_ident = &variant.ident;
+                quote! { #ident::#variant_ident => #index, }
+            });
+            quote! {
+                let __index: usize = match self {
+                    #(#cases)*
+                };
+                rsexp::SexpOf::sexp_of(&__index)
+            }
+        }
         syn::Data::Enum(DataEnum { variants, .. }) => {
-            let cases = variants.iter().map(|variant| {
+            let mut cases = vec![];
+            for variant in variants.iter() {
                 let variant_ident = &variant.ident;
-                let variant_bytes = syn::LitByteStr::new(variant_ident.to_string().as_bytes(), variant_ident.span());
-                let cstor = quote! { rsexp::atom(#variant_bytes) };
+                let variant_bytes = match VariantAttrs::of_variant(variant) {
+                    Ok(attrs) => attrs.constructor(variant),
+                    Err(err) => return err.to_compile_error().into(),
+                };
+                let cstor = if container_attrs.wrapped_variants {
+                    quote! { rsexp::list(&[rsexp::atom(#variant_bytes)]) }
+                } else {
+                    quote! { rsexp::atom(#variant_bytes) }
+                };
                 let (pattern, sexp) = match &variant.fields {
-                    syn::Fields::Named(FieldsNamed { named, .. }) => {
-                        let args = named.iter().map(|field| field.ident.as_ref().unwrap());
-                        let fields = named.iter().map(|field| {
-                            let name = field.ident.as_ref().unwrap();
-                            let name_str = name.to_string();
-                            quote! { rsexp::list(&[rsexp::atom(#name_str.as_bytes()), #name.sexp_of()]) }
-                        });
-                        let sexp =
-                            if variant.fields.is_empty() {
-                                quote! { #cstor }
-                            } else {
-                                quote! { rsexp::list(&[#cstor, #(#fields),*]) }
-                            };
+                    syn::Fields::Named(f) => {
+                        let args = f.named.iter().map(|field| field.ident.as_ref().unwrap());
+                        let sexp = if variant.fields.is_empty() {
+                            quote! { #cstor }
+                        } else {
+                            match named_fields_sexp_of(
+                                f,
+                                Some(cstor),
+                                false,
+                                |name| quote! { #name },
+                            ) {
+                                Ok(sexp) => sexp,
+                                Err(err) => return err.to_compile_error().into(),
+                            }
+                        };
                         (quote! { { #(#args),* } }, sexp)
                     }
                     syn::Fields::Unnamed(FieldsUnnamed { unnamed, .. }) => {
+                        let fields_attrs = match positional_fields_attrs(unnamed.iter()) {
+                            Ok(fields_attrs) => fields_attrs,
+                            Err(err) => return err.to_compile_error().into(),
+                        };
                         let num_fields = unnamed.len();
                         let args = (0..num_fields).map(|index| format_ident!("arg{}", index));
-                        let fields = args.clone().map(|arg| quote! { #arg.sexp_of() });
-                        let sexp =
-                            if num_fields == 0 {
-                                quote! { #cstor }
-                            } else {
-                                quote! { rsexp::list(&[#cstor, #(#fields),*]) }
-                            };
+                        let fields = args
+                            .clone()
+                            .zip(fields_attrs.iter())
+                            .map(|(arg, attrs)| attrs.sexp_of(quote! { #arg }));
+                        let sexp = if num_fields == 0 {
+                            quote! { #cstor }
+                        } else {
+                            quote! { rsexp::list(&[#cstor, #(#fields),*]) }
+                        };
                         (quote! { (#(#args),*) }, sexp)
                     }
                     syn::Fields::Unit => (quote! {}, quote! { #cstor }),
                 };
-                quote! {
+                cases.push(quote! {
                     #ident::#variant_ident #pattern => { #sexp }
-                }
-            });
+                });
+            }
             quote! {
                 match self {
                     #(#cases)*
@@ -111,125 +636,507 @@ fn impl_sexp_of(ast: &DeriveInput) -> TokenStream {
     output.into()
 }
 
-#[proc_macro_derive(OfSexp)]
+#[proc_macro_derive(OfSexp, attributes(sexp))]
 pub fn of_sexp_derive(input: TokenStream) -> TokenStream {
-    let ast = syn::parse(input).unwrap();
+    let ast = syn::parse_macro_input!(input as DeriveInput);
     impl_of_sexp(&ast)
 }
 
+// Where the errors go when converting the fields of a struct with named
+// fields: either the first error is returned, or all the errors are pushed to
+// __errors and returned together once all the fields have been converted.
+#[derive(Clone, Copy)]
+struct ErrorSink {
+    all_errors: bool,
+}
+
+impl ErrorSink {
+    // A statement reporting the error.
+    fn report(self, err: proc_macro2::TokenStream) -> proc_macro2::TokenStream {
+        if self.all_errors {
+            quote! { __errors.push(#err) }
+        } else {
+            quote! { return Err(#err) }
+        }
+    }
+
+    // An expression for a field value that could not be converted, the field
+    // values are options when collecting all the errors.
+    fn report_value(self, err: proc_macro2::TokenStream) -> proc_macro2::TokenStream {
+        if self.all_errors {
+            quote! { { __errors.push(#err); None } }
+        } else {
+            quote! { return Err(#err) }
+        }
+    }
+
+    // An expression for a field value that has been converted.
+    fn value(self, value: proc_macro2::TokenStream) -> proc_macro2::TokenStream {
+        if self.all_errors {
+            quote! { Some(#value) }
+        } else {
+            value
+        }
+    }
+
+    // Returns an error that prevents converting any of the fields.
+    fn fail(self, err: proc_macro2::TokenStream) -> proc_macro2::TokenStream {
+        if self.all_errors {
+            quote! { return Err(vec![#err]) }
+        } else {
+            quote! { return Err(#err) }
+        }
+    }
+}
+
 // This assumes that __fields has been defined as a &[Sexp]
 fn impl_named_struct_of_sexp(
     fields_named: &syn::FieldsNamed,
     output_ident: proc_macro2::TokenStream,
-) -> proc_macro2::TokenStream {
-    let named = &fields_named.named;
+    sink: ErrorSink,
+) -> syn::Result<proc_macro2::TokenStream> {
     let ident_str = output_ident.to_string();
-    let fields = named.iter().map(|field| field.ident.as_ref().unwrap());
-    let mk_fields = named.iter().map(|field| {
-        let name = field.ident.as_ref().unwrap();
+    let fields_attrs = named_fields_attrs(fields_named)?;
+    let collect_extra =
+        fields_attrs.iter().find(|(_, attrs)| attrs.collect_extra).map(|(name, _)| name);
+    let flags: Vec<_> =
+        fields_attrs.iter().filter(|(_, attrs)| attrs.flag).map(|(name, _)| name).collect();
+    let flag_strs = flags.iter().map(|name| name.to_string());
+    let flag_indexes = 0..flags.len();
+    let lists: Vec<_> =
+        fields_attrs.iter().filter(|(_, attrs)| attrs.flatten_list).map(|(name, _)| name).collect();
+    let list_strs = lists.iter().map(|name| name.to_string());
+    let mk_fields = fields_attrs
+        .iter()
+        .filter(|(_, attrs)| !attrs.collect_extra && !attrs.flag)
+        .map(|(name, attrs)| {
+            let name_str = name.to_string();
+            let field_err = sink.report_value(quote! { err.with_field(#ident_str, #name_str) });
+            let missing = sink.report_value(quote! {
+                rsexp::IntoSexpError::MissingFieldsInStruct { type_: #ident_str, field: #name_str }
+            });
+            let value = sink.value(quote! { value });
+            if attrs.flatten_list {
+                let index = lists.iter().position(|l| *l == name).unwrap();
+                let empty = sink.value(quote! { Vec::new() });
+                quote! {
+                    let #name = match __lists[#index] {
+                        Some(values) => {
+                            let values = values.iter().enumerate().map(|(i, v)| {
+                                rsexp::OfSexp::of_sexp(v).map_err(|e| e.with_list_index(i))
+                            });
+                            match values.collect::<Result<_, rsexp::IntoSexpError>>() {
+                                Ok(value) => #value,
+                                Err(err) => #field_err,
+                            }
+                        }
+                        None => #empty,
+                    };
+                }
+            } else if attrs.option {
+                let none = sink.value(quote! { None });
+                let value = sink.value(quote! { Some(value) });
+                quote! {
+                    let #name = match __map.remove(#name_str.as_bytes()) {
+                        Some(sexp) => match rsexp::OfSexp::of_sexp(sexp) {
+                            Ok(value) => #value,
+                            Err(err) => #field_err,
+                        },
+                        None => #none,
+                    };
+                }
+            } else {
+                let of_sexp = attrs.of_sexp(quote! { sexp });
+                quote! {
+                    let #name = match __map.remove(#name_str.as_bytes()) {
+                        Some(sexp) => match #of_sexp {
+                            Ok(value) => #value,
+                            Err(err) => #field_err,
+                        },
+                        None => #missing,
+                    };
+                }
+            }
+        });
+    let extra_fields = match collect_extra {
+        Some(name) => {
+            let name_str = name.to_string();
+            let report_key = sink.report(quote! { map_err(err) });
+            let report_value = sink.report(quote! { map_err(err) });
+            let value = sink.value(quote! { __extra.into_iter().collect() });
+            // The remaining pairs are converted in the order in which they
+            // appear in the input, so that the errors are reported in this
+            // order too.
+            quote! {
+                let mut __extra = Vec::with_capacity(__map.len());
+                for __field in __fields.iter() {
+                    let key = match __field {
+                        rsexp::Sexp::List(kv) => match kv.as_slice() {
+                            [rsexp::Sexp::Atom(key), _] => key,
+                            _ => continue,
+                        },
+                        rsexp::Sexp::Atom(_) => continue,
+                    };
+                    let value = match __map.remove(key.as_slice()) {
+                        Some(value) => value,
+                        None => continue,
+                    };
+                    let key_sexp = rsexp::Sexp::Atom(key.to_vec());
+                    let map_err = |e: rsexp::IntoSexpError| {
+                        e.with_map_key(&key_sexp).with_field(#ident_str, #name_str)
+                    };
+                    let key = rsexp::OfSexp::of_sexp(&key_sexp);
+                    match (key, rsexp::OfSexp::of_sexp(value)) {
+                        (Ok(key), Ok(value)) => __extra.push((key, value)),
+                        (key, value) => {
+                            if let Err(err) = key {
+                                #report_key;
+                            }
+                            if let Err(err) = value {
+                                #report_value;
+                            }
+                        }
+                    }
+                }
+                let #name = #value;
+            }
+        }
+        None => {
+            let report = sink.report(quote! {
+                rsexp::IntoSexpError::ExtraFieldsInStruct { type_: #ident_str, extra_fields }
+            });
+            quote! {
+                if !__map.is_empty() {
+                    let mut extra_fields: Vec<_> = __map.into_keys().map(|x| String::from_utf8_lossy(x).to_string()).collect();
+                    extra_fields.sort();
+                    #report;
+                }
+            }
+        }
+    };
+    let fields_types = fields_named.named.iter().map(|field| &field.ty);
+    let validations = fields_attrs.iter().zip(fields_types).filter_map(|((name, attrs), ty)| {
+        let validate = attrs.validate.as_ref()?;
         let name_str = name.to_string();
+        let report = sink.report(quote! {
+            rsexp::IntoSexpError::ValidationFailed { type_: #ident_str, field: #name_str, msg }
+        });
+        let validation = quote! {
+            let __value: &#ty = __value;
+            if let Err(msg) = #validate(__value) {
+                #report;
+            }
+        };
+        if attrs.flag || !sink.all_errors {
+            Some(quote! { { let __value = &#name; #validation } })
+        } else {
+            // The field value is None when it could not be converted.
+            Some(quote! { if let Some(__value) = #name.as_ref() { #validation } })
+        }
+    });
+    let fields = fields_attrs.iter().map(|(name, attrs)| {
+        if attrs.flag || !sink.all_errors {
+            quote! { #name }
+        } else {
+            quote! { #name: #name.unwrap() }
+        }
+    });
+    let extract_map_err = sink.fail(quote! { err });
+    let extract_map = if flags.is_empty() && lists.is_empty() {
+        quote! {
+            let mut __map: std::collections::HashMap<&[u8], &rsexp::Sexp> =
+                match rsexp::Sexp::extract_map(__fields, #ident_str) {
+                    Ok(map) => map,
+                    Err(err) => #extract_map_err,
+                };
+        }
+    } else {
         quote! {
-            let #name = match __map.remove(#name_str.as_bytes()) {
-                Some(sexp) => rsexp::OfSexp::of_sexp(sexp)?,
-                None => return Err(rsexp::IntoSexpError::MissingFieldsInStruct {
-                    type_: #ident_str,
-                    field: #name_str,
-                })
+            let (mut __map, __flags, __lists): (
+                std::collections::HashMap<&[u8], &rsexp::Sexp>,
+                Vec<bool>,
+                Vec<Option<&[rsexp::Sexp]>>,
+            ) = match rsexp::Sexp::extract_map_flags_and_lists(
+                __fields,
+                &[#(#flag_strs),*],
+                &[#(#list_strs),*],
+                #ident_str,
+            ) {
+                Ok(map_flags_and_lists) => map_flags_and_lists,
+                Err(err) => #extract_map_err,
             };
+            #(let #flags = __flags[#flag_indexes];)*
         }
-    });
-    quote! {
-        let mut __map: std::collections::HashMap<&[u8], &rsexp::Sexp> = rsexp::Sexp::extract_map(__fields, #ident_str)?;
+    };
+    let (init_errors, check_errors) = if sink.all_errors {
+        (
+            quote! { let mut __errors: Vec<rsexp::IntoSexpError> = Vec::new(); },
+            quote! {
+                if !__errors.is_empty() {
+                    return Err(__errors);
+                }
+            },
+        )
+    } else {
+        (quote! {}, quote! {})
+    };
+    Ok(quote! {
+        #init_errors
+        #extract_map
         #(#mk_fields)*
-        if !__map.is_empty() {
-            let mut extra_fields: Vec<_> = __map.into_keys().map(|x| String::from_utf8_lossy(x).to_string()).collect();
-            extra_fields.sort();
-            return Err(rsexp::IntoSexpError::ExtraFieldsInStruct {
-                type_: #ident_str,
-                extra_fields,
-            })
-        }
+        #extra_fields
+        #(#validations)*
+        #check_errors
         Ok(#output_ident { #(#fields),* })
-    }
+    })
 }
 
-fn impl_unnamed_struct_of_sexp(
-    fields_unnamed: &syn::FieldsUnnamed,
+// Converts a list of values to the fields of a positional struct, tuple struct,
+// or tuple variant. Fields with a default value can be missing at the end of
+// the list.
+// This assumes that __fields has been defined as a &[Sexp]
+fn impl_positional_of_sexp(
+    fields: &syn::Fields,
     output_ident: proc_macro2::TokenStream,
-) -> proc_macro2::TokenStream {
-    let unnamed = &fields_unnamed.unnamed;
+) -> syn::Result<proc_macro2::TokenStream> {
     let ident_str = output_ident.to_string();
-
-    let num_fields = unnamed.len();
-    let fields = (0..num_fields).map(|index| format_ident!("__field{}", index));
-    let fields_ = fields.clone();
-    let fields_list = quote! { #(rsexp::OfSexp::of_sexp(#fields)?),*};
-    quote! {
-        match __fields {
-            [#(#fields_,)*] => Ok(#output_ident(#fields_list)),
-            l => Err(rsexp::IntoSexpError::ListLengthMismatch {
+    let fields_attrs = positional_fields_attrs(fields.iter())?;
+    let num_fields = fields_attrs.len();
+    let num_required = fields_attrs.iter().filter(|attrs| !attrs.default).count();
+    let values = (0..num_fields).map(|index| format_ident!("__field{}", index));
+    let mk_fields = fields.iter().zip(values.clone()).enumerate().map(|(index, (field, value))| {
+        let map_err = match &field.ident {
+            Some(name) => {
+                let name_str = name.to_string();
+                quote! { .map_err(|e| e.with_field(#ident_str, #name_str)) }
+            }
+            None => quote! { .map_err(|e| e.with_index(#index)) },
+        };
+        let attrs = &fields_attrs[index];
+        if attrs.default {
+            let of_sexp = attrs.of_sexp(quote! { sexp });
+            quote! {
+                let #value = match __fields.get(#index) {
+                    Some(sexp) => #of_sexp #map_err?,
+                    None => Default::default(),
+                };
+            }
+        } else {
+            let of_sexp = attrs.of_sexp(quote! { &__fields[#index] });
+            quote! { let #value = #of_sexp #map_err?; }
+        }
+    });
+    let output = match fields {
+        syn::Fields::Named(f) => {
+            let names = f.named.iter().map(|field| field.ident.as_ref().unwrap());
+            quote! { #output_ident { #(#names: #values),* } }
+        }
+        _ => quote! { #output_ident(#(#values),*) },
+    };
+    let too_short = if num_required == 0 {
+        quote! { false }
+    } else {
+        quote! { __fields.len() < #num_required }
+    };
+    Ok(quote! {
+        if #too_short || __fields.len() > #num_fields {
+            return Err(rsexp::IntoSexpError::ListLengthMismatch {
                 type_: #ident_str,
                 expected_len: #num_fields,
-                list_len: l.len(),
-            }),
+                list_len: __fields.len(),
+            });
         }
-    }
+        #(#mk_fields)*
+        Ok(#output)
+    })
 }
+
 fn impl_of_sexp(ast: &DeriveInput) -> TokenStream {
     let DeriveInput { ident, data, generics, .. } = ast;
+    let container_attrs = match ContainerAttrs::of_input(ast) {
+        Ok(attrs) => attrs,
+        Err(err) => return err.to_compile_error().into(),
+    };
     let ident_str = ident.to_string();
     let mut generics = generics.clone();
     for param in &mut generics.params {
         if let GenericParam::Type(type_param) = param {
-            type_param.bounds.push(parse_quote!(OfSexp))
+            type_param.bounds.push(parse_quote!(rsexp::OfSexp))
         }
     }
     let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
 
+    let mut all_errors_fn = None;
     let of_sexp_fn = match data {
+        syn::Data::Struct(s) if container_attrs.transparent => {
+            // The errors are the ones of the field type, without any context.
+            let value = quote! { rsexp::OfSexp::of_sexp(__s)? };
+            match &s.fields.iter().next().unwrap().ident {
+                Some(name) => quote! { Ok(#ident { #name: #value }) },
+                None => quote! { Ok(#ident(#value)) },
+            }
+        }
         syn::Data::Struct(s) => match &s.fields {
-            syn::Fields::Named(f) => {
-                let result = impl_named_struct_of_sexp(f, quote! {#ident});
+            syn::Fields::Named(f) if !container_attrs.positional => {
+                let sink = ErrorSink { all_errors: false };
+                let result = match impl_named_struct_of_sexp(f, quote! {#ident}, sink) {
+                    Ok(result) => result,
+                    Err(err) => return err.to_compile_error().into(),
+                };
+                let flat_kv_pairs = |map_err| {
+                    if container_attrs.flat_kv {
+                        quote! {
+                            if __fields.len() % 2 != 0 {
+                                let __err = rsexp::IntoSexpError::ExpectedEvenLengthForFlatKv {
+                                    type_: #ident_str,
+                                    list_len: __fields.len(),
+                                };
+                                return Err(#map_err);
+                            }
+                            let __pairs: Vec<rsexp::Sexp> =
+                                __fields.chunks(2).map(|kv| rsexp::Sexp::List(kv.to_vec())).collect();
+                            let __fields = __pairs.as_slice();
+                        }
+                    } else {
+                        quote! {}
+                    }
+                };
+                let flat_kv_pairs_all_errors = flat_kv_pairs(quote! { vec![__err] });
+                let flat_kv_pairs = flat_kv_pairs(quote! { __err });
+                if container_attrs.all_errors {
+                    let sink = ErrorSink { all_errors: true };
+                    let all_errors = match impl_named_struct_of_sexp(f, quote! {#ident}, sink) {
+                        Ok(all_errors) => all_errors,
+                        Err(err) => return err.to_compile_error().into(),
+                    };
+                    all_errors_fn = Some(quote! {
+                        impl #impl_generics #ident #ty_generics #where_clause {
+                            /// Converts a sexp to this struct, returning all the errors related to
+                            /// the struct fields rather than only the first one.
+                            pub fn of_sexp_all_errors(
+                                __s: &rsexp::Sexp,
+                            ) -> std::result::Result<Self, Vec<rsexp::IntoSexpError>> {
+                                let __fields = __s.extract_list(#ident_str).map_err(|err| vec![err])?;
+                                #flat_kv_pairs_all_errors
+                                #all_errors
+                            }
+                        }
+                    });
+                }
                 quote! {
                     let __fields = __s.extract_list(#ident_str)?;
+                    #flat_kv_pairs
                     #result
                 }
             }
-            syn::Fields::Unnamed(f) => {
-                let result = impl_unnamed_struct_of_sexp(f, quote! {#ident});
+            syn::Fields::Named(_) | syn::Fields::Unnamed(_) => {
+                let result = match impl_positional_of_sexp(&s.fields, quote! {#ident}) {
+                    Ok(result) => result,
+                    Err(err) => return err.to_compile_error().into(),
+                };
                 quote! {
                     let __fields = __s.extract_list(#ident_str)?;
                     #result
                 }
             }
-            syn::Fields::Unit => quote! {#ident},
+            syn::Fields::Unit => return unit_struct_error(ident),
         },
+        syn::Data::Enum(DataEnum { variants, .. }) if container_attrs.by_index => {
+            for variant in variants.iter() {
+                match VariantAttrs::of_variant(variant) {
+                    Ok(attrs) if attrs.aliases.is_empty() && attrs.rename.is_none() => {}
+                    Ok(_) => {
+                        let msg = "alias and rename cannot be used with by_index";
+                        return syn::Error::new_spanned(variant, msg).to_compile_error().into();
+                    }
+                    Err(err) => return err.to_compile_error().into(),
+                }
+            }
+            let cases = variants.iter().enumerate().map(|(index, variant)| {
+                let variant_ident = &variant.ident;
+                quote! { #index => Ok(#ident::#variant_ident), }
+            });
+            quote! {
+                let __index: usize = rsexp::OfSexp::of_sexp(__s)?;
+                match __index {
+                    #(#cases)*
+                    _ => Err(rsexp::IntoSexpError::UnknownConstructorForEnum {
+                        type_: #ident_str,
+                        constructor: __index.to_string(),
+                    }),
+                }
+            }
+        }
         syn::Data::Enum(DataEnum { variants, .. }) => {
-            let cases = variants.iter().map(|variant| {
+            let mut cases = vec![];
+            // The constructors and aliases already used, two variants sharing a
+            // constructor would make the second one unreachable.
+            let mut constructors = std::collections::HashSet::new();
+            for variant in variants.iter() {
                 let variant_ident = &variant.ident;
-                let variant_bytes = syn::LitByteStr::new(
-                    variant_ident.to_string().as_bytes(),
-                    variant_ident.span(),
-                );
+                let variant_attrs = match VariantAttrs::of_variant(variant) {
+                    Ok(attrs) => attrs,
+                    Err(err) => return err.to_compile_error().into(),
+                };
+                let variant_bytes = variant_attrs.constructor(variant);
+                let aliases: Vec<_> = variant_attrs
+                    .aliases
+                    .iter()
+                    .map(|alias| syn::LitByteStr::new(alias.value().as_bytes(), alias.span()))
+                    .collect();
+                for constructor in std::iter::once(&variant_bytes).chain(aliases.iter()) {
+                    if !constructors.insert(constructor.value()) {
+                        let msg = format!(
+                            "duplicate constructor \"{}\"",
+                            String::from_utf8_lossy(&constructor.value())
+                        );
+                        return syn::Error::new(constructor.span(), msg).to_compile_error().into();
+                    }
+                }
                 let branch = match &variant.fields {
                     syn::Fields::Named(f) => {
-                        impl_named_struct_of_sexp(f, quote! {#ident::#variant_ident})
+                        let sink = ErrorSink { all_errors: false };
+                        match impl_named_struct_of_sexp(f, quote! {#ident::#variant_ident}, sink) {
+                            Ok(branch) => branch,
+                            Err(err) => return err.to_compile_error().into(),
+                        }
                     }
-                    syn::Fields::Unnamed(f) => {
-                        impl_unnamed_struct_of_sexp(f, quote! {#ident::#variant_ident})
+                    syn::Fields::Unnamed(_) => {
+                        match impl_positional_of_sexp(
+                            &variant.fields,
+                            quote! {#ident::#variant_ident},
+                        ) {
+                            Ok(branch) => branch,
+                            Err(err) => return err.to_compile_error().into(),
+                        }
                     }
                     syn::Fields::Unit => {
-                        quote! {::core::result::Result::Ok(#ident::#variant_ident)}
+                        let variant_str = quote! {#ident::#variant_ident}.to_string();
+                        quote! {
+                            if !__fields.is_empty() {
+                                return Err(rsexp::IntoSexpError::ListLengthMismatch {
+                                    type_: #variant_str,
+                                    expected_len: 0,
+                                    list_len: __fields.len(),
+                                });
+                            }
+                            ::core::result::Result::Ok(#ident::#variant_ident)
+                        }
                     }
                 };
-                quote! {
-                    (#variant_bytes, __fields) => {
+                cases.push(quote! {
+                    (#variant_bytes #(| #aliases)*, __fields) => {
                         #branch
                     }
-                }
-            });
+                });
+            }
+            let extract_enum = if container_attrs.wrapped_variants {
+                quote! { extract_enum_wrapped }
+            } else {
+                quote! { extract_enum }
+            };
             quote! {
-            match __s.extract_enum(#ident_str)? {
+            match __s.#extract_enum(#ident_str)? {
                 #(#cases)*
                 (ctor, _) =>
                     Err(rsexp::IntoSexpError::UnknownConstructorForEnum {
@@ -252,6 +1159,8 @@ fn impl_of_sexp(ast: &DeriveInput) -> TokenStream {
                 #of_sexp_fn
             }
         }
+
+        #all_errors_fn
     };
 
     output.into()
@@ -0,0 +1,40 @@
+// Random generation of sexps for property based testing with quickcheck.
+use crate::Sexp;
+use quickcheck::{Arbitrary, Gen};
+
+// The maximum nesting depth of the generated lists.
+const MAX_DEPTH: usize = 4;
+
+fn arbitrary_(g: &mut Gen, max_depth: usize) -> Sexp {
+    if max_depth == 0 || bool::arbitrary(g) {
+        // Mix short alphabetic atoms with arbitrary bytes, the latter often
+        // require quoting and escaping.
+        let atom = if bool::arbitrary(g) {
+            let len = 1 + usize::arbitrary(g) % 10;
+            (0..len).map(|_| b'a' + u8::arbitrary(g) % 26).collect()
+        } else {
+            Vec::<u8>::arbitrary(g)
+        };
+        Sexp::Atom(atom)
+    } else {
+        let len = usize::arbitrary(g) % 10;
+        Sexp::List((0..len).map(|_| arbitrary_(g, max_depth - 1)).collect())
+    }
+}
+
+impl Arbitrary for Sexp {
+    fn arbitrary(g: &mut Gen) -> Self {
+        arbitrary_(g, MAX_DEPTH)
+    }
+
+    /// Atoms shrink as byte vectors. Lists shrink to each of their elements,
+    /// then to shorter lists or lists with a shrunk element.
+    fn shrink(&self) -> Box<dyn Iterator<Item = Self>> {
+        match self {
+            Sexp::Atom(atom) => Box::new(atom.shrink().map(Sexp::Atom)),
+            Sexp::List(list) => {
+                Box::new(list.clone().into_iter().chain(list.shrink().map(Sexp::List)))
+            }
+        }
+    }
+}
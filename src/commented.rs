@@ -0,0 +1,132 @@
+// Sexps keeping the line comments of the parsed input, so that a file can be
+// edited programmatically and written back without losing its comments.
+use crate::io::{self, Write};
+use crate::{needs_escaping, write_escaped, write_u8, Sexp};
+use alloc::vec::Vec;
+
+/// A sexp together with the line comments that precede it, see
+/// `from_slice_with_comments`. Each comment includes its leading `;` but not
+/// the end of line.
+#[derive(Debug, PartialEq, Eq, Clone, Hash)]
+pub struct CommentedSexp {
+    pub comments: Vec<Vec<u8>>,
+    pub value: CommentedValue,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Hash)]
+pub enum CommentedValue {
+    Atom(Vec<u8>),
+    /// The elements of the list and the line comments that precede the
+    /// closing parenthesis.
+    List {
+        elems: Vec<CommentedSexp>,
+        trailing_comments: Vec<Vec<u8>>,
+    },
+}
+
+impl CommentedValue {
+    // Whether some comments appear within this value.
+    fn has_comments(&self) -> bool {
+        match self {
+            CommentedValue::Atom(_) => false,
+            CommentedValue::List { elems, trailing_comments } => {
+                !trailing_comments.is_empty()
+                    || elems.iter().any(|s| !s.comments.is_empty() || s.value.has_comments())
+            }
+        }
+    }
+}
+
+impl CommentedSexp {
+    /// Converts to a `Sexp`, dropping the comments.
+    pub fn to_sexp(&self) -> Sexp {
+        match &self.value {
+            CommentedValue::Atom(atom) => Sexp::Atom(atom.clone()),
+            CommentedValue::List { elems, .. } => {
+                Sexp::List(elems.iter().map(|s| s.to_sexp()).collect())
+            }
+        }
+    }
+
+    /// Serialize to a writer in a human readable way, including the comments.
+    /// Each comment is written on its own line before the commented value,
+    /// the lists containing comments have one element per line. Other lists
+    /// are written on a single line.
+    ///
+    /// # Example
+    ///
+    /// ```
+    ///     let input = b";; settings\n((host localhost)\n ;; default port\n (port 8080))";
+    ///     let sexp = rsexp::from_slice_with_comments(input).unwrap();
+    ///     let mut buffer = Vec::new();
+    ///     sexp.write_hum_with_comments(&mut buffer).unwrap();
+    ///     assert_eq!(buffer, input);
+    /// ```
+    pub fn write_hum_with_comments<W: Write>(&self, w: &mut W) -> io::Result<()> {
+        fn newline<W: Write>(indent_level: usize, w: &mut W) -> io::Result<()> {
+            write_u8(b'\n', w)?;
+            for _i in 0..indent_level {
+                write_u8(b' ', w)?;
+            }
+            Ok(())
+        }
+
+        fn write_loop<W: Write>(
+            s: &CommentedSexp,
+            indent_level: usize,
+            w: &mut W,
+        ) -> io::Result<()> {
+            for comment in s.comments.iter() {
+                w.write_all(comment)?;
+                newline(indent_level, w)?;
+            }
+            match &s.value {
+                CommentedValue::Atom(atom) if needs_escaping(atom) => write_escaped(atom, w),
+                CommentedValue::Atom(atom) => w.write_all(atom),
+                CommentedValue::List { .. } if !s.value.has_comments() => s.to_sexp().write(w),
+                CommentedValue::List { elems, trailing_comments } => {
+                    write_u8(b'(', w)?;
+                    for (index, elem) in elems.iter().enumerate() {
+                        if index > 0 {
+                            newline(indent_level + 1, w)?;
+                        }
+                        write_loop(elem, indent_level + 1, w)?;
+                    }
+                    for (index, comment) in trailing_comments.iter().enumerate() {
+                        if index > 0 || !elems.is_empty() {
+                            newline(indent_level + 1, w)?;
+                        }
+                        w.write_all(comment)?;
+                    }
+                    if !trailing_comments.is_empty() {
+                        newline(indent_level + 1, w)?;
+                    }
+                    write_u8(b')', w)
+                }
+            }
+        }
+        write_loop(self, 0, w)
+    }
+
+    /// Serialize to a buffer in a human readable way, including the comments,
+    /// see `write_hum_with_comments`.
+    pub fn to_bytes_hum_with_comments(&self) -> Vec<u8> {
+        let mut buffer = Vec::new();
+        // This could not fail as the buffer gets extended.
+        self.write_hum_with_comments(&mut buffer).unwrap();
+        buffer
+    }
+}
+
+impl From<Sexp> for CommentedSexp {
+    fn from(sexp: Sexp) -> Self {
+        let value = match sexp {
+            Sexp::Atom(atom) => CommentedValue::Atom(atom),
+            Sexp::List(list) => CommentedValue::List {
+                elems: list.into_iter().map(CommentedSexp::from).collect(),
+                trailing_comments: Vec::new(),
+            },
+        };
+        CommentedSexp { comments: Vec::new(), value }
+    }
+}
@@ -0,0 +1,362 @@
+// Conversion from Sexp to types implementing serde::Deserialize, the expected
+// sexps follow the same conventions as the OfSexp deriver.
+use crate::{IntoSexpError, Sexp};
+use serde::de::{self, DeserializeOwned, Visitor};
+
+/// Errors that could be generated when deserializing a value from a Sexp.
+#[derive(Debug, PartialEq, Eq)]
+pub enum DeError {
+    Custom(String),
+    IntoSexpError(IntoSexpError),
+}
+
+impl std::fmt::Display for DeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{self:?}")
+    }
+}
+
+impl std::error::Error for DeError {}
+
+impl de::Error for DeError {
+    fn custom<T: std::fmt::Display>(msg: T) -> Self {
+        DeError::Custom(msg.to_string())
+    }
+}
+
+impl From<IntoSexpError> for DeError {
+    fn from(e: IntoSexpError) -> Self {
+        DeError::IntoSexpError(e)
+    }
+}
+
+impl From<std::str::Utf8Error> for DeError {
+    fn from(e: std::str::Utf8Error) -> Self {
+        DeError::IntoSexpError(e.into())
+    }
+}
+
+/// Converts a Sexp to a value implementing `serde::Deserialize`. Structs are
+/// read from lists of `(field value)` pairs, options use the empty list and
+/// single element list, and enum variants use their constructor name, similar
+/// to the `OfSexp` deriver.
+///
+/// # Example
+///
+/// ```
+///     let sexp = rsexp::from_slice(b"(1 foo ((2.5)))").unwrap();
+///     let value: (i32, String, Option<Vec<f64>>) = rsexp::from_sexp(&sexp).unwrap();
+///     assert_eq!(value, (1, "foo".to_string(), Some(vec![2.5])));
+/// ```
+pub fn from_sexp<T: DeserializeOwned>(s: &Sexp) -> Result<T, DeError> {
+    T::deserialize(Deserializer { sexp: s })
+}
+
+struct Deserializer<'de> {
+    sexp: &'de Sexp,
+}
+
+impl<'de> Deserializer<'de> {
+    fn atom_str(&self, type_: &'static str) -> Result<&'de str, DeError> {
+        let atom = self.sexp.extract_atom(type_)?;
+        Ok(std::str::from_utf8(atom)?)
+    }
+
+    fn parse<T>(&self, type_: &'static str) -> Result<T, DeError>
+    where
+        T: std::str::FromStr,
+        T::Err: std::fmt::Display,
+    {
+        self.atom_str(type_)?.parse().map_err(|err: T::Err| {
+            let err = format!("{err}");
+            IntoSexpError::StringConversionError { err }.into()
+        })
+    }
+
+    fn list(&self, type_: &'static str, expected_len: usize) -> Result<&'de [Sexp], DeError> {
+        let list = self.sexp.extract_list(type_)?;
+        if list.len() != expected_len {
+            let list_len = list.len();
+            return Err(IntoSexpError::ListLengthMismatch { type_, expected_len, list_len }.into());
+        }
+        Ok(list)
+    }
+}
+
+macro_rules! deserialize_parse {
+    ($($fn_name:ident $visit:ident)*) => {
+        $(
+            fn $fn_name<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, DeError> {
+                visitor.$visit(self.parse("stringable")?)
+            }
+        )*
+    };
+}
+
+impl<'de> de::Deserializer<'de> for Deserializer<'de> {
+    type Error = DeError;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, DeError> {
+        match self.sexp {
+            Sexp::Atom(atom) => match std::str::from_utf8(atom) {
+                Ok(str) => visitor.visit_borrowed_str(str),
+                Err(_) => visitor.visit_borrowed_bytes(atom),
+            },
+            Sexp::List(list) => visitor.visit_seq(SeqAccess { iter: list.iter() }),
+        }
+    }
+
+    deserialize_parse! {
+        deserialize_bool visit_bool
+        deserialize_i8 visit_i8
+        deserialize_i16 visit_i16
+        deserialize_i32 visit_i32
+        deserialize_i64 visit_i64
+        deserialize_i128 visit_i128
+        deserialize_u8 visit_u8
+        deserialize_u16 visit_u16
+        deserialize_u32 visit_u32
+        deserialize_u64 visit_u64
+        deserialize_u128 visit_u128
+        deserialize_f32 visit_f32
+        deserialize_f64 visit_f64
+        deserialize_char visit_char
+    }
+
+    fn deserialize_str<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, DeError> {
+        visitor.visit_borrowed_str(self.atom_str("String")?)
+    }
+
+    fn deserialize_string<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, DeError> {
+        self.deserialize_str(visitor)
+    }
+
+    fn deserialize_bytes<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, DeError> {
+        visitor.visit_borrowed_bytes(self.sexp.extract_atom("bytes")?)
+    }
+
+    fn deserialize_byte_buf<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, DeError> {
+        self.deserialize_bytes(visitor)
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, DeError> {
+        match self.sexp.extract_list("option")? {
+            [] => visitor.visit_none(),
+            [sexp] => visitor.visit_some(Deserializer { sexp }),
+            list => Err(IntoSexpError::ListLengthMismatch {
+                type_: "option",
+                expected_len: 1,
+                list_len: list.len(),
+            }
+            .into()),
+        }
+    }
+
+    fn deserialize_unit<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, DeError> {
+        self.list("()", 0)?;
+        visitor.visit_unit()
+    }
+
+    fn deserialize_unit_struct<V: Visitor<'de>>(
+        self,
+        name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, DeError> {
+        self.list(name, 0)?;
+        visitor.visit_unit()
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, DeError> {
+        let list = self.list(name, 1)?;
+        visitor.visit_newtype_struct(Deserializer { sexp: &list[0] })
+    }
+
+    fn deserialize_seq<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, DeError> {
+        let list = self.sexp.extract_list("seq")?;
+        visitor.visit_seq(SeqAccess { iter: list.iter() })
+    }
+
+    fn deserialize_tuple<V: Visitor<'de>>(
+        self,
+        len: usize,
+        visitor: V,
+    ) -> Result<V::Value, DeError> {
+        let list = self.list("tuple", len)?;
+        visitor.visit_seq(SeqAccess { iter: list.iter() })
+    }
+
+    fn deserialize_tuple_struct<V: Visitor<'de>>(
+        self,
+        name: &'static str,
+        len: usize,
+        visitor: V,
+    ) -> Result<V::Value, DeError> {
+        let list = self.list(name, len)?;
+        visitor.visit_seq(SeqAccess { iter: list.iter() })
+    }
+
+    fn deserialize_map<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, DeError> {
+        let list = self.sexp.extract_list("map")?;
+        visitor.visit_map(MapAccess { type_: "map", iter: list.iter(), value: None })
+    }
+
+    fn deserialize_struct<V: Visitor<'de>>(
+        self,
+        name: &'static str,
+        _fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, DeError> {
+        let list = self.sexp.extract_list(name)?;
+        visitor.visit_map(MapAccess { type_: name, iter: list.iter(), value: None })
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, DeError> {
+        let (constructor, args) = self.sexp.extract_enum(name)?;
+        visitor.visit_enum(EnumAccess { type_: name, constructor, args })
+    }
+
+    fn deserialize_identifier<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, DeError> {
+        visitor.visit_borrowed_str(self.atom_str("identifier")?)
+    }
+
+    fn deserialize_ignored_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, DeError> {
+        visitor.visit_unit()
+    }
+}
+
+struct SeqAccess<'de> {
+    iter: std::slice::Iter<'de, Sexp>,
+}
+
+impl<'de> de::SeqAccess<'de> for SeqAccess<'de> {
+    type Error = DeError;
+
+    fn next_element_seed<T: de::DeserializeSeed<'de>>(
+        &mut self,
+        seed: T,
+    ) -> Result<Option<T::Value>, DeError> {
+        match self.iter.next() {
+            None => Ok(None),
+            Some(sexp) => seed.deserialize(Deserializer { sexp }).map(Some),
+        }
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.iter.len())
+    }
+}
+
+// Iterates over a list of (key value) pairs.
+struct MapAccess<'de> {
+    type_: &'static str,
+    iter: std::slice::Iter<'de, Sexp>,
+    value: Option<&'de Sexp>,
+}
+
+impl<'de> de::MapAccess<'de> for MapAccess<'de> {
+    type Error = DeError;
+
+    fn next_key_seed<K: de::DeserializeSeed<'de>>(
+        &mut self,
+        seed: K,
+    ) -> Result<Option<K::Value>, DeError> {
+        let type_ = self.type_;
+        match self.iter.next() {
+            None => Ok(None),
+            Some(Sexp::Atom(_)) => Err(IntoSexpError::ExpectedPairForMapGotAtom { type_ }.into()),
+            Some(Sexp::List(list)) => match list.as_slice() {
+                [key, value] => {
+                    self.value = Some(value);
+                    seed.deserialize(Deserializer { sexp: key }).map(Some)
+                }
+                list => {
+                    let list_len = list.len();
+                    Err(IntoSexpError::ExpectedPairForMapGotList { type_, list_len }.into())
+                }
+            },
+        }
+    }
+
+    fn next_value_seed<V: de::DeserializeSeed<'de>>(
+        &mut self,
+        seed: V,
+    ) -> Result<V::Value, DeError> {
+        match self.value.take() {
+            None => Err(de::Error::custom("next_value_seed called before next_key_seed")),
+            Some(sexp) => seed.deserialize(Deserializer { sexp }),
+        }
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.iter.len())
+    }
+}
+
+struct EnumAccess<'de> {
+    type_: &'static str,
+    constructor: &'de [u8],
+    args: &'de [Sexp],
+}
+
+impl<'de> de::EnumAccess<'de> for EnumAccess<'de> {
+    type Error = DeError;
+    type Variant = Self;
+
+    fn variant_seed<V: de::DeserializeSeed<'de>>(
+        self,
+        seed: V,
+    ) -> Result<(V::Value, Self), DeError> {
+        let constructor = std::str::from_utf8(self.constructor)?;
+        let constructor = de::value::BorrowedStrDeserializer::<DeError>::new(constructor);
+        Ok((seed.deserialize(constructor)?, self))
+    }
+}
+
+impl<'de> EnumAccess<'de> {
+    fn args(&self, expected_len: usize) -> Result<&'de [Sexp], DeError> {
+        if self.args.len() != expected_len {
+            let (type_, list_len) = (self.type_, self.args.len());
+            return Err(IntoSexpError::ListLengthMismatch { type_, expected_len, list_len }.into());
+        }
+        Ok(self.args)
+    }
+}
+
+impl<'de> de::VariantAccess<'de> for EnumAccess<'de> {
+    type Error = DeError;
+
+    fn unit_variant(self) -> Result<(), DeError> {
+        self.args(0)?;
+        Ok(())
+    }
+
+    fn newtype_variant_seed<T: de::DeserializeSeed<'de>>(
+        self,
+        seed: T,
+    ) -> Result<T::Value, DeError> {
+        let args = self.args(1)?;
+        seed.deserialize(Deserializer { sexp: &args[0] })
+    }
+
+    fn tuple_variant<V: Visitor<'de>>(self, len: usize, visitor: V) -> Result<V::Value, DeError> {
+        let args = self.args(len)?;
+        visitor.visit_seq(SeqAccess { iter: args.iter() })
+    }
+
+    fn struct_variant<V: Visitor<'de>>(
+        self,
+        _fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, DeError> {
+        visitor.visit_map(MapAccess { type_: self.type_, iter: self.args.iter(), value: None })
+    }
+}
@@ -0,0 +1,53 @@
+// Sexps sharing their identical atoms, this is a separate type as the atoms
+// of Sexp own their bytes.
+use crate::Sexp;
+use alloc::collections::BTreeMap;
+use alloc::rc::Rc;
+use alloc::vec::Vec;
+
+/// Type for S-expressions where identical atoms share the same buffer, see
+/// `Sexp::intern`. This reduces memory usage for trees with many duplicate
+/// atoms, e.g. the keys of a list of records.
+#[derive(Debug, PartialEq, Eq, Clone, Hash)]
+pub enum InternedSexp {
+    Atom(Rc<[u8]>),
+    List(Vec<InternedSexp>),
+}
+
+impl InternedSexp {
+    /// Converts to a `Sexp`, copying the atoms.
+    pub fn to_sexp(&self) -> Sexp {
+        match self {
+            InternedSexp::Atom(atom) => Sexp::Atom(atom.to_vec()),
+            InternedSexp::List(list) => Sexp::List(list.iter().map(|s| s.to_sexp()).collect()),
+        }
+    }
+}
+
+impl Sexp {
+    /// Rebuilds this sexp so that all the identical atoms share a single
+    /// reference counted buffer.
+    ///
+    /// # Example
+    ///
+    /// ```
+    ///     let sexp = rsexp::from_slice(b"((name foo) (name bar))").unwrap();
+    ///     let interned = sexp.intern();
+    ///     assert_eq!(interned.to_sexp(), sexp);
+    /// ```
+    pub fn intern(&self) -> InternedSexp {
+        fn intern_loop<'a>(s: &'a Sexp, atoms: &mut BTreeMap<&'a [u8], Rc<[u8]>>) -> InternedSexp {
+            match s {
+                Sexp::Atom(atom) => {
+                    let atom =
+                        atoms.entry(atom.as_slice()).or_insert_with(|| atom.as_slice().into());
+                    InternedSexp::Atom(atom.clone())
+                }
+                Sexp::List(list) => {
+                    InternedSexp::List(list.iter().map(|s| intern_loop(s, atoms)).collect())
+                }
+            }
+        }
+        intern_loop(self, &mut BTreeMap::new())
+    }
+}
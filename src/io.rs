@@ -0,0 +1,68 @@
+// The sink used when serializing sexps. With the `std` feature this is
+// `std::io::Write`, otherwise a minimal trait with the same methods is used so
+// that the serialization code is shared.
+#[cfg(feature = "std")]
+pub use std::io::{Error, Result, Write};
+
+#[cfg(not(feature = "std"))]
+use alloc::vec::Vec;
+
+/// Errors returned by `Write` when the `std` feature is disabled.
+#[cfg(not(feature = "std"))]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Error {
+    /// The sink did not accept any more bytes.
+    WriteZero,
+}
+
+#[cfg(not(feature = "std"))]
+impl core::fmt::Display for Error {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        write!(f, "{self:?}")
+    }
+}
+
+#[cfg(not(feature = "std"))]
+pub type Result<T> = core::result::Result<T, Error>;
+
+/// A byte sink, this mirrors the subset of `std::io::Write` used by the
+/// serialization functions.
+#[cfg(not(feature = "std"))]
+pub trait Write {
+    fn write(&mut self, buf: &[u8]) -> Result<usize>;
+
+    fn flush(&mut self) -> Result<()>;
+
+    fn write_all(&mut self, mut buf: &[u8]) -> Result<()> {
+        while !buf.is_empty() {
+            match self.write(buf)? {
+                0 => return Err(Error::WriteZero),
+                n => buf = &buf[n..],
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(not(feature = "std"))]
+impl Write for Vec<u8> {
+    fn write(&mut self, buf: &[u8]) -> Result<usize> {
+        self.extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        Ok(())
+    }
+}
+
+#[cfg(not(feature = "std"))]
+impl<W: Write + ?Sized> Write for &mut W {
+    fn write(&mut self, buf: &[u8]) -> Result<usize> {
+        (**self).write(buf)
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        (**self).flush()
+    }
+}
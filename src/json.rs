@@ -0,0 +1,74 @@
+// Conversions between sexps and serde_json values.
+use crate::{IntoSexpError, Sexp};
+use serde_json::{Map, Number, Value};
+
+/// Objects are converted to lists of `(key value)` pairs, arrays to lists,
+/// `null` to `()`, and the other values to atoms.
+impl From<Value> for Sexp {
+    fn from(value: Value) -> Self {
+        match value {
+            Value::Null => Sexp::List(vec![]),
+            Value::Bool(b) => Sexp::Atom(b.to_string().into_bytes()),
+            Value::Number(n) => Sexp::Atom(n.to_string().into_bytes()),
+            Value::String(s) => Sexp::Atom(s.into_bytes()),
+            Value::Array(values) => Sexp::List(values.into_iter().map(Sexp::from).collect()),
+            Value::Object(map) => Sexp::List(
+                map.into_iter()
+                    .map(|(k, v)| Sexp::List(vec![Sexp::Atom(k.into_bytes()), Sexp::from(v)]))
+                    .collect(),
+            ),
+        }
+    }
+}
+
+// Whether a sexp is a (key value) pair.
+fn is_field(sexp: &Sexp) -> bool {
+    matches!(sexp.as_list(), Some([Sexp::Atom(_), _]))
+}
+
+/// Reverses the conversion from JSON values, failing with
+/// `IntoSexpError::FromUtf8Error` on atoms that are not valid UTF-8. As all
+/// the scalars are stored as atoms, their type is guessed which makes the
+/// round-trip lossy in some cases:
+/// - `true` and `false` become booleans and the atoms that are valid JSON
+///   numbers become numbers, so the strings `"true"` or `"42"` come back as a
+///   boolean or a number.
+/// - `null`, empty arrays, and empty objects are all converted to `()`, which
+///   comes back as an empty array.
+/// - Non-empty lists whose elements are all `(key value)` pairs come back as
+///   objects, including arrays such as `[["a", 1]]`. Duplicate keys keep the
+///   last value.
+impl TryFrom<Sexp> for Value {
+    type Error = IntoSexpError;
+
+    fn try_from(sexp: Sexp) -> Result<Self, Self::Error> {
+        match sexp {
+            Sexp::Atom(atom) => {
+                let s = String::from_utf8(atom)?;
+                let value = match s.as_str() {
+                    "true" => Value::Bool(true),
+                    "false" => Value::Bool(false),
+                    _ => match s.parse::<Number>() {
+                        Ok(n) => Value::Number(n),
+                        Err(_) => Value::String(s),
+                    },
+                };
+                Ok(value)
+            }
+            Sexp::List(list) if !list.is_empty() && list.iter().all(is_field) => {
+                let mut map = Map::new();
+                for elem in list {
+                    if let Sexp::List(pair) = elem {
+                        if let Ok([Sexp::Atom(key), value]) = <[Sexp; 2]>::try_from(pair) {
+                            map.insert(String::from_utf8(key)?, Value::try_from(value)?);
+                        }
+                    }
+                }
+                Ok(Value::Object(map))
+            }
+            Sexp::List(list) => {
+                Ok(Value::Array(list.into_iter().map(Value::try_from).collect::<Result<_, _>>()?))
+            }
+        }
+    }
+}
@@ -1,21 +1,114 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+extern crate alloc;
+
+#[cfg(feature = "quickcheck")]
+mod arbitrary;
+mod commented;
+#[cfg(feature = "serde")]
+mod de;
+mod intern;
+pub mod io;
+#[cfg(feature = "json")]
+mod json;
 mod of_sexp;
 mod parse;
+#[cfg(feature = "proptest")]
+mod proptest_strategy;
+#[cfg(feature = "std")]
+mod reader;
+#[cfg(feature = "serde")]
+mod ser;
 mod sexp_of;
+mod tree;
 
+pub use commented::*;
+#[cfg(feature = "serde")]
+pub use de::*;
+pub use intern::*;
 pub use of_sexp::*;
 pub use parse::*;
+#[cfg(feature = "proptest")]
+pub use proptest_strategy::*;
+#[cfg(feature = "std")]
+pub use reader::*;
+#[cfg(feature = "serde")]
+pub use ser::*;
 pub use sexp_of::*;
-use std::io::Write;
+pub use tree::*;
+
+use alloc::borrow::Cow;
+use alloc::string::{String, ToString};
+use alloc::{format, vec, vec::Vec};
+use io::Write;
 
 const MAX_LINE_WIDTH: usize = 90;
 
+/// Options controlling the layout of the human readable serialization.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct HumConfig {
+    /// Lists that would make a line longer than this are split over multiple
+    /// lines.
+    pub max_line_width: usize,
+    /// The visual width of one indentation level, used to compute the line
+    /// widths. This should match the width at which `indent_str` is displayed.
+    pub indent: usize,
+    /// The bytes written for each nesting level when splitting lists, e.g.
+    /// two spaces or a tab.
+    pub indent_str: Vec<u8>,
+    /// Whether a new line is written after the sexp, disabled by default.
+    pub trailing_newline: bool,
+}
+
+impl Default for HumConfig {
+    fn default() -> Self {
+        HumConfig {
+            max_line_width: MAX_LINE_WIDTH,
+            indent: 1,
+            indent_str: b" ".to_vec(),
+            trailing_newline: false,
+        }
+    }
+}
+
+/// Options controlling the machine readable serialization.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct MachConfig {
+    /// Whether the association lists are sorted by key, see
+    /// `Sexp::write_mach_sorted`, disabled by default.
+    pub sort_alists: bool,
+    /// Whether a new line is written after the sexp, disabled by default.
+    pub trailing_newline: bool,
+}
+
 /// Type for S-expressions using owned values.
-#[derive(Debug, PartialEq, Eq, Clone, Hash)]
+///
+/// Sexps are ordered with atoms before lists, atoms are compared by their
+/// bytes and lists are compared lexicographically element by element.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Hash)]
 pub enum Sexp {
     Atom(Vec<u8>),
     List(Vec<Sexp>),
 }
 
+/// Type for S-expressions borrowing their atoms from the parsed input when
+/// possible. Quoted atoms that contain escape sequences and atoms rewritten by
+/// the parser are owned.
+#[derive(Debug, PartialEq, Eq, Clone, Hash)]
+pub enum SexpRef<'a> {
+    Atom(Cow<'a, [u8]>),
+    List(Vec<SexpRef<'a>>),
+}
+
+impl SexpRef<'_> {
+    /// Converts to an owned `Sexp`, copying the borrowed atoms.
+    pub fn to_owned(&self) -> Sexp {
+        match self {
+            SexpRef::Atom(atom) => Sexp::Atom(atom.to_vec()),
+            SexpRef::List(list) => Sexp::List(list.iter().map(|s| s.to_owned()).collect()),
+        }
+    }
+}
+
 pub fn atom(atom: &[u8]) -> Sexp {
     Sexp::Atom(atom.to_vec())
 }
@@ -24,30 +117,241 @@ pub fn list(list: &[Sexp]) -> Sexp {
     Sexp::List(list.to_vec())
 }
 
-// This trait is used to mark types for which using the to/from string
-// conversion is fine.
+/// Builds an atom from the `Display` representation of a value.
+///
+/// # Example
+///
+/// ```
+///     let sexp = rsexp::atom_from_display(&std::net::Ipv4Addr::LOCALHOST);
+///     assert_eq!(sexp, rsexp::atom(b"127.0.0.1"));
+/// ```
+pub fn atom_from_display<T: core::fmt::Display + ?Sized>(v: &T) -> Sexp {
+    Sexp::Atom(v.to_string().into_bytes())
+}
+
+/// Builds a list from an iterator without collecting it to a slice first.
+///
+/// # Example
+///
+/// ```
+///     let sexp = rsexp::list_from_iter((1..4).map(|i| rsexp::atom_from_display(&i)));
+///     assert_eq!(sexp.to_bytes(), b"(1 2 3)");
+/// ```
+pub fn list_from_iter<I: IntoIterator<Item = Sexp>>(i: I) -> Sexp {
+    Sexp::List(i.into_iter().collect())
+}
+
+/// The radix used by `#x1F`, `#o17`, and `#b101` style integer atoms.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash)]
+pub enum Radix {
+    Binary,
+    Octal,
+    Hexadecimal,
+}
+
+impl Radix {
+    /// The character following `#` for this radix.
+    pub fn prefix(self) -> u8 {
+        match self {
+            Radix::Binary => b'b',
+            Radix::Octal => b'o',
+            Radix::Hexadecimal => b'x',
+        }
+    }
+
+    /// Returns the radix associated with the character following `#`, if any.
+    pub fn of_prefix(c: u8) -> Option<Self> {
+        match c {
+            b'b' | b'B' => Some(Radix::Binary),
+            b'o' | b'O' => Some(Radix::Octal),
+            b'x' | b'X' => Some(Radix::Hexadecimal),
+            _ => None,
+        }
+    }
+
+    pub fn base(self) -> u32 {
+        match self {
+            Radix::Binary => 2,
+            Radix::Octal => 8,
+            Radix::Hexadecimal => 16,
+        }
+    }
+}
+
+/// Builds an atom using the radix-prefixed notation, e.g. `#x1F` for 31 in
+/// hexadecimal. Such atoms can be read back as decimal integers by setting
+/// `ParseOptions::radix_atoms`.
+pub fn radix_atom(value: i128, radix: Radix) -> Sexp {
+    let sign = if value < 0 { "-" } else { "" };
+    let value = value.unsigned_abs();
+    let digits = match radix {
+        Radix::Binary => format!("{value:b}"),
+        Radix::Octal => format!("{value:o}"),
+        Radix::Hexadecimal => format!("{value:X}"),
+    };
+    atom(format!("#{}{sign}{digits}", radix.prefix() as char).as_bytes())
+}
+
+/// How NaN values are spelled by `float_atom`.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash, Default)]
+pub enum NanSpelling {
+    /// `NaN`, as used by the `SexpOf` implementation for floats.
+    #[default]
+    NaN,
+    /// `nan`
+    Lowercase,
+}
+
+/// How infinite values are spelled by `float_atom`, negative infinity always
+/// uses a leading `-`.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash, Default)]
+pub enum InfSpelling {
+    /// `inf`, as used by the `SexpOf` implementation for floats.
+    #[default]
+    Inf,
+    /// `Infinity`
+    Infinity,
+    /// `+inf`
+    PlusInf,
+}
+
+/// The spellings of the special float values used by `float_atom`.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash, Default)]
+pub struct FloatSpelling {
+    pub nan: NanSpelling,
+    pub inf: InfSpelling,
+}
+
+/// Builds an atom for a float using the given spellings for NaN and infinite
+/// values, other values are formatted as by `SexpOf`. All these spellings are
+/// accepted when converting atoms back to floats.
+///
+/// # Example
+///
+/// ```
+///     use rsexp::{FloatSpelling, InfSpelling, NanSpelling};
+///     let spelling = FloatSpelling { nan: NanSpelling::Lowercase, inf: InfSpelling::PlusInf };
+///     assert_eq!(rsexp::float_atom(f64::NAN, spelling), rsexp::atom(b"nan"));
+///     assert_eq!(rsexp::float_atom(f64::INFINITY, spelling), rsexp::atom(b"+inf"));
+///     assert_eq!(rsexp::float_atom(1.5, spelling), rsexp::atom(b"1.5"));
+/// ```
+pub fn float_atom(value: f64, spelling: FloatSpelling) -> Sexp {
+    if value.is_nan() {
+        match spelling.nan {
+            NanSpelling::NaN => atom(b"NaN"),
+            NanSpelling::Lowercase => atom(b"nan"),
+        }
+    } else if value.is_infinite() {
+        let inf = match (spelling.inf, value > 0.) {
+            (InfSpelling::Inf, true) => "inf",
+            (InfSpelling::Infinity, true) => "Infinity",
+            (InfSpelling::PlusInf, true) => "+inf",
+            (InfSpelling::Inf | InfSpelling::PlusInf, false) => "-inf",
+            (InfSpelling::Infinity, false) => "-Infinity",
+        };
+        atom(inf.as_bytes())
+    } else {
+        atom(value.to_string().as_bytes())
+    }
+}
+
+/// Marks the types converted to and from atoms using their `ToString` and
+/// `FromStr` implementations, `SexpOf` and `OfSexp` are then provided by
+/// blanket implementations. A type that needs another representation should
+/// not implement this marker and implement `SexpOf` and `OfSexp` directly
+/// instead, this does not conflict with the blanket implementations even if
+/// the type implements `Display` and `FromStr`.
+///
+/// # Example
+///
+/// ```
+///     #[derive(Debug, PartialEq)]
+///     struct Celsius(f64);
+///     impl std::fmt::Display for Celsius {
+///         fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+///             write!(f, "{}C", self.0)
+///         }
+///     }
+///     impl std::str::FromStr for Celsius {
+///         type Err = std::num::ParseFloatError;
+///         fn from_str(s: &str) -> Result<Self, Self::Err> {
+///             Ok(Celsius(s.trim_end_matches('C').parse()?))
+///         }
+///     }
+///     impl rsexp::UseToString for Celsius {}
+///
+///     use rsexp::SexpOf;
+///     let sexp = Celsius(21.5).sexp_of();
+///     assert_eq!(sexp.to_string(), "21.5C");
+///     assert_eq!(sexp.of_sexp(), Ok(Celsius(21.5)));
+/// ```
 pub trait UseToString {}
 
 pub struct BytesSlice<'a>(pub &'a [u8]);
 
+/// A boolean using the Emacs Lisp representation: `t` for true and `nil` for
+/// false. The empty list `()` is also accepted for false when converting from
+/// a Sexp.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash, Default)]
+pub struct ElispBool(pub bool);
+
 // Conversion from T to sexp.
 
+impl UseToString for u128 {}
 impl UseToString for u64 {}
 impl UseToString for u32 {}
 impl UseToString for u16 {}
 impl UseToString for u8 {}
+impl UseToString for i128 {}
 impl UseToString for i64 {}
 impl UseToString for i32 {}
 impl UseToString for i16 {}
 impl UseToString for i8 {}
 impl UseToString for usize {}
+// The float formatting uses the shortest representation that converts back
+// to the same value, the NaN and infinite spellings are those of float_atom.
 impl UseToString for f64 {}
 impl UseToString for f32 {}
 impl UseToString for bool {}
+impl UseToString for core::num::NonZeroU64 {}
+impl UseToString for core::num::NonZeroU32 {}
+impl UseToString for core::num::NonZeroU16 {}
+impl UseToString for core::num::NonZeroU8 {}
+impl UseToString for core::num::NonZeroI64 {}
+impl UseToString for core::num::NonZeroI32 {}
+impl UseToString for core::num::NonZeroI16 {}
+impl UseToString for core::num::NonZeroI8 {}
+impl UseToString for core::num::NonZeroUsize {}
+// The string representation of decimals preserves their scale, e.g. 1.50.
+#[cfg(feature = "decimal")]
+impl UseToString for rust_decimal::Decimal {}
 
 // Serialization
 
-fn must_escape(data: &[u8]) -> bool {
+/// Returns whether an atom is written as a quoted string by `Sexp::write` and
+/// `Sexp::to_bytes`, this is the same as `needs_escaping`.
+pub fn would_quote(atom: &[u8]) -> bool {
+    needs_escaping(atom)
+}
+
+/// Returns whether an atom has to be escaped with `escape_atom` to be parsed
+/// back, these are the atoms written as quoted strings by `Sexp::write` and
+/// `Sexp::to_bytes`. The rules are the same as the OCaml sexplib ones so that
+/// only atoms that could not be parsed back otherwise are quoted: empty atoms,
+/// atoms containing whitespace, control or non-ASCII bytes, one of `"();\`,
+/// or the block comment delimiters `#|` and `|#`. These delimiters are
+/// rejected by the parser in unquoted atoms, quoting them is what makes the
+/// serialization round-trip.
+///
+/// # Example
+///
+/// ```
+///     assert!(!rsexp::needs_escaping(b"foo-bar#1|2"));
+///     assert!(rsexp::needs_escaping(b"foo bar"));
+///     assert!(rsexp::needs_escaping(b"a|#b"));
+///     assert!(rsexp::needs_escaping(b""));
+/// ```
+pub fn needs_escaping(data: &[u8]) -> bool {
     if data.is_empty() {
         return true;
     }
@@ -62,11 +366,51 @@ fn must_escape(data: &[u8]) -> bool {
     false
 }
 
-fn write_u8<W: Write>(b: u8, w: &mut W) -> std::io::Result<()> {
+// Whether an atom matches the numeric grammar, i.e. an optional sign followed
+// by a decimal integer or float with an optional exponent (`42`, `-3.14`,
+// `.5`, `1e-3`), or by a `0x`/`0o`/`0b` prefixed integer.
+fn looks_like_number(data: &[u8]) -> bool {
+    // Returns the number of leading digits and the remaining bytes.
+    fn digits(data: &[u8], radix: u32) -> (usize, &[u8]) {
+        let len = data.iter().take_while(|&&c| (c as char).is_digit(radix)).count();
+        (len, &data[len..])
+    }
+    fn strip_sign(data: &[u8]) -> &[u8] {
+        match data {
+            [b'+' | b'-', rest @ ..] => rest,
+            data => data,
+        }
+    }
+    let data = strip_sign(data);
+    if let [b'0', prefix, rest @ ..] = data {
+        if let Some(radix) = Radix::of_prefix(*prefix) {
+            let (len, rest) = digits(rest, radix.base());
+            return len > 0 && rest.is_empty();
+        }
+    }
+    let (int_len, data) = digits(data, 10);
+    let (frac_len, data) = match data {
+        [b'.', rest @ ..] => digits(rest, 10),
+        data => (0, data),
+    };
+    if int_len + frac_len == 0 {
+        return false;
+    }
+    match data {
+        [] => true,
+        [b'e' | b'E', rest @ ..] => {
+            let (exp_len, rest) = digits(strip_sign(rest), 10);
+            exp_len > 0 && rest.is_empty()
+        }
+        _ => false,
+    }
+}
+
+fn write_u8<W: Write>(b: u8, w: &mut W) -> io::Result<()> {
     w.write_all(&[b])
 }
 
-fn write_escaped<W: Write>(data: &[u8], w: &mut W) -> std::io::Result<()> {
+fn write_escaped<W: Write>(data: &[u8], w: &mut W) -> io::Result<()> {
     write_u8(b'"', w)?;
     for &c in data.iter() {
         match c {
@@ -83,32 +427,375 @@ fn write_escaped<W: Write>(data: &[u8], w: &mut W) -> std::io::Result<()> {
     Ok(())
 }
 
+/// Escapes an atom as a quoted string, including the surrounding quotes, in
+/// the same way as atoms are quoted when serializing a sexp. Non-printable
+/// bytes use the decimal `\123` form. The result can be decoded back with
+/// `unescape_atom`.
+///
+/// # Example
+///
+/// ```
+///     assert_eq!(rsexp::escape_atom(b"foo bar\n"), b"\"foo bar\\n\"");
+///     assert_eq!(rsexp::escape_atom(b"\x00\xff"), b"\"\\000\\255\"");
+/// ```
+pub fn escape_atom(data: &[u8]) -> Vec<u8> {
+    let mut buffer = Vec::with_capacity(escaped_len(data));
+    // This could not fail as the buffer gets extended.
+    write_escaped(data, &mut buffer).unwrap();
+    buffer
+}
+
+// The number of bytes written by write_escaped.
+fn escaped_len(data: &[u8]) -> usize {
+    let len: usize = data
+        .iter()
+        .map(|&c| match c {
+            b'\\' | b'\"' | b'\n' | b'\t' | b'\r' | 8 => 2,
+            b' '..=b'~' => 1,
+            _ => 4,
+        })
+        .sum();
+    len + 2
+}
+
 impl Sexp {
-    /// Serialize a Sexp to a writer.
-    pub fn write<W: Write>(&self, w: &mut W) -> std::io::Result<()> {
-        match self {
-            Sexp::Atom(v) => {
-                if must_escape(v) {
+    // Serialize a Sexp to a writer, atoms for which `quote` returns true are
+    // written as escaped strings.
+    fn write_quoting<W: Write>(&self, quote: fn(&[u8]) -> bool, w: &mut W) -> io::Result<()> {
+        self.write_with_atom_fmt(
+            |v, w| {
+                if quote(v) {
                     write_escaped(v, w)
                 } else {
                     w.write_all(v)
                 }
+            },
+            w,
+        )
+    }
+
+    /// Serialize a Sexp to a writer, atoms are written by calling `f` rather
+    /// than being escaped when needed. Lists are written as by `write`, with
+    /// a single space between elements. The traversal uses an explicit stack
+    /// rather than recursion so deeply nested sexps cannot overflow the call
+    /// stack.
+    ///
+    /// # Example
+    ///
+    /// ```
+    ///     use std::io::Write;
+    ///     let sexp = rsexp::from_slice(b"(foo (\"bar baz\"))").unwrap();
+    ///     let mut buffer = Vec::new();
+    ///     sexp.write_with_atom_fmt(|a, w| write!(w, "{}:{}", a.len(), a[0] as char), &mut buffer)
+    ///         .unwrap();
+    ///     assert_eq!(buffer, b"(3:f (7:b))");
+    /// ```
+    pub fn write_with_atom_fmt<W, F>(&self, mut f: F, w: &mut W) -> io::Result<()>
+    where
+        W: Write,
+        F: FnMut(&[u8], &mut W) -> io::Result<()>,
+    {
+        // Each frame holds the remaining elements of a list and whether an
+        // element has already been written, the bottom frame only contains
+        // the root.
+        let mut stack = vec![(core::slice::from_ref(self).iter(), false)];
+        while let Some((iter, started)) = stack.last_mut() {
+            match iter.next() {
+                Some(sexp) => {
+                    if *started {
+                        write_u8(b' ', w)?;
+                    }
+                    *started = true;
+                    match sexp {
+                        Sexp::Atom(v) => f(v, w)?,
+                        Sexp::List(vec) => {
+                            write_u8(b'(', w)?;
+                            stack.push((vec.iter(), false));
+                        }
+                    }
+                }
+                None => {
+                    stack.pop();
+                    if !stack.is_empty() {
+                        write_u8(b')', w)?;
+                    }
+                }
             }
-            Sexp::List(vec) => {
-                write_u8(b'(', w)?;
-                for (index, elem) in vec.iter().enumerate() {
+        }
+        Ok(())
+    }
+
+    /// Serialize a Sexp to a writer.
+    pub fn write<W: Write>(&self, w: &mut W) -> io::Result<()> {
+        self.write_quoting(needs_escaping, w)
+    }
+
+    /// Serialize a Sexp to a writer, quoting atoms that look like numbers
+    /// such as `42`, `-3.14`, `1e10`, or `0x1F`, so that a string atom `"42"`
+    /// can be told apart from an integer by a typed reader.
+    ///
+    /// # Example
+    ///
+    /// ```
+    ///     let sexp = rsexp::from_slice(b"(42 3.14 foo)").unwrap();
+    ///     let mut buffer = Vec::new();
+    ///     sexp.write_quoting_numbers(&mut buffer).unwrap();
+    ///     assert_eq!(buffer, b"(\"42\" \"3.14\" foo)");
+    /// ```
+    pub fn write_quoting_numbers<W: Write>(&self, w: &mut W) -> io::Result<()> {
+        self.write_quoting(|v| needs_escaping(v) || looks_like_number(v), w)
+    }
+
+    /// Returns the bytes of an atom, or `None` for a list.
+    ///
+    /// # Example
+    ///
+    /// ```
+    ///     assert_eq!(rsexp::atom(b"foo").as_atom(), Some(b"foo".as_slice()));
+    ///     assert_eq!(rsexp::list(&[]).as_atom(), None);
+    /// ```
+    pub fn as_atom(&self) -> Option<&[u8]> {
+        match self {
+            Sexp::Atom(atom) => Some(atom),
+            Sexp::List(_) => None,
+        }
+    }
+
+    /// Returns the elements of a list, or `None` for an atom.
+    ///
+    /// # Example
+    ///
+    /// ```
+    ///     let sexp = rsexp::from_slice(b"(foo bar)").unwrap();
+    ///     assert_eq!(sexp.as_list().map(|l| l.len()), Some(2));
+    ///     assert_eq!(rsexp::atom(b"foo").as_list(), None);
+    /// ```
+    pub fn as_list(&self) -> Option<&[Sexp]> {
+        match self {
+            Sexp::Atom(_) => None,
+            Sexp::List(list) => Some(list),
+        }
+    }
+
+    /// Returns whether the sexp is an atom.
+    ///
+    /// # Example
+    ///
+    /// ```
+    ///     assert!(rsexp::atom(b"foo").is_atom());
+    ///     assert!(!rsexp::list(&[]).is_atom());
+    /// ```
+    pub fn is_atom(&self) -> bool {
+        matches!(self, Sexp::Atom(_))
+    }
+
+    /// Returns whether the sexp is a list.
+    ///
+    /// # Example
+    ///
+    /// ```
+    ///     assert!(rsexp::list(&[]).is_list());
+    ///     assert!(!rsexp::atom(b"foo").is_list());
+    /// ```
+    pub fn is_list(&self) -> bool {
+        matches!(self, Sexp::List(_))
+    }
+
+    /// Returns the number of bytes of an atom, or the number of elements of a
+    /// list. Nested lists are not traversed.
+    ///
+    /// # Example
+    ///
+    /// ```
+    ///     assert_eq!(rsexp::atom(b"foo").len(), 3);
+    ///     assert_eq!(rsexp::from_slice(b"(foo (bar baz))").unwrap().len(), 2);
+    /// ```
+    pub fn len(&self) -> usize {
+        match self {
+            Sexp::Atom(atom) => atom.len(),
+            Sexp::List(list) => list.len(),
+        }
+    }
+
+    /// Returns whether the sexp is an empty atom or an empty list.
+    ///
+    /// # Example
+    ///
+    /// ```
+    ///     assert!(rsexp::atom(b"").is_empty());
+    ///     assert!(rsexp::list(&[]).is_empty());
+    ///     assert!(!rsexp::list(&[rsexp::atom(b"")]).is_empty());
+    /// ```
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns an iterator over the elements of a list, the iterator is empty
+    /// for atoms.
+    ///
+    /// # Example
+    ///
+    /// ```
+    ///     let sexp = rsexp::from_slice(b"(foo (bar baz))").unwrap();
+    ///     assert_eq!(sexp.iter().map(|s| s.len()).collect::<Vec<_>>(), [3, 2]);
+    ///     assert_eq!(rsexp::atom(b"foo").iter().count(), 0);
+    /// ```
+    pub fn iter(&self) -> core::slice::Iter<'_, Sexp> {
+        match self {
+            Sexp::Atom(_) => [].iter(),
+            Sexp::List(list) => list.iter(),
+        }
+    }
+
+    /// Returns the element at index `idx` of a list, or `None` if `self` is an
+    /// atom or if the index is out of bounds.
+    ///
+    /// # Example
+    ///
+    /// ```
+    ///     let sexp = rsexp::from_slice(b"(foo (bar baz))").unwrap();
+    ///     assert_eq!(sexp.get(1).and_then(|s| s.get(0)), Some(&rsexp::atom(b"bar")));
+    ///     assert_eq!(sexp.get(2), None);
+    /// ```
+    pub fn get(&self, idx: usize) -> Option<&Sexp> {
+        self.as_list()?.get(idx)
+    }
+
+    /// Returns the element found by following the list indexes of `path`, or
+    /// `None` if some index is out of bounds or applies to an atom. The empty
+    /// path designates the sexp itself.
+    ///
+    /// # Example
+    ///
+    /// ```
+    ///     let sexp = rsexp::from_slice(b"((a (b (c 42))))").unwrap();
+    ///     assert_eq!(sexp.get_path(&[0, 1, 1, 1]), Some(&rsexp::atom(b"42")));
+    ///     assert_eq!(sexp.get_path(&[0, 2]), None);
+    /// ```
+    pub fn get_path(&self, path: &[usize]) -> Option<&Sexp> {
+        path.iter().try_fold(self, |sexp, &idx| sexp.get(idx))
+    }
+
+    /// Returns the content of an atom as a string, or `None` for a list or for
+    /// an atom that is not valid UTF-8.
+    ///
+    /// # Example
+    ///
+    /// ```
+    ///     assert_eq!(rsexp::atom(b"foo").atom_str(), Some("foo"));
+    ///     assert_eq!(rsexp::atom(b"\xff").atom_str(), None);
+    /// ```
+    pub fn atom_str(&self) -> Option<&str> {
+        core::str::from_utf8(self.as_atom()?).ok()
+    }
+
+    /// Joins the bytes of the children of a list using `sep` as separator.
+    /// This returns `None` if `self` is an atom or if any of the children is
+    /// a list.
+    ///
+    /// # Example
+    ///
+    /// ```
+    ///     let sexp = rsexp::from_slice(b"(hello world)").unwrap();
+    ///     assert_eq!(sexp.join_atoms(b" "), Some(b"hello world".to_vec()));
+    /// ```
+    pub fn join_atoms(&self, sep: &[u8]) -> Option<Vec<u8>> {
+        let mut res = Vec::new();
+        match self {
+            Sexp::Atom(_) => return None,
+            Sexp::List(list) => {
+                for (index, elem) in list.iter().enumerate() {
                     if index > 0 {
-                        write_u8(b' ', w)?;
+                        res.extend_from_slice(sep)
+                    }
+                    match elem {
+                        Sexp::Atom(atom) => res.extend_from_slice(atom),
+                        Sexp::List(_) => return None,
                     }
-                    elem.write(w)?;
                 }
-                write_u8(b')', w)
             }
         }
+        Some(res)
+    }
+
+    /// Returns a copy of the sexp where each atom is replaced by the result of
+    /// applying `f` to its bytes, atoms are visited from left to right. The
+    /// traversal uses an explicit stack rather than recursion so deeply
+    /// nested inputs cannot overflow the call stack.
+    ///
+    /// # Example
+    ///
+    /// ```
+    ///     let sexp = rsexp::from_slice(b"((user foo) (groups (a b)))").unwrap();
+    ///     let sexp = sexp.map_atoms(|a| a.to_ascii_uppercase());
+    ///     assert_eq!(sexp.to_bytes(), b"((USER FOO) (GROUPS (A B)))");
+    /// ```
+    pub fn map_atoms<F: FnMut(&[u8]) -> Vec<u8>>(&self, mut f: F) -> Sexp {
+        // Each frame holds the remaining children of a list and the already
+        // mapped ones, the bottom frame has the root as single child.
+        let mut stack = vec![(core::slice::from_ref(self).iter(), Vec::new())];
+        loop {
+            let (iter, mapped) = stack.last_mut().unwrap();
+            match iter.next() {
+                Some(Sexp::Atom(atom)) => mapped.push(Sexp::Atom(f(atom))),
+                Some(Sexp::List(list)) => stack.push((list.iter(), Vec::with_capacity(list.len()))),
+                None => {
+                    let (_, mut mapped) = stack.pop().unwrap();
+                    match stack.last_mut() {
+                        Some((_, parent)) => parent.push(Sexp::List(mapped)),
+                        None => return mapped.pop().unwrap(),
+                    }
+                }
+            }
+        }
+    }
+
+    /// Folds `f` over all the nodes of the sexp, lists and atoms, in
+    /// depth-first order with each list being visited before its children.
+    /// The traversal uses an explicit stack rather than recursion so deeply
+    /// nested inputs cannot overflow the call stack.
+    ///
+    /// # Example
+    ///
+    /// ```
+    ///     let sexp = rsexp::from_slice(b"((foo bar) (baz (1 2 3)))").unwrap();
+    ///     let atom_bytes = sexp.fold(0, |acc, s| acc + s.as_atom().map_or(0, |a| a.len()));
+    ///     assert_eq!(atom_bytes, 12);
+    /// ```
+    pub fn fold<B, F: FnMut(B, &Sexp) -> B>(&self, init: B, mut f: F) -> B {
+        let mut acc = init;
+        let mut stack = vec![self];
+        while let Some(sexp) = stack.pop() {
+            acc = f(acc, sexp);
+            if let Sexp::List(list) = sexp {
+                stack.extend(list.iter().rev())
+            }
+        }
+        acc
+    }
+
+    /// Sorts the children of a list using the `compare` function, this does
+    /// nothing on atoms. The sort is stable.
+    ///
+    /// # Example
+    ///
+    /// ```
+    ///     let mut sexp = rsexp::from_slice(b"(b c a)").unwrap();
+    ///     sexp.sort_children_by(|s1, s2| s1.to_bytes().cmp(&s2.to_bytes()));
+    ///     assert_eq!(sexp.to_bytes(), b"(a b c)");
+    /// ```
+    pub fn sort_children_by<F>(&mut self, compare: F)
+    where
+        F: FnMut(&Sexp, &Sexp) -> core::cmp::Ordering,
+    {
+        match self {
+            Sexp::Atom(_) => {}
+            Sexp::List(list) => list.sort_by(compare),
+        }
     }
 
     /// Serialize multiple Sexps to a writer.
-    pub fn write_multi<W: Write>(sexps: &[Self], w: &mut W) -> std::io::Result<()> {
+    pub fn write_multi<W: Write>(sexps: &[Self], w: &mut W) -> io::Result<()> {
         for (index, s) in sexps.iter().enumerate() {
             if index > 0 {
                 write_u8(b' ', w)?
@@ -120,16 +807,59 @@ impl Sexp {
 
     /// Serialize a Sexp to a writer in a machine readable way rather than
     /// human readable. This tries to avoid unnecessary whitespaces.
-    pub fn write_mach<W: Write>(&self, w: &mut W) -> std::io::Result<()> {
+    pub fn write_mach<W: Write>(&self, w: &mut W) -> io::Result<()> {
+        self.write_mach_with_config(&MachConfig::default(), w)
+    }
+
+    /// Serialize a Sexp to a writer in the same way as `write_mach` but with
+    /// the association lists sorted by key, so that the output does not depend
+    /// on the iteration order of a `HashMap`. A list is sorted when all its
+    /// elements are lists of two elements starting with an atom, the sort is
+    /// stable and compares the key atoms bytewise. Other lists are written
+    /// in their original order.
+    ///
+    /// # Example
+    ///
+    /// ```
+    ///     let sexp = rsexp::from_slice(b"((b 1) (a (2 1)) (c ((y 1) (x 2))))").unwrap();
+    ///     let mut buffer = Vec::new();
+    ///     sexp.write_mach_sorted(&mut buffer).unwrap();
+    ///     assert_eq!(buffer, b"((a(2 1))(b 1)(c((x 2)(y 1))))");
+    /// ```
+    pub fn write_mach_sorted<W: Write>(&self, w: &mut W) -> io::Result<()> {
+        self.write_mach_with_config(&MachConfig { sort_alists: true, ..Default::default() }, w)
+    }
+
+    /// Serialize a Sexp to a writer in a machine readable way using the given
+    /// options.
+    ///
+    /// # Example
+    ///
+    /// ```
+    ///     let sexp = rsexp::from_slice(b"((b 1) (a 2))").unwrap();
+    ///     let cfg = rsexp::MachConfig { sort_alists: true, trailing_newline: true };
+    ///     let mut buffer = Vec::new();
+    ///     sexp.write_mach_with_config(&cfg, &mut buffer).unwrap();
+    ///     assert_eq!(buffer, b"((a 2)(b 1))\n");
+    /// ```
+    pub fn write_mach_with_config<W: Write>(&self, cfg: &MachConfig, w: &mut W) -> io::Result<()> {
+        // Returns the key of an association list element.
+        fn alist_key(s: &Sexp) -> Option<&[u8]> {
+            match s {
+                Sexp::List(l) if l.len() == 2 => l[0].as_atom(),
+                _ => None,
+            }
+        }
         // The returned bool mentions whether a white space could be required.
         fn write_loop<W: Write>(
             s: &Sexp,
+            sort_alists: bool,
             need_whitespace: bool,
             w: &mut W,
-        ) -> std::io::Result<bool> {
+        ) -> io::Result<bool> {
             match s {
                 Sexp::Atom(v) => {
-                    if must_escape(v) {
+                    if needs_escaping(v) {
                         write_escaped(v, w)?;
                         Ok(false)
                     } else {
@@ -143,20 +873,74 @@ impl Sexp {
                 Sexp::List(vec) => {
                     write_u8(b'(', w)?;
                     let mut need_whitespace = false;
-                    for elem in vec.iter() {
-                        need_whitespace = write_loop(elem, need_whitespace, w)?;
+                    if sort_alists && vec.iter().all(|s| alist_key(s).is_some()) {
+                        let mut sorted: Vec<&Sexp> = vec.iter().collect();
+                        sorted.sort_by_key(|s| alist_key(s));
+                        for elem in sorted {
+                            need_whitespace = write_loop(elem, true, need_whitespace, w)?;
+                        }
+                    } else {
+                        for elem in vec.iter() {
+                            need_whitespace = write_loop(elem, sort_alists, need_whitespace, w)?;
+                        }
                     }
                     write_u8(b')', w)?;
                     Ok(false)
                 }
             }
         }
-        write_loop(self, false, w).map(|_| ())
+        write_loop(self, cfg.sort_alists, false, w)?;
+        if cfg.trailing_newline {
+            write_u8(b'\n', w)?;
+        }
+        Ok(())
+    }
+
+    /// Serialize a Sexp to a writer using the canonical representation from
+    /// Rivest's S-expressions, where each atom is written as its length in
+    /// decimal followed by a colon and the raw bytes, e.g. `(3:foo3:bar)`.
+    /// There is no whitespace nor escaping so that each sexp has a unique
+    /// representation, which makes this suitable for hashing or signing.
+    pub fn write_canonical<W: Write>(&self, w: &mut W) -> io::Result<()> {
+        match self {
+            Sexp::Atom(v) => {
+                w.write_all(format!("{}:", v.len()).as_bytes())?;
+                w.write_all(v)
+            }
+            Sexp::List(l) => {
+                write_u8(b'(', w)?;
+                for s in l.iter() {
+                    s.write_canonical(w)?;
+                }
+                write_u8(b')', w)
+            }
+        }
     }
 
     /// Serialize a Sexp to a writer in a human readable way with some new lines
     /// and indentation.
-    pub fn write_hum<W: Write>(&self, w: &mut W) -> std::io::Result<()> {
+    pub fn write_hum<W: Write>(&self, w: &mut W) -> io::Result<()> {
+        self.write_hum_with_config(&HumConfig::default(), w)
+    }
+
+    /// Serialize a Sexp to a writer in a human readable way using the given
+    /// line width and indentation.
+    ///
+    /// # Example
+    ///
+    /// ```
+    ///     let sexp = rsexp::from_slice(b"(foo (bar baz))").unwrap();
+    ///     let cfg = rsexp::HumConfig {
+    ///         max_line_width: 10,
+    ///         indent: 2,
+    ///         indent_str: b"  ".to_vec(),
+    ///         trailing_newline: true,
+    ///     };
+    ///     let mut buffer = Vec::new();
+    ///     sexp.write_hum_with_config(&cfg, &mut buffer).unwrap();
+    ///     assert_eq!(buffer, b"(foo\n  (bar baz))\n");
+    /// ```
+    pub fn write_hum_with_config<W: Write>(&self, cfg: &HumConfig, w: &mut W) -> io::Result<()> {
         enum EscapedSexpWithSize<'a> {
             AtomRef(&'a [u8]),
             AtomOwned(Vec<u8>),
@@ -171,9 +955,9 @@ impl Sexp {
             }
         }
 
-        fn escape(s: &Sexp) -> EscapedSexpWithSize {
+        fn escape(s: &Sexp) -> EscapedSexpWithSize<'_> {
             match s {
-                Sexp::Atom(a) if must_escape(a) => {
+                Sexp::Atom(a) if needs_escaping(a) => {
                     let mut escaped = Vec::new();
                     write_escaped(a, &mut escaped).unwrap();
                     EscapedSexpWithSize::AtomOwned(escaped)
@@ -194,17 +978,18 @@ impl Sexp {
 
         fn write_loop<W: Write>(
             s: &EscapedSexpWithSize<'_>,
+            cfg: &HumConfig,
             first_elem: bool,
             indent_level: usize,
             already_written_on_line: &mut usize,
             w: &mut W,
-        ) -> std::io::Result<()> {
-            if !first_elem && size(s) + *already_written_on_line > MAX_LINE_WIDTH {
+        ) -> io::Result<()> {
+            if !first_elem && size(s) + *already_written_on_line > cfg.max_line_width {
                 write_u8(b'\n', w)?;
                 for _i in 0..indent_level {
-                    write_u8(b' ', w)?;
+                    w.write_all(&cfg.indent_str)?;
                 }
-                *already_written_on_line = indent_level
+                *already_written_on_line = indent_level * cfg.indent
             } else if !first_elem {
                 *already_written_on_line += 1;
                 write_u8(b' ', w)?;
@@ -222,7 +1007,15 @@ impl Sexp {
                     *already_written_on_line += 1;
                     write_u8(b'(', w)?;
                     for (index, elem) in values.iter().enumerate() {
-                        write_loop(elem, index == 0, indent_level + 1, already_written_on_line, w)?;
+                        let first_elem = index == 0;
+                        write_loop(
+                            elem,
+                            cfg,
+                            first_elem,
+                            indent_level + 1,
+                            already_written_on_line,
+                            w,
+                        )?;
                     }
                     *already_written_on_line += 1;
                     write_u8(b')', w)?;
@@ -231,10 +1024,57 @@ impl Sexp {
             }
         }
         let s = escape(self);
-        write_loop(&s, true, 0, &mut 0, w)
+        write_loop(&s, cfg, true, 0, &mut 0, w)?;
+        if cfg.trailing_newline {
+            write_u8(b'\n', w)?;
+        }
+        Ok(())
     }
 
-    /// Serialize a Sexp to a buffer.
+    /// Returns the length of the longest line that `write_hum_with_config`
+    /// would produce with `wrap_width` as the maximum line width, without
+    /// allocating the output.
+    ///
+    /// # Example
+    ///
+    /// ```
+    ///     let sexp = rsexp::from_slice(b"(foo (bar baz))").unwrap();
+    ///     assert_eq!(sexp.max_line_width_hum(90), 15);
+    ///     assert_eq!(sexp.max_line_width_hum(10), 11);
+    /// ```
+    pub fn max_line_width_hum(&self, wrap_width: usize) -> usize {
+        struct LineWidths {
+            current: usize,
+            max: usize,
+        }
+
+        impl Write for LineWidths {
+            fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+                for &c in buf.iter() {
+                    if c == b'\n' {
+                        self.current = 0
+                    } else {
+                        self.current += 1;
+                        self.max = self.max.max(self.current)
+                    }
+                }
+                Ok(buf.len())
+            }
+
+            fn flush(&mut self) -> io::Result<()> {
+                Ok(())
+            }
+        }
+
+        let cfg = HumConfig { max_line_width: wrap_width, ..Default::default() };
+        let mut widths = LineWidths { current: 0, max: 0 };
+        self.write_hum_with_config(&cfg, &mut widths).unwrap();
+        widths.max
+    }
+
+    /// Serialize a Sexp to a buffer. Any sexp round-trips through this, i.e.
+    /// `from_slice(&s.to_bytes()) == Ok(s)` for all `s`, whatever the bytes
+    /// of its atoms. The same holds for `to_bytes_hum` and `to_bytes_mach`.
     ///
     /// # Example
     ///
@@ -243,12 +1083,30 @@ impl Sexp {
     ///     assert_eq!(sexp.to_bytes(), b"((foo bar) (baz (1 2 3)))");
     /// ```
     pub fn to_bytes(&self) -> Vec<u8> {
-        let mut buffer = Vec::new();
+        let mut buffer = Vec::with_capacity(self.serialized_len());
         // This could not fail as the buffer gets extended.
         self.write(&mut buffer).unwrap();
         buffer
     }
 
+    /// Returns the number of bytes written by `write` or returned by
+    /// `to_bytes`, without serializing the sexp.
+    ///
+    /// # Example
+    ///
+    /// ```
+    ///     let sexp = rsexp::from_slice(b"((foo bar) (\"a b\" ()))").unwrap();
+    ///     assert_eq!(sexp.serialized_len(), sexp.to_bytes().len());
+    /// ```
+    pub fn serialized_len(&self) -> usize {
+        self.fold(0, |len, s| match s {
+            Sexp::Atom(v) if needs_escaping(v) => len + escaped_len(v),
+            Sexp::Atom(v) => len + v.len(),
+            // The parentheses and the spaces between elements.
+            Sexp::List(l) => len + 2 + l.len().saturating_sub(1),
+        })
+    }
+
     /// Serialize multiple Sexps to a buffer.
     ///
     /// # Example
@@ -272,11 +1130,62 @@ impl Sexp {
     ///     assert_eq!(sexp.to_bytes_mach(), b"((foo bar)(baz(1 2 3)))");
     /// ```
     pub fn to_bytes_mach(&self) -> Vec<u8> {
-        let mut buffer = Vec::new();
+        // The machine representation is never longer than the default one.
+        let mut buffer = Vec::with_capacity(self.serialized_len());
         self.write_mach(&mut buffer).unwrap();
         buffer
     }
 
+    /// Serialize a Sexp to a buffer, machine readable version with sorted
+    /// association lists, see `write_mach_sorted`.
+    pub fn to_bytes_mach_sorted(&self) -> Vec<u8> {
+        let mut buffer = Vec::with_capacity(self.serialized_len());
+        self.write_mach_sorted(&mut buffer).unwrap();
+        buffer
+    }
+
+    /// Serialize a Sexp to a buffer, canonical version, see `write_canonical`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    ///     let sexp = rsexp::from_slice(b"(foo (\"a b\" ()))").unwrap();
+    ///     assert_eq!(sexp.to_bytes_canonical(), b"(3:foo(3:a b()))");
+    /// ```
+    pub fn to_bytes_canonical(&self) -> Vec<u8> {
+        let mut buffer = Vec::new();
+        self.write_canonical(&mut buffer).unwrap();
+        buffer
+    }
+
+    /// Lazily serialize a Sexp as a sequence of byte chunks of at most 8192
+    /// bytes each. The concatenation of the chunks is the same as the output
+    /// of `to_bytes`. The traversal uses an explicit stack rather than
+    /// recursion.
+    ///
+    /// # Example
+    ///
+    /// ```
+    ///     let sexp = rsexp::from_slice(b"((foo bar)(baz (1 2 3)))").unwrap();
+    ///     let bytes: Vec<u8> = sexp.byte_chunks().flatten().collect();
+    ///     assert_eq!(bytes, sexp.to_bytes());
+    /// ```
+    pub fn byte_chunks(&self) -> ByteChunks<'_> {
+        self.byte_chunks_with_size(8192)
+    }
+
+    /// Lazily serialize a Sexp as a sequence of byte chunks of at most
+    /// `chunk_size` bytes each, only the last chunk can be smaller.
+    pub fn byte_chunks_with_size(&self, chunk_size: usize) -> ByteChunks<'_> {
+        ByteChunks {
+            chunk_size: chunk_size.max(1),
+            root: Some(self),
+            stack: vec![],
+            first_in_list: true,
+            buffer: Vec::new(),
+        }
+    }
+
     /// Serialize a Sexp to a buffer, human readable version.
     ///
     /// # Example
@@ -290,11 +1199,200 @@ impl Sexp {
         self.write_hum(&mut buffer).unwrap();
         buffer
     }
+
+    /// Serialize a Sexp to a string, human readable version, see
+    /// `to_bytes_hum`. Atoms containing non-ASCII bytes are quoted and these
+    /// bytes escaped so the output is valid UTF-8, it would otherwise be
+    /// converted lossily with invalid sequences replaced by `U+FFFD`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    ///     let sexp = rsexp::from_slice(b"((foo bar)(baz \"\\xff\"))").unwrap();
+    ///     assert_eq!(sexp.pretty(), "((foo bar) (baz \"\\255\"))");
+    /// ```
+    pub fn pretty(&self) -> String {
+        String::from_utf8_lossy(&self.to_bytes_hum()).into_owned()
+    }
+
+    /// Serialize a Sexp to a string, machine readable version, see
+    /// `to_bytes_mach` and `pretty`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    ///     let sexp = rsexp::from_slice(b"((foo bar)(baz (1 2 3)))").unwrap();
+    ///     assert_eq!(sexp.compact(), "((foo bar)(baz(1 2 3)))");
+    /// ```
+    pub fn compact(&self) -> String {
+        String::from_utf8_lossy(&self.to_bytes_mach()).into_owned()
+    }
+
+    /// Serialize a list of equal-length lists to a buffer as a table, each row
+    /// on its own line with the columns padded to the width of their largest
+    /// cell. Returns `None` if the sexp is not such a table.
+    ///
+    /// # Example
+    ///
+    /// ```
+    ///     let sexp = rsexp::from_slice(b"((foo 1) (barbaz 22))").unwrap();
+    ///     assert_eq!(sexp.to_bytes_table().unwrap(), b"((foo    1)\n (barbaz 22))");
+    ///     assert_eq!(rsexp::from_slice(b"((foo 1) (bar))").unwrap().to_bytes_table(), None);
+    /// ```
+    pub fn to_bytes_table(&self) -> Option<Vec<u8>> {
+        let rows = match self {
+            Sexp::Atom(_) => return None,
+            Sexp::List(rows) => rows,
+        };
+        let n_columns = match rows.first() {
+            None | Some(Sexp::Atom(_)) => 0,
+            Some(Sexp::List(row)) => row.len(),
+        };
+        let mut cells: Vec<Vec<Vec<u8>>> = Vec::with_capacity(rows.len());
+        for row in rows.iter() {
+            match row {
+                Sexp::List(row) if row.len() == n_columns => {
+                    cells.push(row.iter().map(|cell| cell.to_bytes()).collect())
+                }
+                _ => return None,
+            }
+        }
+        let widths: Vec<usize> =
+            (0..n_columns).map(|j| cells.iter().map(|r| r[j].len()).max().unwrap_or(0)).collect();
+        let mut buffer = vec![b'('];
+        for (i, row) in cells.iter().enumerate() {
+            if i > 0 {
+                buffer.extend_from_slice(b"\n ")
+            }
+            buffer.push(b'(');
+            for (j, cell) in row.iter().enumerate() {
+                if j > 0 {
+                    buffer.push(b' ')
+                }
+                buffer.extend_from_slice(cell);
+                // The last column is not padded to avoid trailing spaces.
+                if j + 1 < n_columns {
+                    buffer.resize(buffer.len() + widths[j] - cell.len(), b' ')
+                }
+            }
+            buffer.push(b')')
+        }
+        buffer.push(b')');
+        Some(buffer)
+    }
+}
+
+#[cfg(feature = "regex")]
+impl Sexp {
+    /// Returns a copy of this sexp where every atom matching `re` has been
+    /// replaced by `replacement`, e.g. to redact secrets before logging.
+    ///
+    /// # Example
+    ///
+    /// ```
+    ///     let re = regex::bytes::Regex::new(r"^\d+$").unwrap();
+    ///     let sexp = rsexp::from_slice(b"((user foo) (pin 1234))").unwrap();
+    ///     let sexp = sexp.redact_matching(&re, b"<redacted>");
+    ///     assert_eq!(sexp.to_bytes(), b"((user foo) (pin <redacted>))");
+    /// ```
+    pub fn redact_matching(&self, re: &regex::bytes::Regex, replacement: &[u8]) -> Sexp {
+        match self {
+            Sexp::Atom(a) if re.is_match(a) => Sexp::Atom(replacement.to_vec()),
+            Sexp::Atom(a) => Sexp::Atom(a.clone()),
+            Sexp::List(l) => {
+                Sexp::List(l.iter().map(|s| s.redact_matching(re, replacement)).collect())
+            }
+        }
+    }
+}
+
+/// Iterator over the serialized bytes of a Sexp, see `Sexp::byte_chunks`.
+pub struct ByteChunks<'a> {
+    chunk_size: usize,
+    root: Option<&'a Sexp>,
+    stack: Vec<core::slice::Iter<'a, Sexp>>,
+    first_in_list: bool,
+    buffer: Vec<u8>,
+}
+
+impl<'a> ByteChunks<'a> {
+    fn push_sexp(&mut self, s: &'a Sexp) {
+        match s {
+            Sexp::Atom(v) => {
+                if needs_escaping(v) {
+                    // This could not fail as the buffer gets extended.
+                    write_escaped(v, &mut self.buffer).unwrap()
+                } else {
+                    self.buffer.extend_from_slice(v)
+                }
+                self.first_in_list = false;
+            }
+            Sexp::List(l) => {
+                self.buffer.push(b'(');
+                self.stack.push(l.iter());
+                self.first_in_list = true;
+            }
+        }
+    }
+
+    // Serializes the next atom or parenthesis to the buffer, returns false
+    // when there is nothing left to serialize.
+    fn step(&mut self) -> bool {
+        if let Some(root) = self.root.take() {
+            self.push_sexp(root);
+            return true;
+        }
+        let next = match self.stack.last_mut() {
+            None => return false,
+            Some(iter) => iter.next(),
+        };
+        match next {
+            Some(s) => {
+                if !self.first_in_list {
+                    self.buffer.push(b' ')
+                }
+                self.push_sexp(s)
+            }
+            None => {
+                self.buffer.push(b')');
+                self.stack.pop();
+                self.first_in_list = false;
+            }
+        }
+        true
+    }
+}
+
+impl<'a> Iterator for ByteChunks<'a> {
+    type Item = Vec<u8>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while self.buffer.len() < self.chunk_size && self.step() {}
+        if self.buffer.is_empty() {
+            None
+        } else if self.buffer.len() <= self.chunk_size {
+            Some(core::mem::take(&mut self.buffer))
+        } else {
+            let remaining = self.buffer.split_off(self.chunk_size);
+            Some(core::mem::replace(&mut self.buffer, remaining))
+        }
+    }
+}
+
+impl<'a> IntoIterator for &'a Sexp {
+    type Item = &'a Sexp;
+    type IntoIter = core::slice::Iter<'a, Sexp>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
 }
 
-impl std::fmt::Display for Sexp {
-    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
-        let bytes = self.to_bytes();
+// The alternate flag, as in `{:#}`, selects the human readable form of
+// `to_bytes_hum` with new lines and indentation.
+impl core::fmt::Display for Sexp {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        let bytes = if f.alternate() { self.to_bytes_hum() } else { self.to_bytes() };
         let cow = String::from_utf8_lossy(&bytes);
         write!(f, "{cow}")
     }
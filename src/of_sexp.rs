@@ -1,5 +1,10 @@
-use crate::{Sexp, UseToString};
-use std::collections::{BTreeMap, HashMap};
+use crate::{ElispBool, Sexp, UseToString};
+use alloc::boxed::Box;
+use alloc::collections::{BTreeMap, BTreeSet};
+use alloc::string::{String, ToString};
+use alloc::{format, vec::Vec};
+#[cfg(feature = "std")]
+use std::{collections::HashMap, path::PathBuf};
 
 // Conversion from Sexp to T
 
@@ -7,42 +12,99 @@ use std::collections::{BTreeMap, HashMap};
 /// type.
 #[derive(Debug, PartialEq, Eq)]
 pub enum IntoSexpError {
-    Utf8Error(std::str::Utf8Error),
-    FromUtf8Error(std::string::FromUtf8Error),
+    Utf8Error(core::str::Utf8Error),
+    FromUtf8Error(alloc::string::FromUtf8Error),
     ExpectedAtomGotList { type_: &'static str, list_len: usize },
     ExpectedListGotAtom { type_: &'static str },
     ExpectedPairForMapGotAtom { type_: &'static str },
     DuplicateKeyInMap { type_: &'static str, key: Option<String> },
+    DuplicateKeyAtPath { key: String, path: String },
     ExpectedPairForMapGotList { type_: &'static str, list_len: usize },
+    ExpectedEvenLengthForFlatKv { type_: &'static str, list_len: usize },
     ListLengthMismatch { type_: &'static str, expected_len: usize, list_len: usize },
+    RowWidthMismatch { row: usize, expected_len: usize, row_len: usize },
     StringConversionError { err: String },
     MissingFieldsInStruct { type_: &'static str, field: &'static str },
+    ValidationFailed { type_: &'static str, field: &'static str, msg: String },
+    MissingKeyInList { type_: &'static str, key: String },
     ExtraFieldsInStruct { type_: &'static str, extra_fields: Vec<String> },
     UnknownConstructorForEnum { type_: &'static str, constructor: String },
     ExpectedConstructorGotEmptyList { type_: &'static str },
     ExpectedConstructorGotListInList { type_: &'static str },
+    ExpectedWrappedConstructor { type_: &'static str },
+    InTupleElement { index: usize, err: Box<IntoSexpError> },
+    InListElement { index: usize, err: Box<IntoSexpError> },
+    InMapEntry { key: String, err: Box<IntoSexpError> },
+    InField { type_: &'static str, field: &'static str, err: Box<IntoSexpError> },
 }
 
-impl std::fmt::Display for IntoSexpError {
-    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
-        write!(f, "{self:?}")
+impl core::fmt::Display for IntoSexpError {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        match self {
+            IntoSexpError::InTupleElement { index, err } => {
+                write!(f, "tuple element {index}: {err}")
+            }
+            IntoSexpError::InListElement { index, err } => {
+                write!(f, "list element {index}: {err}")
+            }
+            IntoSexpError::InMapEntry { key, err } => write!(f, "map entry {key}: {err}"),
+            IntoSexpError::InField { type_, field, err } => {
+                write!(f, "field {field} of {type_}: {err}")
+            }
+            _ => write!(f, "{self:?}"),
+        }
+    }
+}
+
+impl IntoSexpError {
+    /// Adds the position of the tuple element that failed to convert.
+    pub fn with_index(self, index: usize) -> Self {
+        IntoSexpError::InTupleElement { index, err: Box::new(self) }
+    }
+
+    /// Adds the position of the list element that failed to convert.
+    pub fn with_list_index(self, index: usize) -> Self {
+        IntoSexpError::InListElement { index, err: Box::new(self) }
+    }
+
+    /// Adds the key of the map entry that failed to convert.
+    pub fn with_map_key(self, key: &Sexp) -> Self {
+        IntoSexpError::InMapEntry { key: key.to_string(), err: Box::new(self) }
+    }
+
+    /// Adds the struct field that failed to convert, this is used by the
+    /// derived `OfSexp` implementations.
+    pub fn with_field(self, type_: &'static str, field: &'static str) -> Self {
+        IntoSexpError::InField { type_, field, err: Box::new(self) }
     }
 }
 
+#[cfg(feature = "std")]
 impl std::error::Error for IntoSexpError {}
 
-impl From<std::str::Utf8Error> for IntoSexpError {
-    fn from(e: std::str::Utf8Error) -> Self {
+impl From<core::str::Utf8Error> for IntoSexpError {
+    fn from(e: core::str::Utf8Error) -> Self {
         IntoSexpError::Utf8Error(e)
     }
 }
 
-impl From<std::string::FromUtf8Error> for IntoSexpError {
-    fn from(e: std::string::FromUtf8Error) -> Self {
+impl From<alloc::string::FromUtf8Error> for IntoSexpError {
+    fn from(e: alloc::string::FromUtf8Error) -> Self {
         IntoSexpError::FromUtf8Error(e)
     }
 }
 
+// The (key value) pairs of a record indexed by key.
+#[cfg(feature = "std")]
+type FieldMap<'a> = HashMap<&'a [u8], &'a Sexp>;
+
+// The values of the (key value1 value2 ...) lists, if present.
+#[cfg(feature = "std")]
+type ListValues<'a> = Vec<Option<&'a [Sexp]>>;
+
+// The column names and rows of a table.
+type Table = (Vec<Vec<u8>>, Vec<Vec<Sexp>>);
+
 impl Sexp {
     pub fn extract_atom<'a>(&'a self, type_: &'static str) -> Result<&'a [u8], IntoSexpError> {
         match self {
@@ -53,6 +115,54 @@ impl Sexp {
         }
     }
 
+    /// Returns the content of an atom as a string, failing for lists and for
+    /// atoms that are not valid UTF-8.
+    ///
+    /// # Example
+    ///
+    /// ```
+    ///     assert_eq!(rsexp::atom(b"foo").extract_atom_str("name"), Ok("foo"));
+    ///     assert!(rsexp::atom(b"\xff").extract_atom_str("name").is_err());
+    /// ```
+    pub fn extract_atom_str<'a>(&'a self, type_: &'static str) -> Result<&'a str, IntoSexpError> {
+        Ok(core::str::from_utf8(self.extract_atom(type_)?)?)
+    }
+
+    // Parses an atom using `FromStr`, the parsing errors are reported as
+    // `StringConversionError`.
+    fn extract_parsed<T>(&self, type_: &'static str) -> Result<T, IntoSexpError>
+    where
+        T: core::str::FromStr,
+        T::Err: core::fmt::Display,
+    {
+        self.extract_atom_str(type_)?.parse().map_err(|err| {
+            let err = format!("{err}");
+            IntoSexpError::StringConversionError { err }
+        })
+    }
+
+    /// Parses an atom as an integer.
+    ///
+    /// # Example
+    ///
+    /// ```
+    ///     assert_eq!(rsexp::atom(b"-42").extract_i64(), Ok(-42));
+    ///     assert!(rsexp::atom(b"4.2").extract_i64().is_err());
+    /// ```
+    pub fn extract_i64(&self) -> Result<i64, IntoSexpError> {
+        self.extract_parsed("i64")
+    }
+
+    /// Parses an atom as a float.
+    pub fn extract_f64(&self) -> Result<f64, IntoSexpError> {
+        self.extract_parsed("f64")
+    }
+
+    /// Parses an atom as a boolean, either `true` or `false`.
+    pub fn extract_bool(&self) -> Result<bool, IntoSexpError> {
+        self.extract_parsed("bool")
+    }
+
     pub fn extract_list<'a>(&'a self, type_: &'static str) -> Result<&'a [Self], IntoSexpError> {
         match self {
             Sexp::List(list) => Ok(list),
@@ -60,6 +170,183 @@ impl Sexp {
         }
     }
 
+    /// Returns the value associated with `key` in a list of `(key value)`
+    /// pairs, elements that are not such pairs are ignored. If the key
+    /// appears multiple times, the first value is returned.
+    ///
+    /// # Example
+    ///
+    /// ```
+    ///     let sexp = rsexp::from_slice(b"((name foo) (size 42))").unwrap();
+    ///     assert_eq!(sexp.require(b"size", "config"), Ok(&rsexp::atom(b"42")));
+    ///     assert!(sexp.require(b"color", "config").is_err());
+    /// ```
+    pub fn require<'a>(
+        &'a self,
+        key: &[u8],
+        type_: &'static str,
+    ) -> Result<&'a Self, IntoSexpError> {
+        self.extract_list(type_)?;
+        self.field(key).ok_or_else(|| IntoSexpError::MissingKeyInList {
+            type_,
+            key: String::from_utf8_lossy(key).to_string(),
+        })
+    }
+
+    /// Returns the value associated with the first occurrence of `key` in a
+    /// list of `(key value)` pairs, or `None` if `self` is an atom or if
+    /// there is no such key.
+    ///
+    /// # Example
+    ///
+    /// ```
+    ///     let sexp = rsexp::from_slice(b"((name foo) (size 42))").unwrap();
+    ///     assert_eq!(sexp.field(b"name"), Some(&rsexp::atom(b"foo")));
+    ///     assert_eq!(sexp.field(b"color"), None);
+    /// ```
+    pub fn field(&self, key: &[u8]) -> Option<&Self> {
+        self.fields(key).next()
+    }
+
+    /// Iterates over the values associated with `key` in a list of
+    /// `(key value)` pairs, elements that are not such pairs are ignored.
+    ///
+    /// # Example
+    ///
+    /// ```
+    ///     let sexp = rsexp::from_slice(b"((dep foo) (name bar) (dep baz))").unwrap();
+    ///     let deps: Vec<_> = sexp.fields(b"dep").map(|s| s.to_string()).collect();
+    ///     assert_eq!(deps, ["foo", "baz"]);
+    /// ```
+    pub fn fields<'a, 'k>(&'a self, key: &'k [u8]) -> impl Iterator<Item = &'a Self> + 'k
+    where
+        'a: 'k,
+    {
+        let list = self.as_list().unwrap_or_default();
+        list.iter().filter_map(move |elem| match elem.as_list()? {
+            [Sexp::Atom(k), value] if k == key => Some(value),
+            _ => None,
+        })
+    }
+
+    /// Returns true if this is a list of `(key value)` pairs where each key is
+    /// an atom, the empty list is a record.
+    ///
+    /// # Example
+    ///
+    /// ```
+    ///     assert!(rsexp::from_slice(b"((name foo) (size 42))").unwrap().is_record());
+    ///     assert!(!rsexp::from_slice(b"((name foo) size)").unwrap().is_record());
+    /// ```
+    pub fn is_record(&self) -> bool {
+        match self {
+            Sexp::Atom(_) => false,
+            Sexp::List(list) => {
+                list.iter().all(|elem| matches!(elem.as_list(), Some([Sexp::Atom(_), _])))
+            }
+        }
+    }
+
+    /// Checks recursively that no record, i.e. list of `(key value)` pairs, has
+    /// the same key twice. The error contains the duplicate key and the path
+    /// of the record, made of `/`-separated keys for records and indexes for
+    /// other lists.
+    ///
+    /// # Example
+    ///
+    /// ```
+    ///     let sexp = rsexp::from_slice(b"((a 1) (b ((c 2) (c 3))))").unwrap();
+    ///     let err = sexp.check_no_duplicate_keys().unwrap_err();
+    ///     let expected =
+    ///         rsexp::IntoSexpError::DuplicateKeyAtPath { key: "c".to_string(), path: "b".to_string() };
+    ///     assert_eq!(err, expected);
+    /// ```
+    pub fn check_no_duplicate_keys(&self) -> Result<(), IntoSexpError> {
+        fn walk(sexp: &Sexp, path: &mut Vec<String>) -> Result<(), IntoSexpError> {
+            let list = match sexp {
+                Sexp::Atom(_) => return Ok(()),
+                Sexp::List(list) => list,
+            };
+            if sexp.is_record() {
+                let mut keys: BTreeSet<&[u8]> = BTreeSet::new();
+                for elem in list.iter() {
+                    if let Some([Sexp::Atom(key), value]) = elem.as_list() {
+                        let new_key = keys.insert(key.as_slice());
+                        let key = String::from_utf8_lossy(key).to_string();
+                        if !new_key {
+                            return Err(IntoSexpError::DuplicateKeyAtPath {
+                                key,
+                                path: path.join("/"),
+                            });
+                        }
+                        path.push(key);
+                        walk(value, path)?;
+                        path.pop();
+                    }
+                }
+            } else {
+                for (index, elem) in list.iter().enumerate() {
+                    path.push(index.to_string());
+                    walk(elem, path)?;
+                    path.pop();
+                }
+            }
+            Ok(())
+        }
+        walk(self, &mut Vec::new())
+    }
+
+    /// Splits a table, i.e. a list starting with a row of column names
+    /// followed by data rows, into the column names and the rows. The rows
+    /// are indexed from 0 starting with the first data row. The empty list is
+    /// a table with no columns and no rows.
+    ///
+    /// # Example
+    ///
+    /// ```
+    ///     let sexp = rsexp::from_slice(b"((name size) (foo 1) (bar (2 3)))").unwrap();
+    ///     let (headers, rows) = sexp.to_table().unwrap();
+    ///     assert_eq!(headers, [b"name".to_vec(), b"size".to_vec()]);
+    ///     assert_eq!(rows[1], [rsexp::atom(b"bar"), rsexp::from_slice(b"(2 3)").unwrap()]);
+    ///     assert_eq!(rsexp::Sexp::of_table(headers, rows), sexp);
+    /// ```
+    pub fn to_table(&self) -> Result<Table, IntoSexpError> {
+        let list = self.extract_list("table")?;
+        let (headers, rows) = match list.split_first() {
+            None => return Ok((Vec::new(), Vec::new())),
+            Some(split) => split,
+        };
+        let headers = headers
+            .extract_list("table")?
+            .iter()
+            .map(|header| header.extract_atom("table").map(|atom| atom.to_vec()))
+            .collect::<Result<Vec<_>, _>>()?;
+        let rows = rows
+            .iter()
+            .enumerate()
+            .map(|(row, sexp)| {
+                let values = sexp.extract_list("table")?;
+                if values.len() != headers.len() {
+                    return Err(IntoSexpError::RowWidthMismatch {
+                        row,
+                        expected_len: headers.len(),
+                        row_len: values.len(),
+                    });
+                }
+                Ok(values.to_vec())
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok((headers, rows))
+    }
+
+    /// Builds a table from column names and rows, this is the inverse of
+    /// `to_table`.
+    pub fn of_table(headers: Vec<Vec<u8>>, rows: Vec<Vec<Sexp>>) -> Sexp {
+        let headers = Sexp::List(headers.into_iter().map(Sexp::Atom).collect());
+        let rows = rows.into_iter().map(Sexp::List);
+        Sexp::List(core::iter::once(headers).chain(rows).collect())
+    }
+
     /// Extracts the constructor and fields for an Enum.
     pub fn extract_enum<'a>(
         &'a self,
@@ -77,17 +364,82 @@ impl Sexp {
         }
     }
 
+    /// Extracts the constructor and fields for an Enum where the constructor
+    /// is wrapped in a list, i.e. `(C)` or `((C) arg1 arg2)`.
+    pub fn extract_enum_wrapped<'a>(
+        &'a self,
+        type_: &'static str,
+    ) -> Result<(&'a [u8], &'a [Self]), IntoSexpError> {
+        match self.extract_list(type_)? {
+            [] => Err(IntoSexpError::ExpectedConstructorGotEmptyList { type_ }),
+            [Sexp::Atom(atom)] => Ok((atom, &[])),
+            [Sexp::List(cstor), args @ ..] => match cstor.as_slice() {
+                [Sexp::Atom(atom)] => Ok((atom, args)),
+                _ => Err(IntoSexpError::ExpectedWrappedConstructor { type_ }),
+            },
+            [Sexp::Atom(_), ..] => Err(IntoSexpError::ExpectedWrappedConstructor { type_ }),
+        }
+    }
+
+    #[cfg(feature = "std")]
     pub fn extract_map<'a>(
         list: &'a [Self],
         type_: &'static str,
     ) -> Result<HashMap<&'a [u8], &'a Self>, IntoSexpError> {
+        let (map, _flags) = Self::extract_map_and_flags(list, &[], type_)?;
+        Ok(map)
+    }
+
+    /// Similar to `extract_map` but also accepts the single element lists
+    /// `(flag)` for the given flags, the returned vector indicates which of
+    /// the flags were present.
+    #[cfg(feature = "std")]
+    pub fn extract_map_and_flags<'a>(
+        list: &'a [Self],
+        flags: &[&str],
+        type_: &'static str,
+    ) -> Result<(FieldMap<'a>, Vec<bool>), IntoSexpError> {
+        let (map, present, _lists) = Self::extract_map_flags_and_lists(list, flags, &[], type_)?;
+        Ok((map, present))
+    }
+
+    /// Similar to `extract_map_and_flags` but also accepts lists
+    /// `(key value1 value2 ...)` with any number of values for the given list
+    /// keys, the returned vector contains the values for each of these keys.
+    #[cfg(feature = "std")]
+    pub fn extract_map_flags_and_lists<'a>(
+        list: &'a [Self],
+        flags: &[&str],
+        lists: &[&str],
+        type_: &'static str,
+    ) -> Result<(FieldMap<'a>, Vec<bool>, ListValues<'a>), IntoSexpError> {
         let mut map = HashMap::new();
+        let mut present = vec![false; flags.len()];
+        let mut list_values = vec![None; lists.len()];
         for elem in list.iter() {
             match elem {
                 Sexp::Atom(_atom) => {
                     return Err(IntoSexpError::ExpectedPairForMapGotAtom { type_ })
                 }
                 Sexp::List(list) => match list.as_slice() {
+                    [Sexp::Atom(key), values @ ..] if lists.iter().any(|l| l.as_bytes() == key) => {
+                        let index = lists.iter().position(|l| l.as_bytes() == key).unwrap();
+                        if list_values[index].replace(values).is_some() {
+                            return Err(IntoSexpError::DuplicateKeyInMap {
+                                type_,
+                                key: Some(String::from_utf8_lossy(key).to_string()),
+                            });
+                        }
+                    }
+                    [Sexp::Atom(key)] if flags.iter().any(|f| f.as_bytes() == key) => {
+                        let index = flags.iter().position(|f| f.as_bytes() == key).unwrap();
+                        if core::mem::replace(&mut present[index], true) {
+                            return Err(IntoSexpError::DuplicateKeyInMap {
+                                type_,
+                                key: Some(String::from_utf8_lossy(key).to_string()),
+                            });
+                        }
+                    }
                     [Sexp::Atom(key), value] => {
                         if map.insert(key.as_slice(), value).is_some() {
                             return Err(IntoSexpError::DuplicateKeyInMap {
@@ -105,7 +457,7 @@ impl Sexp {
                 },
             }
         }
-        Ok(map)
+        Ok((map, present, list_values))
     }
 }
 
@@ -121,6 +473,12 @@ impl Sexp {
     }
 }
 
+impl OfSexp for Sexp {
+    fn of_sexp(s: &Sexp) -> Result<Self, IntoSexpError> {
+        Ok(s.clone())
+    }
+}
+
 impl OfSexp for String {
     fn of_sexp(s: &Sexp) -> Result<Self, IntoSexpError> {
         let atom = s.extract_atom("String")?;
@@ -128,18 +486,54 @@ impl OfSexp for String {
     }
 }
 
+#[cfg(feature = "bytes")]
+impl OfSexp for bytes::Bytes {
+    fn of_sexp(s: &Sexp) -> Result<Self, IntoSexpError> {
+        let atom = s.extract_atom("Bytes")?;
+        Ok(bytes::Bytes::copy_from_slice(atom))
+    }
+}
+
+#[cfg(feature = "std")]
+impl OfSexp for PathBuf {
+    fn of_sexp(s: &Sexp) -> Result<Self, IntoSexpError> {
+        let atom = s.extract_atom("PathBuf")?;
+        #[cfg(unix)]
+        {
+            use std::os::unix::ffi::OsStrExt;
+            Ok(PathBuf::from(std::ffi::OsStr::from_bytes(atom)))
+        }
+        #[cfg(not(unix))]
+        {
+            Ok(PathBuf::from(String::from_utf8(atom.to_vec())?))
+        }
+    }
+}
+
+impl OfSexp for ElispBool {
+    fn of_sexp(s: &Sexp) -> Result<Self, IntoSexpError> {
+        match s {
+            Sexp::Atom(atom) if atom == b"t" => Ok(ElispBool(true)),
+            Sexp::Atom(atom) if atom == b"nil" => Ok(ElispBool(false)),
+            Sexp::List(list) if list.is_empty() => Ok(ElispBool(false)),
+            Sexp::Atom(atom) => {
+                let err = format!("expected t or nil, got {}", String::from_utf8_lossy(atom));
+                Err(IntoSexpError::StringConversionError { err })
+            }
+            Sexp::List(list) => {
+                Err(IntoSexpError::ExpectedAtomGotList { type_: "ElispBool", list_len: list.len() })
+            }
+        }
+    }
+}
+
 impl<T> OfSexp for T
 where
-    T: UseToString + std::str::FromStr,
-    T::Err: std::fmt::Display,
+    T: UseToString + core::str::FromStr,
+    T::Err: core::fmt::Display,
 {
     fn of_sexp(s: &Sexp) -> Result<Self, IntoSexpError> {
-        let atom = s.extract_atom("stringable")?;
-        let atom = std::str::from_utf8(atom)?;
-        T::from_str(atom).map_err(|err| {
-            let err = format!("{err}");
-            IntoSexpError::StringConversionError { err }
-        })
+        s.extract_parsed("stringable")
     }
 }
 
@@ -150,8 +544,8 @@ where
     fn of_sexp(s: &Sexp) -> Result<Self, IntoSexpError> {
         let list = s.extract_list("Vec")?;
         let mut res = Vec::new();
-        for elem in list.iter() {
-            res.push(T::of_sexp(elem)?)
+        for (index, elem) in list.iter().enumerate() {
+            res.push(T::of_sexp(elem).map_err(|e| e.with_list_index(index))?)
         }
         Ok(res)
     }
@@ -167,14 +561,14 @@ macro_rules! count_tts {
 }
 
 macro_rules! tuple_impls {
-    ( $( $name:ident )+ ) => {
+    ( $( ($index:tt $name:ident) )+ ) => {
         impl<$($name: OfSexp),+> OfSexp for ($($name,)+)
         {
             #[allow(non_snake_case)]
             fn of_sexp(s: &Sexp) -> Result<Self, IntoSexpError> {
                 match s.extract_list("tuple")? {
                     [$($name,)+] => {
-                        $(let $name = $name::of_sexp($name)?;)+
+                        $(let $name = $name::of_sexp($name).map_err(|e| e.with_index($index))?;)+
                         Ok(($($name,)+))
                     }
                     l => Err(IntoSexpError::ListLengthMismatch {
@@ -189,16 +583,22 @@ macro_rules! tuple_impls {
     };
 }
 
-tuple_impls! { A }
-tuple_impls! { A B }
-tuple_impls! { A B C }
-tuple_impls! { A B C D }
-tuple_impls! { A B C D E }
-tuple_impls! { A B C D E F }
-tuple_impls! { A B C D E F G }
-tuple_impls! { A B C D E F G H }
-tuple_impls! { A B C D E F G H I }
-tuple_impls! { A B C D E F G H I J }
+tuple_impls! { (0 A) }
+tuple_impls! { (0 A) (1 B) }
+tuple_impls! { (0 A) (1 B) (2 C) }
+tuple_impls! { (0 A) (1 B) (2 C) (3 D) }
+tuple_impls! { (0 A) (1 B) (2 C) (3 D) (4 E) }
+tuple_impls! { (0 A) (1 B) (2 C) (3 D) (4 E) (5 F) }
+tuple_impls! { (0 A) (1 B) (2 C) (3 D) (4 E) (5 F) (6 G) }
+tuple_impls! { (0 A) (1 B) (2 C) (3 D) (4 E) (5 F) (6 G) (7 H) }
+tuple_impls! { (0 A) (1 B) (2 C) (3 D) (4 E) (5 F) (6 G) (7 H) (8 I) }
+tuple_impls! { (0 A) (1 B) (2 C) (3 D) (4 E) (5 F) (6 G) (7 H) (8 I) (9 J) }
+tuple_impls! { (0 A) (1 B) (2 C) (3 D) (4 E) (5 F) (6 G) (7 H) (8 I) (9 J) (10 K) }
+tuple_impls! { (0 A) (1 B) (2 C) (3 D) (4 E) (5 F) (6 G) (7 H) (8 I) (9 J) (10 K) (11 L) }
+tuple_impls! { (0 A) (1 B) (2 C) (3 D) (4 E) (5 F) (6 G) (7 H) (8 I) (9 J) (10 K) (11 L) (12 M) }
+tuple_impls! { (0 A) (1 B) (2 C) (3 D) (4 E) (5 F) (6 G) (7 H) (8 I) (9 J) (10 K) (11 L) (12 M) (13 N) }
+tuple_impls! { (0 A) (1 B) (2 C) (3 D) (4 E) (5 F) (6 G) (7 H) (8 I) (9 J) (10 K) (11 L) (12 M) (13 N) (14 O) }
+tuple_impls! { (0 A) (1 B) (2 C) (3 D) (4 E) (5 F) (6 G) (7 H) (8 I) (9 J) (10 K) (11 L) (12 M) (13 N) (14 O) (15 P) }
 
 impl<T> OfSexp for Option<T>
 where
@@ -230,6 +630,42 @@ impl OfSexp for () {
     }
 }
 
+impl<T: OfSexp> OfSexp for core::num::Wrapping<T> {
+    fn of_sexp(s: &Sexp) -> Result<Self, IntoSexpError> {
+        Ok(core::num::Wrapping(T::of_sexp(s)?))
+    }
+}
+
+impl<T: OfSexp> OfSexp for core::num::Saturating<T> {
+    fn of_sexp(s: &Sexp) -> Result<Self, IntoSexpError> {
+        Ok(core::num::Saturating(T::of_sexp(s)?))
+    }
+}
+
+// Uses the OCaml representation, (Ok v) or (Error e).
+impl<T, E> OfSexp for Result<T, E>
+where
+    T: OfSexp,
+    E: OfSexp,
+{
+    fn of_sexp(s: &Sexp) -> Result<Self, IntoSexpError> {
+        let type_ = "Result";
+        match s.extract_enum(type_)? {
+            (b"Ok", [v]) => Ok(Ok(T::of_sexp(v)?)),
+            (b"Error", [e]) => Ok(Err(E::of_sexp(e)?)),
+            (b"Ok" | b"Error", args) => Err(IntoSexpError::ListLengthMismatch {
+                type_,
+                expected_len: 1,
+                list_len: args.len(),
+            }),
+            (constructor, _) => Err(IntoSexpError::UnknownConstructorForEnum {
+                type_,
+                constructor: String::from_utf8_lossy(constructor).to_string(),
+            }),
+        }
+    }
+}
+
 macro_rules! of_sexp_map {
     ($container_name:ident) => {
         fn of_sexp(s: &Sexp) -> Result<Self, IntoSexpError> {
@@ -243,8 +679,9 @@ macro_rules! of_sexp_map {
                     }
                     Sexp::List(list) => match list.as_slice() {
                         [key, value] => {
-                            if map.insert(OfSexp::of_sexp(key)?, OfSexp::of_sexp(value)?).is_some()
-                            {
+                            let k = OfSexp::of_sexp(key).map_err(|e| e.with_map_key(key))?;
+                            let v = OfSexp::of_sexp(value).map_err(|e| e.with_map_key(key))?;
+                            if map.insert(k, v).is_some() {
                                 return Err(IntoSexpError::DuplicateKeyInMap { type_, key: None });
                             }
                         }
@@ -262,9 +699,10 @@ macro_rules! of_sexp_map {
     };
 }
 
-impl<K, V> OfSexp for std::collections::HashMap<K, V>
+#[cfg(feature = "std")]
+impl<K, V> OfSexp for HashMap<K, V>
 where
-    K: OfSexp + Eq + std::hash::Hash,
+    K: OfSexp + Eq + core::hash::Hash,
     V: OfSexp,
 {
     of_sexp_map!(HashMap);
@@ -1,25 +1,298 @@
-// TODO: Block comments.
-use crate::Sexp;
+use crate::{CommentedSexp, CommentedValue, Radix, Sexp, SexpRef};
+use alloc::borrow::Cow;
+use alloc::string::{String, ToString};
+use alloc::{vec, vec::Vec};
 
+/// Errors that could be generated when parsing a Sexp. Each variant holds the
+/// byte offset in the input at which the error was detected, `line_col` can be
+/// used to convert it to a line and column.
 #[derive(Clone, Debug, PartialEq, Eq)]
 pub enum Error {
-    UnexpectedCharInString(u8),
-    UnexpectedEofInString,
-    UnexpectedEof,
-    EmptyAtom,
+    UnexpectedCharInString {
+        c: u8,
+        offset: usize,
+    },
+    /// The offset is the one of the opening quote.
+    UnexpectedEofInString {
+        offset: usize,
+    },
+    UnexpectedEof {
+        offset: usize,
+    },
+    EmptyAtom {
+        offset: usize,
+    },
+    /// The offset is the one of the opening `#|`.
+    UnterminatedBlockComment {
+        offset: usize,
+    },
+    /// The nesting of lists is deeper than the allowed maximum depth, the
+    /// offset is the one of the opening parenthesis exceeding the limit.
+    MaxDepthExceeded {
+        depth: usize,
+        offset: usize,
+    },
+    /// A byte that is not valid at this position, used by the canonical
+    /// representation parser and by `unescape_atom`.
+    UnexpectedChar {
+        c: u8,
+        offset: usize,
+    },
+    /// The node budget of a `BatchParser` has been used up, the offset is the
+    /// one of the node exceeding the budget.
+    BudgetExhausted {
+        offset: usize,
+    },
+    /// An error returned by the underlying reader when parsing from a stream.
+    Io {
+        err: String,
+        offset: usize,
+    },
+}
+
+impl Error {
+    /// The byte offset in the input at which the error was detected.
+    pub fn offset(&self) -> usize {
+        match self {
+            Error::UnexpectedCharInString { offset, .. }
+            | Error::UnexpectedEofInString { offset }
+            | Error::UnexpectedEof { offset }
+            | Error::EmptyAtom { offset }
+            | Error::UnterminatedBlockComment { offset }
+            | Error::MaxDepthExceeded { offset, .. }
+            | Error::UnexpectedChar { offset, .. }
+            | Error::BudgetExhausted { offset }
+            | Error::Io { offset, .. } => *offset,
+        }
+    }
+
+    // Whether this error could disappear if more input was available.
+    #[cfg(feature = "std")]
+    pub(crate) fn is_eof(&self, input_len: usize) -> bool {
+        match self {
+            Error::UnexpectedEofInString { .. } | Error::UnterminatedBlockComment { .. } => true,
+            Error::UnexpectedEof { offset } | Error::EmptyAtom { offset } => *offset == input_len,
+            Error::UnexpectedCharInString { .. }
+            | Error::MaxDepthExceeded { .. }
+            | Error::UnexpectedChar { .. }
+            | Error::BudgetExhausted { .. }
+            | Error::Io { .. } => false,
+        }
+    }
+
+    #[cfg(feature = "std")]
+    pub(crate) fn shift_offset(mut self, delta: usize) -> Self {
+        match &mut self {
+            Error::UnexpectedCharInString { offset, .. }
+            | Error::UnexpectedEofInString { offset }
+            | Error::UnexpectedEof { offset }
+            | Error::EmptyAtom { offset }
+            | Error::UnterminatedBlockComment { offset }
+            | Error::MaxDepthExceeded { offset, .. }
+            | Error::UnexpectedChar { offset, .. }
+            | Error::BudgetExhausted { offset }
+            | Error::Io { offset, .. } => *offset += delta,
+        }
+        self
+    }
+
+    /// The line and column at which the error was detected, `input` should be
+    /// the input that was passed to the parser.
+    pub fn line_col(&self, input: &[u8]) -> (usize, usize) {
+        line_col(input, self.offset())
+    }
+}
+
+impl core::fmt::Display for Error {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        write!(f, "{self:?}")
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for Error {}
+
+/// Converts a byte offset in `input` to a line and a column, both starting
+/// at 1. The column is counted in bytes.
+///
+/// # Example
+///
+/// ```
+///     let input = b"(foo\n bar))";
+///     let err = rsexp::from_slice(input).unwrap_err();
+///     assert_eq!(err.offset(), 10);
+///     assert_eq!(rsexp::line_col(input, err.offset()), (2, 6));
+/// ```
+pub fn line_col(input: &[u8], offset: usize) -> (usize, usize) {
+    let before = &input[..offset.min(input.len())];
+    let line = 1 + before.iter().filter(|&&c| c == b'\n').count();
+    let line_start = before.iter().rposition(|&c| c == b'\n').map_or(0, |i| i + 1);
+    (line, 1 + offset - line_start)
 }
 
 type Res<'a, T> = Result<(&'a [u8], T), Error>;
 
-fn space_or_comments(input: &[u8]) -> Res<()> {
+// The parser is shared between owned and borrowed sexps, atoms are borrowed
+// from the input when they do not need any unescaping.
+trait Node<'a>: Sized {
+    fn atom(atom: Cow<'a, [u8]>) -> Self;
+    fn list(list: Vec<Self>) -> Self;
+}
+
+impl<'a> Node<'a> for Sexp {
+    fn atom(atom: Cow<'a, [u8]>) -> Self {
+        Sexp::Atom(atom.into_owned())
+    }
+
+    fn list(list: Vec<Self>) -> Self {
+        Sexp::List(list)
+    }
+}
+
+impl<'a> Node<'a> for SexpRef<'a> {
+    fn atom(atom: Cow<'a, [u8]>) -> Self {
+        SexpRef::Atom(atom)
+    }
+
+    fn list(list: Vec<Self>) -> Self {
+        SexpRef::List(list)
+    }
+}
+
+/// The maximum nesting depth of lists used by default when parsing. Parsing
+/// does not recurse but dropping the parsed sexp does, as well as some
+/// functions using it such as `Sexp::intern`, so the default depth is bounded.
+pub const DEFAULT_MAX_DEPTH: usize = 10_000;
+
+/// Options controlling how the parser interprets its input.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ParseOptions {
+    /// When set, unquoted atoms such as `#x1F`, `#o17`, or `#b101` are read as
+    /// radix-prefixed integers and stored in their decimal form, e.g. `31`.
+    /// Atoms that are not valid numbers in the given radix are left untouched.
+    pub radix_atoms: bool,
+    /// The maximum nesting depth of lists, parsing deeper inputs fails with
+    /// `Error::MaxDepthExceeded`, `DEFAULT_MAX_DEPTH` by default. Lists are
+    /// parsed without recursion so larger values can be used, but dropping or
+    /// serializing the resulting sexp still recurses, `usize::MAX` disables
+    /// the limit.
+    pub max_depth: usize,
+    /// The byte starting line comments, `;` by default. Another byte replaces
+    /// `;` which is then an ordinary atom character. When using `#`, block
+    /// comments `#|...|#` and datum comments `#;` are not recognized and a
+    /// `#` always starts a line comment.
+    pub line_comment_char: u8,
+}
+
+impl Default for ParseOptions {
+    fn default() -> Self {
+        ParseOptions { radix_atoms: false, max_depth: DEFAULT_MAX_DEPTH, line_comment_char: b';' }
+    }
+}
+
+// State shared by the parsing functions, the whole input is used to compute
+// the offsets reported in errors as the parsing functions only see a suffix of
+// it.
+struct Ctx<'a> {
+    input: &'a [u8],
+    opts: &'a ParseOptions,
+    depth: core::cell::Cell<usize>,
+    // The number of nodes that can still be parsed, unbounded when None.
+    budget: core::cell::Cell<Option<usize>>,
+}
+
+impl<'a> Ctx<'a> {
+    fn new(input: &'a [u8], opts: &'a ParseOptions) -> Self {
+        Ctx { input, opts, depth: core::cell::Cell::new(0), budget: core::cell::Cell::new(None) }
+    }
+
+    fn offset(&self, input: &[u8], index: usize) -> usize {
+        self.input.len() - input.len() + index
+    }
+
+    // Accounts for a new atom or list starting at the beginning of input.
+    fn spend_node(&self, input: &[u8]) -> Result<(), Error> {
+        match self.budget.get() {
+            None => Ok(()),
+            Some(0) => Err(Error::BudgetExhausted { offset: self.offset(input, 0) }),
+            Some(budget) => {
+                self.budget.set(Some(budget - 1));
+                Ok(())
+            }
+        }
+    }
+}
+
+// Returns the index following the `|#` that closes the block comment starting
+// at index, block comments can be nested.
+fn block_comment(input: &[u8], index: usize, ctx: &Ctx) -> Result<usize, Error> {
+    let start = index;
+    let mut depth = 0;
+    let mut index = index;
+    while index + 1 < input.len() {
+        match (input[index], input[index + 1]) {
+            (b'#', b'|') => {
+                depth += 1;
+                index += 2
+            }
+            (b'|', b'#') => {
+                depth -= 1;
+                index += 2;
+                if depth == 0 {
+                    return Ok(index);
+                }
+            }
+            _ => index += 1,
+        }
+    }
+    Err(Error::UnterminatedBlockComment { offset: ctx.offset(input, start) })
+}
+
+fn space_or_comments<'a>(input: &'a [u8], ctx: &Ctx) -> Res<'a, ()> {
+    space_or_comments_collecting(input, ctx, None)
+}
+
+// Similar to space_or_comments, the line comments are pushed to `comments`
+// if provided. The line comments within and after a datum comment are not
+// collected.
+fn space_or_comments_collecting<'a>(
+    input: &'a [u8],
+    ctx: &Ctx,
+    comments: Option<&mut Vec<Vec<u8>>>,
+) -> Res<'a, ()> {
+    let (input, ()) = blanks(input, ctx, comments)?;
+    if !is_datum_comment(input, ctx) {
+        return Ok((input, ()));
+    }
+    // Nothing is built when skipping datum comments, SexpRef is only used as
+    // a placeholder.
+    let (input, _) = sexp_no_leading_blank_::<SexpRef>(input, ctx, true)?;
+    Ok((input, ()))
+}
+
+fn is_datum_comment(input: &[u8], ctx: &Ctx) -> bool {
+    ctx.opts.line_comment_char != b'#' && input.starts_with(b"#;")
+}
+
+// Skips spaces, line comments, and block comments, the line comments are
+// pushed to `comments` if provided. Datum comments are left untouched.
+fn blanks<'a>(input: &'a [u8], ctx: &Ctx, mut comments: Option<&mut Vec<Vec<u8>>>) -> Res<'a, ()> {
     let mut index = 0;
     while index < input.len() {
+        let hash_comments = ctx.opts.line_comment_char == b'#';
         match input[index] {
             b' ' | b'\t' | b'\r' | b'\n' => index += 1,
-            b';' => {
+            b'#' if !hash_comments && input.get(index + 1) == Some(&b'|') => {
+                index = block_comment(input, index, ctx)?
+            }
+            c if c == ctx.opts.line_comment_char => {
+                let start = index;
                 while index < input.len() && input[index] != b'\r' && input[index] != b'\n' {
                     index += 1
                 }
+                if let Some(comments) = comments.as_mut() {
+                    comments.push(input[start..index].to_vec())
+                }
             }
             _ => return Ok((&input[index..], ())),
         }
@@ -27,18 +300,24 @@ fn space_or_comments(input: &[u8]) -> Res<()> {
     Ok((&[], ()))
 }
 
-fn unquoted_string_(input: &[u8]) -> Res<&[u8]> {
+fn unquoted_string_<'a>(input: &'a [u8], ctx: &Ctx) -> Res<'a, &'a [u8]> {
     for (index, &c) in input.iter().enumerate() {
         match c {
-            b';' | b'(' | b')' | b'"' | b' ' | b'\t' | b'\r' | b'\n' => {
+            b'(' | b')' | b'"' | b' ' | b'\t' | b'\r' | b'\n' => {
+                let (str, remaining) = input.split_at(index);
+                return Ok((remaining, str));
+            }
+            c if c == ctx.opts.line_comment_char => {
                 let (str, remaining) = input.split_at(index);
                 return Ok((remaining, str));
             }
             b'#' if index > 0 && input[index - 1] == b'|' => {
-                return Err(Error::UnexpectedCharInString(b'|'))
+                let offset = ctx.offset(input, index - 1);
+                return Err(Error::UnexpectedCharInString { c: b'|', offset });
             }
             b'|' if index > 0 && input[index - 1] == b'#' => {
-                return Err(Error::UnexpectedCharInString(b'#'))
+                let offset = ctx.offset(input, index - 1);
+                return Err(Error::UnexpectedCharInString { c: b'#', offset });
             }
             _ => {}
         }
@@ -46,13 +325,13 @@ fn unquoted_string_(input: &[u8]) -> Res<&[u8]> {
     Ok((&[], input))
 }
 
-fn unquoted_string(input: &[u8]) -> Res<Vec<u8>> {
-    match unquoted_string_(input) {
+fn unquoted_string<'a>(input: &'a [u8], ctx: &Ctx) -> Res<'a, &'a [u8]> {
+    match unquoted_string_(input, ctx) {
         Ok((next_input, atom)) => {
             if atom.is_empty() {
-                Err(Error::EmptyAtom)
+                Err(Error::EmptyAtom { offset: ctx.offset(input, 0) })
             } else {
-                Ok((next_input, atom.to_vec()))
+                Ok((next_input, atom))
             }
         }
         Err(err) => Err(err),
@@ -102,21 +381,66 @@ fn two_hex_digits(input: &[u8], index: usize) -> Option<u8> {
     Some(16 * d1 + d2)
 }
 
+// Decodes the code point of a `\uXXXX` or `\u{X...}` escape, index being the
+// one following the `u`. Returns the character and the number of bytes used
+// after the `u`. A high surrogate followed by a low surrogate escape, e.g.
+// `\ud83d\ude00`, is combined into a single character as in JSON.
+fn unicode_escape(input: &[u8], index: usize) -> Option<(char, usize)> {
+    let (code_point, len) = code_point_escape(input, index)?;
+    if (0xD800..0xDC00).contains(&code_point)
+        && input.get(index + len..index + len + 2) == Some(b"\\u")
+    {
+        let (low, low_len) = code_point_escape(input, index + len + 2)?;
+        if (0xDC00..0xE000).contains(&low) {
+            let code_point = 0x10000 + ((code_point - 0xD800) << 10) + (low - 0xDC00);
+            return Some((char::from_u32(code_point)?, len + 2 + low_len));
+        }
+    }
+    Some((char::from_u32(code_point)?, len))
+}
+
+fn code_point_escape(input: &[u8], index: usize) -> Option<(u32, usize)> {
+    let (digits, len) = if input.get(index) == Some(&b'{') {
+        let num_digits = input[index + 1..].iter().take(7).position(|&c| c == b'}')?;
+        if num_digits == 0 {
+            return None;
+        }
+        (&input[index + 1..index + 1 + num_digits], num_digits + 2)
+    } else {
+        (input.get(index..index + 4)?, 4)
+    };
+    let mut code_point = 0u32;
+    for i in 0..digits.len() {
+        code_point = 16 * code_point + hex_digit(digits, i)? as u32;
+    }
+    Some((code_point, len))
+}
+
 // Maybe this should be rewritten using combinators?
-fn quoted_string(input: &[u8]) -> Res<Vec<u8>> {
-    let mut buffer: Vec<u8> = Vec::new();
-    let mut index = 0;
+// The input starts after the opening quote. The result borrows from the input
+// when there is no escape sequence.
+fn quoted_string<'a>(input: &'a [u8], ctx: &Ctx) -> Res<'a, Cow<'a, [u8]>> {
+    let unexpected_eof = || Error::UnexpectedEofInString { offset: ctx.offset(input, 0) - 1 };
+    let index = match input.iter().position(|&c| c == b'"' || c == b'\\') {
+        None => return Err(unexpected_eof()),
+        Some(index) => index,
+    };
+    if input[index] == b'"' {
+        let (str, remaining) = input.split_at(index);
+        return Ok((remaining, Cow::Borrowed(str)));
+    }
+    let mut buffer: Vec<u8> = input[..index].to_vec();
+    let mut index = index;
     while index < input.len() {
         match input[index] {
             b'"' => {
                 let (_, remaining) = input.split_at(index);
-                return Ok((remaining, buffer));
+                return Ok((remaining, Cow::Owned(buffer)));
             }
             b'\\' => {
                 index += 1;
                 if index == input.len() {
-                    // Unexpected eof
-                    return Err(Error::UnexpectedEofInString);
+                    return Err(unexpected_eof());
                 }
                 match input[index] {
                     b'\n' => {
@@ -152,6 +476,16 @@ fn quoted_string(input: &[u8]) -> Res<Vec<u8>> {
                             buffer.push(b'x');
                         }
                     },
+                    b'u' => match unicode_escape(input, index + 1) {
+                        Some((c, len)) => {
+                            index += len;
+                            buffer.extend_from_slice(c.encode_utf8(&mut [0; 4]).as_bytes())
+                        }
+                        None => {
+                            buffer.push(b'\\');
+                            buffer.push(b'u');
+                        }
+                    },
                     c => match three_digits(input, index) {
                         Some(v) => {
                             index += 2;
@@ -168,67 +502,271 @@ fn quoted_string(input: &[u8]) -> Res<Vec<u8>> {
         };
         index += 1;
     }
-    Err(Error::UnexpectedEofInString)
+    Err(unexpected_eof())
+}
+
+/// Decodes a quoted string as produced by `escape_atom`, the input must start
+/// and end with a double quote. The escape sequences are the ones supported
+/// by the parser, including the decimal `\123`, hexadecimal `\xNN`, and
+/// unicode `\uXXXX` forms.
+///
+/// # Example
+///
+/// ```
+///     assert_eq!(rsexp::unescape_atom(br#""foo\n\065\x42""#).unwrap(), b"foo\nAB");
+///     assert!(rsexp::unescape_atom(b"foo").is_err());
+/// ```
+pub fn unescape_atom(data: &[u8]) -> Result<Vec<u8>, Error> {
+    let opts = ParseOptions::default();
+    let ctx = Ctx::new(data, &opts);
+    match data.first() {
+        None => return Err(Error::UnexpectedEof { offset: 0 }),
+        Some(b'"') => {}
+        Some(&c) => return Err(Error::UnexpectedChar { c, offset: 0 }),
+    }
+    let (remaining, atom) = quoted_string(&data[1..], &ctx)?;
+    // The remaining input starts with the closing quote.
+    match remaining.get(1) {
+        None => Ok(atom.into_owned()),
+        Some(&c) => Err(Error::UnexpectedChar { c, offset: ctx.offset(remaining, 1) }),
+    }
 }
 
 fn first_char_is(c: u8, input: &[u8]) -> bool {
     input.first().map(|x| *x == c).unwrap_or(false)
 }
 
-fn char(c: u8, input: &[u8]) -> Res<()> {
+fn char<'a>(c: u8, input: &'a [u8], ctx: &Ctx) -> Res<'a, ()> {
     if first_char_is(c, input) {
         Ok((&input[1..], ()))
     } else {
-        Err(Error::UnexpectedEof)
+        Err(Error::UnexpectedEof { offset: ctx.offset(input, 0) })
+    }
+}
+
+// Converts `#x1F`, `#o17`, `#b101` to their decimal representation.
+fn decimal_of_radix_atom(atom: &[u8]) -> Option<Vec<u8>> {
+    match atom {
+        [b'#', prefix, digits @ ..] => {
+            let radix = Radix::of_prefix(*prefix)?;
+            let digits = core::str::from_utf8(digits).ok()?;
+            let value = i128::from_str_radix(digits, radix.base()).ok()?;
+            Some(value.to_string().into_bytes())
+        }
+        _ => None,
     }
 }
 
-fn atom(input: &[u8]) -> Res<Sexp> {
-    let (next_input, atom) = if first_char_is(b'"', input) {
-        let (input, ()) = char(b'"', input)?;
-        let (input, atom) = quoted_string(input)?;
-        let (input, ()) = char(b'"', input)?;
-        (input, atom)
+fn atom<'a, S: Node<'a>>(input: &'a [u8], ctx: &Ctx) -> Res<'a, S> {
+    ctx.spend_node(input)?;
+    let (next_input, atom) = atom_bytes(input, ctx)?;
+    Ok((next_input, S::atom(atom)))
+}
+
+// Parses an atom, the result borrows from the input when possible.
+fn atom_bytes<'a>(input: &'a [u8], ctx: &Ctx) -> Res<'a, Cow<'a, [u8]>> {
+    if first_char_is(b'"', input) {
+        let (input, ()) = char(b'"', input, ctx)?;
+        let (input, atom) = quoted_string(input, ctx)?;
+        let (input, ()) = char(b'"', input, ctx)?;
+        Ok((input, atom))
     } else {
-        unquoted_string(input)?
-    };
-    Ok((next_input, Sexp::Atom(atom)))
+        let (input, atom) = unquoted_string(input, ctx)?;
+        if ctx.opts.radix_atoms {
+            Ok((input, decimal_of_radix_atom(atom).map_or(Cow::Borrowed(atom), Cow::Owned)))
+        } else {
+            Ok((input, Cow::Borrowed(atom)))
+        }
+    }
+}
+
+// A list being parsed by sexp_no_leading_blank_.
+struct Frame<S> {
+    // The elements parsed so far.
+    elems: Vec<S>,
+    // The number of datum comments waiting for the datum they discard.
+    discard: usize,
+    // Whether the list is within a datum comment, its elements are then
+    // validated but not built.
+    discarded: bool,
 }
 
-fn sexp_in_list(input: &[u8]) -> Res<Sexp> {
-    let (input, ()) = char(b'(', input)?;
-    let (input, ()) = space_or_comments(input)?;
+impl<S> Frame<S> {
+    fn new(discarded: bool) -> Self {
+        Frame { elems: vec![], discard: 0, discarded }
+    }
+}
+
+// Parses a sexp followed by blanks and comments. Lists are parsed with an
+// explicit stack of the lists being built rather than by recursion so that
+// deeply nested inputs do not overflow the call stack, datum comments are
+// handled within the same loop. When `skip_only` is set, only the datum
+// comments at the beginning of input are skipped and None is returned.
+fn sexp_no_leading_blank_<'a, S: Node<'a>>(
+    input: &'a [u8],
+    ctx: &Ctx,
+    skip_only: bool,
+) -> Res<'a, Option<S>> {
+    // The enclosing lists, the innermost list is the last one. The first
+    // frame holds the top-level sexp once parsed.
+    let mut stack: Vec<Frame<S>> = vec![Frame::new(false)];
     let mut input = input;
-    let mut res = vec![];
-    while let Ok((next_input, sexp)) = sexp_no_leading_blank(input) {
+    loop {
+        let top_level = stack.len() == 1;
+        let frame = stack.last_mut().unwrap();
+        if is_datum_comment(input, ctx) {
+            frame.discard += 1;
+            let (next_input, ()) = blanks(&input[2..], ctx, None)?;
+            input = next_input;
+            continue;
+        }
+        if top_level && frame.discard == 0 {
+            if let Some(sexp) = frame.elems.pop() {
+                return Ok((input, Some(sexp)));
+            }
+            if skip_only {
+                return Ok((input, None));
+            }
+        }
+        let keep = !frame.discarded && frame.discard == 0;
+        let sexp = match input.first() {
+            Some(b'(') => {
+                let depth = ctx.depth.get() + 1;
+                if depth > ctx.opts.max_depth {
+                    let offset = ctx.offset(input, 0);
+                    return Err(Error::MaxDepthExceeded { depth: ctx.opts.max_depth, offset });
+                }
+                ctx.spend_node(input)?;
+                ctx.depth.set(depth);
+                let (next_input, ()) = blanks(&input[1..], ctx, None)?;
+                input = next_input;
+                stack.push(Frame::new(!keep));
+                continue;
+            }
+            Some(b')') if !top_level && frame.discard == 0 => {
+                ctx.depth.set(ctx.depth.get() - 1);
+                input = &input[1..];
+                let frame = stack.pop().unwrap();
+                (!frame.discarded).then(|| S::list(frame.elems))
+            }
+            None if !top_level || frame.discard > 0 => {
+                return Err(Error::UnexpectedEof { offset: ctx.offset(input, 0) })
+            }
+            _ if keep => {
+                let (next_input, sexp) = atom(input, ctx)?;
+                input = next_input;
+                Some(sexp)
+            }
+            _ => {
+                ctx.spend_node(input)?;
+                let (next_input, _) = atom_bytes(input, ctx)?;
+                input = next_input;
+                None
+            }
+        };
+        let (next_input, ()) = blanks(input, ctx, None)?;
         input = next_input;
-        res.push(sexp)
+        let frame = stack.last_mut().unwrap();
+        if frame.discard > 0 {
+            frame.discard -= 1
+        } else if let Some(sexp) = sexp {
+            frame.elems.push(sexp)
+        }
     }
-    let (input, ()) = char(b')', input)?;
-    Ok((input, Sexp::List(res)))
 }
 
-// This is used to encode a list separated by spaces as the
-// separated_list combinator does not seem to handle separators that
-// can be empty.
-fn sexp_no_leading_blank(input: &[u8]) -> Res<Sexp> {
-    if first_char_is(b'(', input) {
-        let (input, sexp) = sexp_in_list(input)?;
-        let (input, ()) = space_or_comments(input)?;
-        Ok((input, sexp))
+fn sexp_no_leading_blank<'a, S: Node<'a>>(input: &'a [u8], ctx: &Ctx) -> Res<'a, S> {
+    let (input, sexp) = sexp_no_leading_blank_(input, ctx, false)?;
+    Ok((input, sexp.unwrap()))
+}
+
+/// The callbacks used by `parse_visit`.
+pub trait SexpVisitor {
+    /// Called for each atom, quoted atoms are unescaped.
+    fn atom(&mut self, bytes: &[u8]);
+    /// Called on the opening parenthesis of a list.
+    fn begin_list(&mut self);
+    /// Called on the closing parenthesis of a list.
+    fn end_list(&mut self);
+}
+
+/// Parses a sexp from bytes calling the visitor on each atom and at the start
+/// and end of each list rather than building a tree, so that values can be
+/// extracted from large documents without allocating the whole sexp. The
+/// nesting depth is not limited as the parser does not recurse. This
+/// fails if there are remaining bytes, the visitor may have received some
+/// events before an error is returned.
+///
+/// # Example
+///
+/// ```
+///     struct AtomCount(usize);
+///     impl rsexp::SexpVisitor for AtomCount {
+///         fn atom(&mut self, _bytes: &[u8]) {
+///             self.0 += 1
+///         }
+///         fn begin_list(&mut self) {}
+///         fn end_list(&mut self) {}
+///     }
+///     let mut count = AtomCount(0);
+///     rsexp::parse_visit(b"((foo bar) (baz (1 2 3)))", &mut count).unwrap();
+///     assert_eq!(count.0, 6);
+/// ```
+pub fn parse_visit<V: SexpVisitor + ?Sized>(input: &[u8], v: &mut V) -> Result<(), Error> {
+    // Nothing is built so the depth is not limited, including in the datum
+    // comments.
+    let opts = ParseOptions { max_depth: usize::MAX, ..Default::default() };
+    let ctx = Ctx::new(input, &opts);
+    let (mut input, ()) = space_or_comments(input, &ctx)?;
+    // The number of lists that have been started but not yet closed.
+    let mut depth = 0usize;
+    loop {
+        let next_input = match input.first() {
+            Some(b'(') => {
+                depth += 1;
+                v.begin_list();
+                &input[1..]
+            }
+            Some(b')') if depth > 0 => {
+                depth -= 1;
+                v.end_list();
+                &input[1..]
+            }
+            None if depth > 0 => return Err(Error::UnexpectedEof { offset: ctx.offset(input, 0) }),
+            _ => {
+                let (next_input, atom) = atom_bytes(input, &ctx)?;
+                v.atom(&atom);
+                next_input
+            }
+        };
+        (input, ()) = space_or_comments(next_input, &ctx)?;
+        if depth == 0 {
+            break;
+        }
+    }
+    if input.is_empty() {
+        Ok(())
     } else {
-        let (input, sexp) = atom(input)?;
-        let (input, ()) = space_or_comments(input)?;
-        Ok((input, sexp))
+        Err(Error::UnexpectedEof { offset: ctx.offset(input, 0) })
     }
 }
 
 /// Deserialize a Sexp from bytes, returning both the sexp and the remaining
 /// bytes.
-pub fn from_slice_allow_remaining<T: AsRef<[u8]> + ?Sized>(input: &T) -> Res<Sexp> {
+pub fn from_slice_allow_remaining<T: AsRef<[u8]> + ?Sized>(input: &T) -> Res<'_, Sexp> {
+    from_slice_allow_remaining_with_options(input, &ParseOptions::default())
+}
+
+/// Deserialize a Sexp from bytes using the given parsing options, returning
+/// both the sexp and the remaining bytes.
+pub fn from_slice_allow_remaining_with_options<'a, T: AsRef<[u8]> + ?Sized>(
+    input: &'a T,
+    opts: &ParseOptions,
+) -> Res<'a, Sexp> {
     let input = input.as_ref();
-    let (input, ()) = space_or_comments(input)?;
-    sexp_no_leading_blank(input)
+    let ctx = Ctx::new(input, opts);
+    let (input, ()) = space_or_comments(input, &ctx)?;
+    sexp_no_leading_blank(input, &ctx)
 }
 
 /// Deserialize a Sexp from bytes. This fails if there are remaining bytes.
@@ -248,12 +786,216 @@ pub fn from_slice_allow_remaining<T: AsRef<[u8]> + ?Sized>(input: &T) -> Res<Sex
 /// This deserialization can fail if the bytes do not follow the expected
 /// sexp format.
 pub fn from_slice<T: AsRef<[u8]> + ?Sized>(input: &T) -> Result<Sexp, Error> {
+    from_slice_with_options(input, &ParseOptions::default())
+}
+
+/// Parses a Sexp with `from_slice`, failing if there are remaining bytes.
+///
+/// # Example
+///
+/// ```
+///     let sexp: rsexp::Sexp = "(foo (bar baz))".parse().unwrap();
+///     assert_eq!(sexp.to_bytes(), b"(foo (bar baz))");
+///     assert!("(foo) bar".parse::<rsexp::Sexp>().is_err());
+/// ```
+impl core::str::FromStr for Sexp {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        from_slice(s)
+    }
+}
+
+/// Parses a Sexp with `from_slice`, failing if there are remaining bytes.
+///
+/// # Example
+///
+/// ```
+///     let sexp = rsexp::Sexp::try_from("(foo bar)").unwrap();
+///     assert_eq!(sexp, rsexp::Sexp::try_from(&b"(foo bar)"[..]).unwrap());
+/// ```
+impl TryFrom<&str> for Sexp {
+    type Error = Error;
+
+    fn try_from(s: &str) -> Result<Self, Self::Error> {
+        from_slice(s)
+    }
+}
+
+impl TryFrom<&[u8]> for Sexp {
+    type Error = Error;
+
+    fn try_from(s: &[u8]) -> Result<Self, Self::Error> {
+        from_slice(s)
+    }
+}
+
+/// Deserialize a Sexp from bytes using the given parsing options. This fails
+/// if there are remaining bytes.
+///
+/// # Example
+///
+/// ```
+///     let opts = rsexp::ParseOptions { radix_atoms: true, ..Default::default() };
+///     let sexp = rsexp::from_slice_with_options(b"(#x1F #b101 foo)", &opts).unwrap();
+///     assert_eq!(sexp.to_bytes(), b"(31 5 foo)");
+/// ```
+pub fn from_slice_with_options<T: AsRef<[u8]> + ?Sized>(
+    input: &T,
+    opts: &ParseOptions,
+) -> Result<Sexp, Error> {
     let input = input.as_ref();
-    let (remaining, sexp) = from_slice_allow_remaining(input)?;
+    let (remaining, sexp) = from_slice_allow_remaining_with_options(input, opts)?;
     if remaining.is_empty() {
         Ok(sexp)
     } else {
-        Err(Error::UnexpectedEof)
+        Err(Error::UnexpectedEof { offset: input.len() - remaining.len() })
+    }
+}
+
+/// Deserialize a Sexp from bytes and returns the value found by following
+/// `path` through nested lists of `(key value)` pairs, see `Sexp::field`.
+/// The keys in `path` are separated by `/` and the empty path designates the
+/// whole sexp. This returns `Ok(None)` if some key is missing.
+///
+/// # Example
+///
+/// ```
+///     let input = b"((server ((host localhost) (port 8080))) (debug true))";
+///     let port = rsexp::lookup(input, "server/port").unwrap();
+///     assert_eq!(port, Some(rsexp::atom(b"8080")));
+///     assert_eq!(rsexp::lookup(input, "server/user").unwrap(), None);
+/// ```
+///
+/// # Errors
+///
+/// This fails if the bytes do not follow the expected sexp format.
+pub fn lookup<T: AsRef<[u8]> + ?Sized>(input: &T, path: &str) -> Result<Option<Sexp>, Error> {
+    let sexp = from_slice(input)?;
+    if path.is_empty() {
+        return Ok(Some(sexp));
+    }
+    let mut value = &sexp;
+    for key in path.split('/') {
+        match value.field(key.as_bytes()) {
+            Some(v) => value = v,
+            None => return Ok(None),
+        }
+    }
+    Ok(Some(value.clone()))
+}
+
+/// Deserialize a Sexp from bytes, failing with `Error::MaxDepthExceeded` if
+/// lists are nested more than `max_depth` levels deep.
+///
+/// # Example
+///
+/// ```
+///     assert!(rsexp::from_slice_with_depth_limit(b"((a) b)", 2).is_ok());
+///     let err = rsexp::from_slice_with_depth_limit(b"((a) b)", 1).unwrap_err();
+///     assert_eq!(err, rsexp::Error::MaxDepthExceeded { depth: 1, offset: 1 });
+/// ```
+pub fn from_slice_with_depth_limit<T: AsRef<[u8]> + ?Sized>(
+    input: &T,
+    max_depth: usize,
+) -> Result<Sexp, Error> {
+    from_slice_with_options(input, &ParseOptions { max_depth, ..Default::default() })
+}
+
+/// Deserialize a Sexp from bytes keeping the line comments, each comment is
+/// attached to the value that follows it or, when followed by a closing
+/// parenthesis, to the enclosing list. Block and datum comments as well as
+/// the comments following the whole sexp are dropped. This fails if there are
+/// remaining bytes.
+///
+/// # Example
+///
+/// ```
+///     let input = b"((name foo) ; the name\n ; the size\n (size 42))";
+///     let sexp = rsexp::from_slice_with_comments(input).unwrap();
+///     assert_eq!(sexp.to_sexp(), rsexp::from_slice(input).unwrap());
+///     let rsexp::CommentedValue::List { elems, .. } = &sexp.value else { panic!() };
+///     assert_eq!(elems[1].comments, [b"; the name".to_vec(), b"; the size".to_vec()]);
+/// ```
+pub fn from_slice_with_comments<T: AsRef<[u8]> + ?Sized>(
+    input: &T,
+) -> Result<CommentedSexp, Error> {
+    let input = input.as_ref();
+    let opts = ParseOptions::default();
+    let ctx = Ctx::new(input, &opts);
+    let mut comments = vec![];
+    let (mut input, ()) = space_or_comments_collecting(input, &ctx, Some(&mut comments))?;
+    // Similar to sexp_no_leading_blank, the stack holds the comments and the
+    // elements parsed so far for each of the enclosing lists.
+    let mut stack: Vec<(Vec<Vec<u8>>, Vec<CommentedSexp>)> = vec![];
+    loop {
+        let value = match input.first() {
+            Some(b'(') => {
+                let depth = ctx.depth.get() + 1;
+                if depth > ctx.opts.max_depth {
+                    let offset = ctx.offset(input, 0);
+                    return Err(Error::MaxDepthExceeded { depth: ctx.opts.max_depth, offset });
+                }
+                ctx.depth.set(depth);
+                stack.push((core::mem::take(&mut comments), vec![]));
+                let (next_input, ()) =
+                    space_or_comments_collecting(&input[1..], &ctx, Some(&mut comments))?;
+                input = next_input;
+                continue;
+            }
+            Some(b')') if !stack.is_empty() => {
+                ctx.depth.set(ctx.depth.get() - 1);
+                let trailing_comments = core::mem::take(&mut comments);
+                let (list_comments, elems) = stack.pop().unwrap();
+                comments = list_comments;
+                input = &input[1..];
+                CommentedValue::List { elems, trailing_comments }
+            }
+            None if !stack.is_empty() => {
+                return Err(Error::UnexpectedEof { offset: ctx.offset(input, 0) })
+            }
+            _ => {
+                let (next_input, atom) = atom::<Sexp>(input, &ctx)?;
+                input = next_input;
+                match atom {
+                    Sexp::Atom(atom) => CommentedValue::Atom(atom),
+                    Sexp::List(_) => unreachable!(),
+                }
+            }
+        };
+        let sexp = CommentedSexp { comments: core::mem::take(&mut comments), value };
+        let mut next_comments = vec![];
+        let (next_input, ()) = space_or_comments_collecting(input, &ctx, Some(&mut next_comments))?;
+        input = next_input;
+        match stack.last_mut() {
+            None if input.is_empty() => return Ok(sexp),
+            None => return Err(Error::UnexpectedEof { offset: ctx.offset(input, 0) }),
+            Some((_, elems)) => elems.push(sexp),
+        }
+        comments = next_comments;
+    }
+}
+
+/// Deserialize a SexpRef from bytes, atoms borrow from the input unless they
+/// contain escape sequences. This fails if there are remaining bytes.
+///
+/// # Example
+///
+/// ```
+///     let input = b"(foo \"bar\")";
+///     let sexp = rsexp::from_slice_ref(input).unwrap();
+///     assert_eq!(sexp.to_owned(), rsexp::from_slice(input).unwrap());
+/// ```
+pub fn from_slice_ref<T: AsRef<[u8]> + ?Sized>(input: &T) -> Result<SexpRef<'_>, Error> {
+    let input = input.as_ref();
+    let opts = ParseOptions::default();
+    let ctx = Ctx::new(input, &opts);
+    let (remaining, ()) = space_or_comments(input, &ctx)?;
+    let (remaining, sexp) = sexp_no_leading_blank(remaining, &ctx)?;
+    if remaining.is_empty() {
+        Ok(sexp)
+    } else {
+        Err(Error::UnexpectedEof { offset: input.len() - remaining.len() })
     }
 }
 
@@ -271,26 +1013,302 @@ pub fn from_slice<T: AsRef<[u8]> + ?Sized>(input: &T) -> Result<Sexp, Error> {
 ///
 /// This deserialization can fail if the bytes do not follow the expected
 /// sexp format.
-
 pub fn from_slice_multi<T: AsRef<[u8]> + ?Sized>(input: &T) -> Result<Vec<Sexp>, Error> {
+    from_slice_multi_with_options(input, &ParseOptions::default())
+}
+
+/// Deserialize multiple Sexps from bytes using the given parsing options.
+pub fn from_slice_multi_with_options<T: AsRef<[u8]> + ?Sized>(
+    input: &T,
+    opts: &ParseOptions,
+) -> Result<Vec<Sexp>, Error> {
     let input = input.as_ref();
-    let (input, ()) = space_or_comments(input)?;
+    let ctx = Ctx::new(input, opts);
+    let (input, ()) = space_or_comments(input, &ctx)?;
     let mut input = input;
     let mut sexps = vec![];
-    while let Ok((next_input, sexp)) = sexp_no_leading_blank(input) {
+    while !input.is_empty() {
+        let (next_input, sexp) = sexp_no_leading_blank(input, &ctx)?;
         input = next_input;
         sexps.push(sexp)
     }
-    if input.is_empty() {
-        Ok(sexps)
-    } else {
-        Err(Error::UnexpectedEof)
+    Ok(sexps)
+}
+
+/// Iterates over the Sexps contained in some bytes, parsing them one at a
+/// time. A parse error is returned as the last item of the iterator.
+///
+/// # Example
+///
+/// ```
+///   let sexps: Vec<_> = rsexp::iter_from_slice(b"(foo bar) baz").collect();
+///   assert_eq!(sexps, [rsexp::from_slice(b"(foo bar)"), Ok(rsexp::atom(b"baz"))]);
+/// ```
+pub fn iter_from_slice<T: AsRef<[u8]> + ?Sized>(
+    input: &T,
+) -> impl Iterator<Item = Result<Sexp, Error>> + '_ {
+    let input = input.as_ref();
+    let opts = ParseOptions::default();
+    let mut remaining = Some(input);
+    core::iter::from_fn(move || {
+        let ctx = Ctx::new(input, &opts);
+        let sexp = match space_or_comments(remaining?, &ctx) {
+            Ok(([], ())) => {
+                remaining = None;
+                return None;
+            }
+            Ok((next_input, ())) => sexp_no_leading_blank(next_input, &ctx),
+            Err(err) => Err(err),
+        };
+        match sexp {
+            Ok((next_input, sexp)) => {
+                remaining = Some(next_input);
+                Some(Ok(sexp))
+            }
+            Err(err) => {
+                remaining = None;
+                Some(Err(err))
+            }
+        }
+    })
+}
+
+// Parses a length prefixed atom `<len>:<bytes>` starting at index, returns the
+// atom and the index following it.
+fn canonical_atom(input: &[u8], index: usize) -> Result<(&[u8], usize), Error> {
+    let digits = input[index..].iter().take_while(|c| c.is_ascii_digit()).count();
+    // Leading zeros are rejected so that the representation stays unique.
+    if digits > 1 && input[index] == b'0' {
+        return Err(Error::UnexpectedChar { c: b'0', offset: index });
+    }
+    let colon = index + digits;
+    match input.get(colon) {
+        Some(b':') if digits > 0 => {}
+        Some(&c) => return Err(Error::UnexpectedChar { c, offset: colon }),
+        None => return Err(Error::UnexpectedEof { offset: colon }),
+    }
+    let truncated = Error::UnexpectedEof { offset: input.len() };
+    // The digits are ASCII so this cannot fail, overflows mean that the atom
+    // is longer than the input.
+    let len: usize =
+        core::str::from_utf8(&input[index..colon]).unwrap().parse().or(Err(truncated.clone()))?;
+    let start = colon + 1;
+    match start.checked_add(len) {
+        Some(end) if end <= input.len() => Ok((&input[start..end], end)),
+        _ => Err(truncated),
+    }
+}
+
+// Decodes the base64 content of the transport encoding, whitespace is ignored.
+// `offset` is the offset of data in the whole input and is used for errors.
+fn decode_base64(data: &[u8], offset: usize) -> Result<Vec<u8>, Error> {
+    let mut res = Vec::with_capacity(data.len() * 3 / 4);
+    let (mut acc, mut bits, mut padding) = (0u32, 0, false);
+    for (index, &c) in data.iter().enumerate() {
+        let value = match c {
+            b'A'..=b'Z' => c - b'A',
+            b'a'..=b'z' => c - b'a' + 26,
+            b'0'..=b'9' => c - b'0' + 52,
+            b'+' => 62,
+            b'/' => 63,
+            b' ' | b'\t' | b'\r' | b'\n' => continue,
+            b'=' => {
+                padding = true;
+                continue;
+            }
+            c => return Err(Error::UnexpectedChar { c, offset: offset + index }),
+        };
+        if padding {
+            return Err(Error::UnexpectedChar { c, offset: offset + index });
+        }
+        acc = (acc << 6) | value as u32;
+        bits += 6;
+        if bits >= 8 {
+            bits -= 8;
+            res.push((acc >> bits) as u8);
+            acc &= (1 << bits) - 1;
+        }
+    }
+    Ok(res)
+}
+
+/// Deserialize a Sexp from its canonical representation as produced by
+/// `Sexp::to_bytes_canonical`, e.g. `(3:foo3:bar)`. No whitespace is allowed
+/// and this fails if there are remaining bytes. Lists are parsed using an
+/// explicit stack so deeply nested inputs do not overflow the stack.
+///
+/// The base64 transport encoding, where the canonical representation is
+/// base64 encoded and wrapped in braces, e.g. `{KDM6Zm9vMzpiYXIp}`, is also
+/// accepted. The offsets of the errors detected in the decoded representation
+/// refer to the decoded bytes.
+///
+/// # Example
+///
+/// ```
+///     let sexp = rsexp::from_slice_canonical(b"(3:foo(5:a (b)))").unwrap();
+///     assert_eq!(sexp.to_bytes(), b"(foo (\"a (b)\"))");
+///     let sexp = rsexp::from_slice_canonical(b"{KDM6Zm9vMzpiYXIp}").unwrap();
+///     assert_eq!(sexp.to_bytes(), b"(foo bar)");
+/// ```
+pub fn from_slice_canonical<T: AsRef<[u8]> + ?Sized>(input: &T) -> Result<Sexp, Error> {
+    let input = input.as_ref();
+    if input.first() != Some(&b'{') {
+        return canonical(input);
+    }
+    match input.iter().position(|&c| c == b'}') {
+        None => Err(Error::UnexpectedEof { offset: input.len() }),
+        Some(end) if end + 1 < input.len() => Err(Error::UnexpectedEof { offset: end + 1 }),
+        Some(end) => canonical(&decode_base64(&input[1..end], 1)?),
+    }
+}
+
+fn canonical(input: &[u8]) -> Result<Sexp, Error> {
+    let mut stack: Vec<Vec<Sexp>> = vec![];
+    let mut index = 0;
+    loop {
+        let sexp = match input.get(index) {
+            None => return Err(Error::UnexpectedEof { offset: index }),
+            Some(b'(') => {
+                stack.push(vec![]);
+                index += 1;
+                continue;
+            }
+            Some(b')') => match stack.pop() {
+                None => return Err(Error::UnexpectedChar { c: b')', offset: index }),
+                Some(list) => {
+                    index += 1;
+                    Sexp::List(list)
+                }
+            },
+            Some(_) => {
+                let (atom, next_index) = canonical_atom(input, index)?;
+                index = next_index;
+                Sexp::Atom(atom.to_vec())
+            }
+        };
+        match stack.last_mut() {
+            Some(list) => list.push(sexp),
+            None if index == input.len() => return Ok(sexp),
+            None => return Err(Error::UnexpectedEof { offset: index }),
+        }
+    }
+}
+
+/// Shrinks an input that `from_slice` fails to parse to a small slice failing
+/// with the same kind of error, e.g. to attach a minimal reproduction to a bug
+/// report. This uses delta debugging: byte ranges of decreasing size are
+/// removed as long as the remaining bytes still fail with the same `Error`
+/// variant. The input is returned unchanged if it parses successfully.
+///
+/// # Example
+///
+/// ```
+///     let input = b"((foo bar) (baz \"qux\") (a (b (c d|#e))))";
+///     let minimized = rsexp::minimize_parse_failure(input);
+///     assert_eq!(minimized, b"|#");
+/// ```
+pub fn minimize_parse_failure(input: &[u8]) -> Vec<u8> {
+    let variant = match from_slice(input) {
+        Ok(_) => return input.to_vec(),
+        Err(err) => core::mem::discriminant(&err),
+    };
+    let fails = |input: &[u8]| match from_slice(input) {
+        Ok(_) => false,
+        Err(err) => core::mem::discriminant(&err) == variant,
+    };
+    let mut input = input.to_vec();
+    let mut n_chunks = 2;
+    while !input.is_empty() {
+        n_chunks = n_chunks.min(input.len());
+        let chunk_len = input.len().div_ceil(n_chunks);
+        let mut reduced = None;
+        for start in (0..input.len()).step_by(chunk_len) {
+            let end = usize::min(start + chunk_len, input.len());
+            if n_chunks > 1 && fails(&input[start..end]) {
+                reduced = Some((input[start..end].to_vec(), 2));
+                break;
+            }
+            let mut complement = input[..start].to_vec();
+            complement.extend_from_slice(&input[end..]);
+            if fails(&complement) {
+                reduced = Some((complement, usize::max(n_chunks - 1, 2)));
+                break;
+            }
+        }
+        match reduced {
+            Some((reduced, n)) => {
+                input = reduced;
+                n_chunks = n;
+            }
+            None if n_chunks == input.len() => break,
+            None => n_chunks = usize::min(2 * n_chunks, input.len()),
+        }
+    }
+    input
+}
+
+/// Parses sexps one at a time while sharing a total node budget across all of
+/// them, e.g. to bound the work done when processing a batch of untrusted
+/// inputs. Each atom and each list counts as one node.
+///
+/// # Example
+///
+/// ```
+///     let mut parser = rsexp::BatchParser::new(5);
+///     let (sexp, remaining) = parser.parse_next(b"(a b) c").unwrap();
+///     assert_eq!(sexp.to_bytes(), b"(a b)");
+///     assert_eq!(parser.remaining_budget(), 2);
+///     let err = parser.parse_next(b"(d e)").unwrap_err();
+///     assert_eq!(err, rsexp::Error::BudgetExhausted { offset: 3 });
+/// ```
+#[derive(Clone, Debug)]
+pub struct BatchParser {
+    budget: usize,
+    opts: ParseOptions,
+}
+
+impl BatchParser {
+    pub fn new(total_node_budget: usize) -> Self {
+        Self::new_with_options(total_node_budget, ParseOptions::default())
+    }
+
+    pub fn new_with_options(total_node_budget: usize, opts: ParseOptions) -> Self {
+        BatchParser { budget: total_node_budget, opts }
+    }
+
+    /// The number of nodes that can still be parsed.
+    pub fn remaining_budget(&self) -> usize {
+        self.budget
+    }
+
+    /// Parses the next sexp from `input`, returning it together with the
+    /// remaining bytes. Fails with `Error::BudgetExhausted` when the total
+    /// node budget has been used up, the nodes parsed before the failure are
+    /// still deducted from the budget.
+    pub fn parse_next<'a, T: AsRef<[u8]> + ?Sized>(
+        &mut self,
+        input: &'a T,
+    ) -> Result<(Sexp, &'a [u8]), Error> {
+        let input = input.as_ref();
+        let ctx = Ctx::new(input, &self.opts);
+        ctx.budget.set(Some(self.budget));
+        let res = space_or_comments(input, &ctx)
+            .and_then(|(input, ())| sexp_no_leading_blank::<Sexp>(input, &ctx));
+        self.budget = ctx.budget.get().unwrap_or(0);
+        let (remaining, sexp) = res?;
+        Ok((sexp, remaining))
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::{from_slice, from_slice_multi, Sexp};
+    use crate::{
+        from_slice, from_slice_multi, from_slice_multi_with_options, from_slice_ref,
+        from_slice_with_depth_limit, from_slice_with_options, iter_from_slice, line_col,
+        minimize_parse_failure, parse_visit, radix_atom, BatchParser, Error, ParseOptions, Radix,
+        Sexp, SexpRef, SexpVisitor, DEFAULT_MAX_DEPTH,
+    };
+    use std::borrow::Cow;
 
     fn atom(b: &[u8]) -> Sexp {
         Sexp::Atom(b.to_vec())
@@ -322,4 +1340,382 @@ mod tests {
         assert_eq!(from_slice_multi(b"(\t\t\t)()"), Ok(vec![list(&[]), list(&[])]));
         assert_eq!(from_slice_multi(b"(\"\\\\\\n\")"), Ok(vec![list(&[atom(b"\\\n")])]));
     }
+
+    #[test]
+    fn unicode_escapes() {
+        assert_eq!(from_slice(br#""\u00e9t\u00E9""#), Ok(atom("été".as_bytes())));
+        assert_eq!(from_slice(br#""\u20ac\u0041""#), Ok(atom("€A".as_bytes())));
+        assert_eq!(from_slice(br#""\u{1F600}\u{41}""#), Ok(atom("😀A".as_bytes())));
+        // Invalid escapes are kept verbatim.
+        assert_eq!(from_slice(br#""\u00e""#), Ok(atom(br"\u00e")));
+        assert_eq!(from_slice(br#""\uZZZZ""#), Ok(atom(br"\uZZZZ")));
+        assert_eq!(from_slice(br#""\ud800""#), Ok(atom(br"\ud800")));
+        // Surrogate pairs are combined, lone surrogates are kept verbatim.
+        assert_eq!(from_slice(br#""\ud83d\ude00!""#), Ok(atom("😀!".as_bytes())));
+        assert_eq!(from_slice(br#""\uD83D\uDE00""#), Ok(atom("😀".as_bytes())));
+        assert_eq!(from_slice(br#""\ud83dx""#), Ok(atom(br"\ud83dx")));
+        assert_eq!(from_slice(br#""\ud83d\u0041""#), Ok(atom(br"\ud83dA")));
+        assert_eq!(from_slice(br#""\ude00\ud83d""#), Ok(atom(br"\ude00\ud83d")));
+        assert_eq!(
+            from_slice(br#""\u{}\u{110000}\u{1234567}""#),
+            Ok(atom(br"\u{}\u{110000}\u{1234567}"))
+        );
+        assert_eq!(from_slice(br#""\u{41""#), Ok(atom(br"\u{41")));
+    }
+
+    #[test]
+    fn block_comments() {
+        assert_eq!(from_slice(b"#| comment |# (a b)"), Ok(list(&[atom(b"a"), atom(b"b")])));
+        assert_eq!(from_slice(b"(a #| comment |# b)"), Ok(list(&[atom(b"a"), atom(b"b")])));
+        assert_eq!(from_slice(b"(a #||#b)"), Ok(list(&[atom(b"a"), atom(b"b")])));
+        assert_eq!(
+            from_slice(b"(a#||#b)"),
+            Err(Error::UnexpectedCharInString { c: b'#', offset: 2 })
+        );
+        assert_eq!(
+            from_slice(b"(a #| outer #| inner |# still (commented |# b) #||#"),
+            Ok(list(&[atom(b"a"), atom(b"b")]))
+        );
+        assert_eq!(
+            from_slice(b"(#|\n multi\n line\n|#a ; line comment\n #|;|# b)"),
+            Ok(list(&[atom(b"a"), atom(b"b")]))
+        );
+        assert_eq!(from_slice(b"(\"#|\" \"|#\")"), Ok(list(&[atom(b"#|"), atom(b"|#")])));
+        assert_eq!(from_slice_multi(b"#| a |# #| b |#"), Ok(vec![]));
+        assert_eq!(
+            from_slice(b"#| unterminated"),
+            Err(Error::UnterminatedBlockComment { offset: 0 })
+        );
+        assert_eq!(
+            from_slice(b"(a #| #| |# b)"),
+            Err(Error::UnterminatedBlockComment { offset: 3 })
+        );
+        assert_eq!(from_slice_multi(b"a #|"), Err(Error::UnterminatedBlockComment { offset: 2 }));
+    }
+
+    #[test]
+    fn datum_comments() {
+        assert_eq!(from_slice(b"(a #;ignored b)"), Ok(list(&[atom(b"a"), atom(b"b")])));
+        assert_eq!(from_slice(b"(a #; \"ignored\" b)"), Ok(list(&[atom(b"a"), atom(b"b")])));
+        assert_eq!(
+            from_slice(b"(a #;(ignored (nested thing)) b)"),
+            Ok(list(&[atom(b"a"), atom(b"b")]))
+        );
+        assert_eq!(
+            from_slice(b"(a #;\n ; line\n #|block|# (x) b)"),
+            Ok(list(&[atom(b"a"), atom(b"b")]))
+        );
+        assert_eq!(from_slice(b"(a #;#;b c d)"), Ok(list(&[atom(b"a"), atom(b"d")])));
+        assert_eq!(from_slice(b"(a #;b)"), Ok(list(&[atom(b"a")])));
+        assert_eq!(from_slice(b"#;(a b) c"), Ok(atom(b"c")));
+        assert_eq!(from_slice_multi(b"#;a b #;c d #;e"), Ok(vec![atom(b"b"), atom(b"d")]));
+        assert_eq!(from_slice_multi(b"#;(a b)"), Ok(vec![]));
+        assert_eq!(from_slice(b"a#;b"), Ok(atom(b"a#")));
+        assert_eq!(from_slice(b"(a #;)"), Err(Error::EmptyAtom { offset: 5 }));
+        assert_eq!(from_slice_multi(b"a #;"), Err(Error::UnexpectedEof { offset: 4 }));
+        assert_eq!(from_slice_multi(b"a #;(b"), Err(Error::UnexpectedEof { offset: 6 }));
+    }
+
+    #[test]
+    fn errors_in_lists() {
+        assert_eq!(from_slice(b"(a b"), Err(Error::UnexpectedEof { offset: 4 }));
+        assert_eq!(from_slice(b"(a (b)"), Err(Error::UnexpectedEof { offset: 6 }));
+        assert_eq!(from_slice(b"(a \"b)"), Err(Error::UnexpectedEofInString { offset: 3 }));
+        assert_eq!(
+            from_slice(b"(a b|#)"),
+            Err(Error::UnexpectedCharInString { c: b'|', offset: 4 })
+        );
+        assert_eq!(from_slice_multi(b"(a) b)"), Err(Error::EmptyAtom { offset: 5 }));
+    }
+
+    #[test]
+    fn error_offsets() {
+        assert_eq!(from_slice(b"(a) b"), Err(Error::UnexpectedEof { offset: 4 }));
+        assert_eq!(from_slice(b""), Err(Error::EmptyAtom { offset: 0 }));
+        assert_eq!(from_slice(b"  )"), Err(Error::EmptyAtom { offset: 2 }));
+        let input = b"((foo bar)\n (baz \"qux\n  quux))\n";
+        let err = from_slice(input).unwrap_err();
+        assert_eq!(err, Error::UnexpectedEofInString { offset: 17 });
+        assert_eq!(err.line_col(input), (2, 7));
+        let input = b"(a\n b\n ; comment\n (c d)\n  e|#f)";
+        let err = from_slice(input).unwrap_err();
+        assert_eq!(err.offset(), 27);
+        assert_eq!(err.line_col(input), (5, 4));
+        assert_eq!(line_col(input, 0), (1, 1));
+        assert_eq!(line_col(input, 2), (1, 3));
+        assert_eq!(line_col(input, 3), (2, 1));
+    }
+
+    #[test]
+    fn depth_limit() {
+        let nested = |depth: usize| "(".repeat(depth) + &")".repeat(depth);
+        assert_eq!(from_slice_with_depth_limit(b"a", 0), Ok(atom(b"a")));
+        assert_eq!(
+            from_slice_with_depth_limit(b"()", 0),
+            Err(Error::MaxDepthExceeded { depth: 0, offset: 0 })
+        );
+        assert_eq!(
+            from_slice_with_depth_limit(b"(() (()) ())", 3).unwrap().to_bytes(),
+            b"(() (()) ())"
+        );
+        assert_eq!(
+            from_slice_with_depth_limit(b"(() (()) ())", 2),
+            Err(Error::MaxDepthExceeded { depth: 2, offset: 5 })
+        );
+        assert_eq!(
+            from_slice_with_depth_limit(&nested(10), 10).unwrap().to_bytes(),
+            nested(10).as_bytes()
+        );
+        assert_eq!(
+            from_slice_with_depth_limit(&nested(11), 10),
+            Err(Error::MaxDepthExceeded { depth: 10, offset: 10 })
+        );
+        assert_eq!(
+            from_slice_with_depth_limit(b"(a #;((b)) c)", 2),
+            Err(Error::MaxDepthExceeded { depth: 2, offset: 6 })
+        );
+        assert!(from_slice(&nested(DEFAULT_MAX_DEPTH)).is_ok());
+        let depth_error =
+            Error::MaxDepthExceeded { depth: DEFAULT_MAX_DEPTH, offset: DEFAULT_MAX_DEPTH };
+        assert_eq!(from_slice(&nested(DEFAULT_MAX_DEPTH + 1)), Err(depth_error.clone()));
+        assert_eq!(from_slice_ref(&nested(DEFAULT_MAX_DEPTH + 1)), Err(depth_error.clone()));
+        // Deeply nested adversarial inputs result in an error rather than a stack
+        // overflow.
+        let input = "(".repeat(1_000_000);
+        assert_eq!(from_slice(&input), Err(depth_error.clone()));
+        assert_eq!(from_slice_ref(&input), Err(depth_error));
+        assert_eq!(
+            from_slice_with_depth_limit(&input, usize::MAX),
+            Err(Error::UnexpectedEof { offset: 1_000_000 })
+        );
+        assert!(from_slice_multi(&input).is_err());
+    }
+
+    #[derive(Debug, PartialEq)]
+    enum Event {
+        Atom(Vec<u8>),
+        BeginList,
+        EndList,
+    }
+
+    impl SexpVisitor for Vec<Event> {
+        fn atom(&mut self, bytes: &[u8]) {
+            self.push(Event::Atom(bytes.to_vec()))
+        }
+
+        fn begin_list(&mut self) {
+            self.push(Event::BeginList)
+        }
+
+        fn end_list(&mut self) {
+            self.push(Event::EndList)
+        }
+    }
+
+    fn events_of_sexp(sexp: &Sexp, events: &mut Vec<Event>) {
+        match sexp {
+            Sexp::Atom(atom) => events.push(Event::Atom(atom.clone())),
+            Sexp::List(list) => {
+                events.push(Event::BeginList);
+                list.iter().for_each(|s| events_of_sexp(s, events));
+                events.push(Event::EndList)
+            }
+        }
+    }
+
+    #[test]
+    fn visitor() {
+        let inputs = [
+            "foo",
+            "  \"foo bar\\n\" ; comment",
+            "()",
+            "((foo bar) (baz (1 2 3)) () \"\")",
+            "(a #| block |# b #;(datum comment) c ; line\n d)",
+            "(((\"\\065\")))",
+        ];
+        for input in inputs {
+            let mut events = vec![];
+            parse_visit(input.as_bytes(), &mut events).unwrap();
+            let mut expected = vec![];
+            events_of_sexp(&from_slice(input).unwrap(), &mut expected);
+            assert_eq!(events, expected, "{input}");
+        }
+        // Errors match the ones from the tree parser.
+        for input in ["", "(a b", "(a b))", "a b", ")", "(\"a)", "(a #| b)", "(a|#)"] {
+            let err = parse_visit(input.as_bytes(), &mut vec![]).unwrap_err();
+            assert_eq!(Err(err), from_slice(input), "{input}");
+        }
+        // The nesting depth is not limited.
+        let depth = 2 * DEFAULT_MAX_DEPTH;
+        let input = [vec![b'('; depth], vec![b')'; depth]].concat();
+        let mut events = vec![];
+        parse_visit(&input, &mut events).unwrap();
+        assert_eq!(events.len(), 2 * depth);
+    }
+
+    #[test]
+    fn minimize() {
+        let mut input: Vec<u8> =
+            (0..200).flat_map(|i| format!("(foo \"bar {i}\" (baz {i})) ").into_bytes()).collect();
+        input.extend_from_slice(b"(a (b \"unterminated c) d)");
+        input.extend((0..200).flat_map(|i| format!("(qux {i})").into_bytes()));
+        let input = [b"(".as_slice(), &input, b")"].concat();
+        assert_eq!(from_slice(&input), Err(Error::UnexpectedEofInString { offset: 4987 }));
+        let minimized = minimize_parse_failure(&input);
+        assert_eq!(minimized, b"\"");
+        assert_eq!(from_slice(&minimized), Err(Error::UnexpectedEofInString { offset: 0 }));
+
+        let input = b"((a b) (c d) e) (f g)";
+        assert!(matches!(from_slice(input), Err(Error::UnexpectedEof { .. })));
+        let minimized = minimize_parse_failure(input);
+        assert!(minimized.len() <= 3);
+        assert!(matches!(from_slice(&minimized), Err(Error::UnexpectedEof { .. })));
+        assert_eq!(minimize_parse_failure(b""), b"");
+        assert_eq!(minimize_parse_failure(b"(a b)"), b"(a b)");
+    }
+
+    #[test]
+    fn line_comment_char() {
+        let opts = ParseOptions { line_comment_char: b'#', ..Default::default() };
+        assert_eq!(
+            from_slice_with_options(b"# header\n(a # trailing\n b;c d#e\n)", &opts),
+            Ok(list(&[atom(b"a"), atom(b"b;c"), atom(b"d")]))
+        );
+        // Block and datum comments are not recognized with `#` line comments.
+        assert_eq!(
+            from_slice_multi_with_options(b"a #|b\n c #;d\n e", &opts),
+            Ok(vec![atom(b"a"), atom(b"c"), atom(b"e")])
+        );
+        assert_eq!(
+            from_slice_with_options(b"(a b|#\n)", &opts),
+            Ok(list(&[atom(b"a"), atom(b"b|")]))
+        );
+        assert_eq!(from_slice_multi_with_options(b"# only a comment", &opts), Ok(vec![]));
+        let opts = ParseOptions { line_comment_char: b'%', ..Default::default() };
+        assert_eq!(
+            from_slice_with_options(b"(a %ignored\n b #;c d #|e|#)", &opts),
+            Ok(list(&[atom(b"a"), atom(b"b"), atom(b"d")]))
+        );
+    }
+
+    #[test]
+    fn radix_atoms() {
+        let opts = ParseOptions { radix_atoms: true, ..Default::default() };
+        assert_eq!(from_slice_with_options(b"#x1F", &opts), Ok(atom(b"31")));
+        assert_eq!(from_slice_with_options(b"#X1f", &opts), Ok(atom(b"31")));
+        assert_eq!(from_slice_with_options(b"#o17", &opts), Ok(atom(b"15")));
+        assert_eq!(from_slice_with_options(b"#b101", &opts), Ok(atom(b"5")));
+        assert_eq!(from_slice_with_options(b"#x-ff", &opts), Ok(atom(b"-255")));
+        assert_eq!(
+            from_slice_multi_with_options(b"(#xZZ #b102 #x # foo#x1) #b11", &opts),
+            Ok(vec![
+                list(&[atom(b"#xZZ"), atom(b"#b102"), atom(b"#x"), atom(b"#"), atom(b"foo#x1")]),
+                atom(b"3")
+            ])
+        );
+        assert_eq!(
+            from_slice_with_options(b"(#x10 #;#x11 #|#x12|# #b1)", &opts),
+            Ok(list(&[atom(b"16"), atom(b"1")]))
+        );
+        // Quoted atoms are never converted.
+        assert_eq!(from_slice_with_options(b"\"#x1F\"", &opts), Ok(atom(b"#x1F")));
+        // Without the option, radix atoms are plain atoms.
+        assert_eq!(from_slice(b"#x1F"), Ok(atom(b"#x1F")));
+        assert_eq!(from_slice(b"#x1F").unwrap().to_bytes(), b"#x1F");
+
+        for (value, radix, expected) in [
+            (31, Radix::Hexadecimal, "#x1F"),
+            (15, Radix::Octal, "#o17"),
+            (5, Radix::Binary, "#b101"),
+            (-255, Radix::Hexadecimal, "#x-FF"),
+        ] {
+            let sexp = radix_atom(value, radix);
+            assert_eq!(sexp.to_bytes(), expected.as_bytes());
+            let value = value.to_string();
+            assert_eq!(
+                from_slice_with_options(&sexp.to_bytes(), &opts),
+                Ok(atom(value.as_bytes()))
+            );
+        }
+    }
+
+    #[test]
+    fn borrowed_atoms() {
+        let input = b"(foo \"bar baz\" (\"esc\\\"aped\") #| c |# qux)";
+        let sexp = from_slice_ref(input).unwrap();
+        assert_eq!(sexp.to_owned(), from_slice(input).unwrap());
+        let in_input = |atom: &[u8]| input.as_ptr_range().contains(&atom.as_ptr());
+        let list = match sexp {
+            SexpRef::List(list) => list,
+            SexpRef::Atom(_) => panic!("expected a list"),
+        };
+        let mut borrowed = vec![];
+        for sexp in list.iter() {
+            match sexp {
+                SexpRef::Atom(Cow::Borrowed(atom)) => {
+                    assert!(in_input(atom));
+                    borrowed.push(*atom)
+                }
+                SexpRef::Atom(Cow::Owned(atom)) => panic!("unexpected owned atom {atom:?}"),
+                SexpRef::List(list) => {
+                    assert_eq!(list.len(), 1);
+                    assert!(
+                        matches!(&list[0], SexpRef::Atom(Cow::Owned(atom)) if atom == b"esc\"aped")
+                    )
+                }
+            }
+        }
+        assert_eq!(borrowed, [b"foo" as &[u8], b"bar baz", b"qux"]);
+        assert_eq!(from_slice_ref(b"(foo"), Err(Error::UnexpectedEof { offset: 4 }));
+        assert_eq!(from_slice_ref(b"foo bar"), Err(Error::UnexpectedEof { offset: 4 }));
+    }
+
+    #[test]
+    fn iter_sexps() {
+        for input in [
+            "",
+            " ; comment\n ",
+            "()",
+            "(foo bar) baz \"q u x\"",
+            "a (b (c d)) #| comment |# e #; (f) g ; end",
+        ] {
+            let sexps: Result<Vec<_>, _> = iter_from_slice(input).collect();
+            assert_eq!(sexps, from_slice_multi(input));
+        }
+        let mut iter = iter_from_slice("(a b) c (d");
+        assert_eq!(iter.next(), Some(Ok(list(&[atom(b"a"), atom(b"b")]))));
+        assert_eq!(iter.next(), Some(Ok(atom(b"c"))));
+        assert_eq!(iter.next(), Some(Err(Error::UnexpectedEof { offset: 10 })));
+        assert_eq!(iter.next(), None);
+        let sexps: Vec<_> = iter_from_slice("a \"b").collect();
+        assert_eq!(sexps, [Ok(atom(b"a")), Err(Error::UnexpectedEofInString { offset: 2 })]);
+        // The blanks and comments following a sexp are consumed with it.
+        let sexps: Vec<_> = iter_from_slice("a #| b").collect();
+        assert_eq!(sexps, [Err(Error::UnterminatedBlockComment { offset: 2 })]);
+    }
+
+    #[test]
+    fn batch_budget() {
+        let inputs = ["(a (b c))", "d", "#; (ignored) (e f)", "(g h i)", "j"];
+        let mut parser = BatchParser::new(12);
+        for input in &inputs[..3] {
+            let (sexp, remaining) = parser.parse_next(input).unwrap();
+            assert_eq!(sexp, from_slice(input).unwrap());
+            assert_eq!(remaining, b"");
+        }
+        // Nodes in datum comments count towards the budget.
+        assert_eq!(parser.remaining_budget(), 1);
+        assert_eq!(parser.parse_next(inputs[3]), Err(Error::BudgetExhausted { offset: 1 }));
+        assert_eq!(parser.remaining_budget(), 0);
+        assert_eq!(parser.parse_next(inputs[4]), Err(Error::BudgetExhausted { offset: 0 }));
+
+        let mut parser = BatchParser::new(3);
+        let (sexp, remaining) = parser.parse_next("(a b) (c)").unwrap();
+        assert_eq!(sexp, list(&[atom(b"a"), atom(b"b")]));
+        assert_eq!(parser.parse_next(remaining), Err(Error::BudgetExhausted { offset: 0 }));
+        // Parse errors unrelated to the budget are still reported.
+        let mut parser = BatchParser::new(10);
+        assert_eq!(parser.parse_next("(a"), Err(Error::UnexpectedEof { offset: 2 }));
+        assert_eq!(parser.remaining_budget(), 8);
+    }
 }
@@ -0,0 +1,32 @@
+// Strategies generating sexps for property based testing with proptest.
+use crate::Sexp;
+use proptest::collection::vec;
+use proptest::prelude::{any, prop_oneof, Strategy};
+
+/// A strategy generating sexps with lists nested at most 4 levels deep and
+/// atoms of at most 10 bytes, see `sexp_strategy_with_limits`.
+///
+/// # Example
+///
+/// ```
+///     use proptest::prelude::*;
+///     proptest!(|(sexp in rsexp::sexp_strategy())| {
+///         prop_assert_eq!(rsexp::from_slice(&sexp.to_bytes()), Ok(sexp));
+///     });
+/// ```
+pub fn sexp_strategy() -> impl Strategy<Value = Sexp> {
+    sexp_strategy_with_limits(4, 10)
+}
+
+/// A strategy generating sexps with lists nested at most `max_depth` levels
+/// deep and atoms of at most `max_atom_len` bytes. Half of the atoms are made
+/// of lowercase letters, the others of arbitrary bytes that often require
+/// quoting and escaping.
+pub fn sexp_strategy_with_limits(
+    max_depth: u32,
+    max_atom_len: usize,
+) -> impl Strategy<Value = Sexp> {
+    let atom = prop_oneof![vec(b'a'..=b'z', 0..=max_atom_len), vec(any::<u8>(), 0..=max_atom_len)]
+        .prop_map(Sexp::Atom);
+    atom.prop_recursive(max_depth, 256, 10, |inner| vec(inner, 0..10).prop_map(Sexp::List))
+}
@@ -0,0 +1,335 @@
+use crate::{from_slice_allow_remaining_with_options, Error, ParseOptions, Sexp};
+use std::io::{BufRead, BufReader, Read, Write};
+
+// The minimal number of bytes to read when refilling the buffer, the buffer
+// size is also at least doubled so that re-parsing a sexp that straddles
+// multiple refills has a linear cost overall.
+const MIN_REFILL: usize = 4096;
+
+/// Incremental parser that reads one top-level sexp at a time from a
+/// `BufRead`, without loading the whole input in memory.
+///
+/// # Example
+///
+/// ```
+///     let mut reader = rsexp::SexpReader::new(&b"(foo bar) baz ; comment\n"[..]);
+///     assert_eq!(reader.next_sexp().unwrap().unwrap().to_bytes(), b"(foo bar)");
+///     assert_eq!(reader.next_sexp().unwrap().unwrap().to_bytes(), b"baz");
+///     assert_eq!(reader.next_sexp().unwrap(), None);
+/// ```
+pub struct SexpReader<R: BufRead> {
+    reader: R,
+    opts: ParseOptions,
+    buffer: Vec<u8>,
+    // Offset in the stream of the first byte of the buffer.
+    buffer_offset: usize,
+    eof: bool,
+}
+
+impl<R: BufRead> SexpReader<R> {
+    pub fn new(reader: R) -> Self {
+        Self::with_options(reader, ParseOptions::default())
+    }
+
+    pub fn with_options(reader: R, opts: ParseOptions) -> Self {
+        SexpReader { reader, opts, buffer: vec![], buffer_offset: 0, eof: false }
+    }
+
+    /// Returns the underlying reader.
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+
+    fn refill(&mut self) -> Result<(), Error> {
+        let target_len = self.buffer.len() + self.buffer.len().max(MIN_REFILL);
+        while self.buffer.len() < target_len {
+            let available = match self.reader.fill_buf() {
+                Ok(available) => available,
+                Err(err) if err.kind() == std::io::ErrorKind::Interrupted => continue,
+                Err(err) => {
+                    let offset = self.buffer_offset + self.buffer.len();
+                    return Err(Error::Io { err: err.to_string(), offset });
+                }
+            };
+            if available.is_empty() {
+                self.eof = true;
+                break;
+            }
+            let len = available.len();
+            self.buffer.extend_from_slice(available);
+            self.reader.consume(len);
+        }
+        Ok(())
+    }
+
+    /// Parses the next top-level sexp, returns `None` once the end of the
+    /// input has been reached. Error offsets are relative to the start of the
+    /// stream. After an error is returned, the reader is exhausted.
+    pub fn next_sexp(&mut self) -> Result<Option<Sexp>, Error> {
+        loop {
+            match from_slice_allow_remaining_with_options(&self.buffer, &self.opts) {
+                // When the parser stops at the end of the buffer, an unquoted
+                // atom or the blanks and comments following the sexp may
+                // continue in the next refill, these are parsed again after
+                // refilling rather than being only partially consumed.
+                Ok((remaining, _)) if remaining.is_empty() && !self.eof => {}
+                Ok((remaining, sexp)) => {
+                    let consumed = self.buffer.len() - remaining.len();
+                    self.buffer.drain(..consumed);
+                    self.buffer_offset += consumed;
+                    return Ok(Some(sexp));
+                }
+                Err(err) if err.is_eof(self.buffer.len()) && !self.eof => {}
+                Err(Error::EmptyAtom { offset }) if offset == self.buffer.len() => {
+                    // Only blanks and comments remain.
+                    return Ok(None);
+                }
+                Err(err) => {
+                    self.buffer.clear();
+                    self.eof = true;
+                    return Err(err.shift_offset(self.buffer_offset));
+                }
+            }
+            self.refill()?
+        }
+    }
+}
+
+impl<R: BufRead> Iterator for SexpReader<R> {
+    type Item = Result<Sexp, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_sexp().transpose()
+    }
+}
+
+/// Checks that a stream only contains well-formed sexps and returns the number
+/// of top-level sexps. Each sexp is dropped as soon as it has been parsed so
+/// that huge inputs can be validated in bounded memory.
+pub fn validate_multi_from_reader<R: BufRead>(r: R) -> Result<usize, Error> {
+    let mut reader = SexpReader::new(r);
+    let mut count = 0;
+    while reader.next_sexp()?.is_some() {
+        count += 1
+    }
+    Ok(count)
+}
+
+/// The layout used by `transform_stream` to write the resulting sexps.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum StreamFormat {
+    /// Each sexp is written on a single line using `Sexp::write`.
+    Line,
+    /// Each sexp is written using `Sexp::write_hum` followed by a new line.
+    Hum,
+}
+
+/// Reads the top-level sexps from `r` one at a time, applies `f` to each of
+/// them and writes the results to `w`. Returns the number of sexps processed.
+/// Errors when writing are returned as `Error::Io` with the offset of the end
+/// of the sexp being processed.
+///
+/// # Example
+///
+/// ```
+///     let mut output = Vec::new();
+///     let wrap = |s| rsexp::list(&[rsexp::atom(b"wrapped"), s]);
+///     let input = &b"(a b) c"[..];
+///     let count = rsexp::transform_stream(input, &mut output, wrap, rsexp::StreamFormat::Line);
+///     assert_eq!(count, Ok(2));
+///     assert_eq!(output, b"(wrapped (a b))\n(wrapped c)\n");
+/// ```
+pub fn transform_stream<R: Read, W: Write, F: FnMut(Sexp) -> Sexp>(
+    r: R,
+    mut w: W,
+    mut f: F,
+    format: StreamFormat,
+) -> Result<usize, Error> {
+    let mut reader = SexpReader::new(BufReader::new(r));
+    let mut count = 0;
+    while let Some(sexp) = reader.next_sexp()? {
+        let sexp = f(sexp);
+        let res = match format {
+            StreamFormat::Line => sexp.write(&mut w),
+            StreamFormat::Hum => sexp.write_hum(&mut w),
+        };
+        res.and_then(|()| w.write_all(b"\n"))
+            .map_err(|err| Error::Io { err: err.to_string(), offset: reader.buffer_offset })?;
+        count += 1
+    }
+    w.flush().map_err(|err| Error::Io { err: err.to_string(), offset: reader.buffer_offset })?;
+    Ok(count)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        from_slice_multi, transform_stream, validate_multi_from_reader, Error, Sexp, SexpReader,
+        StreamFormat,
+    };
+    use std::io::{BufReader, Read};
+
+    // A reader that returns a single byte per read call.
+    struct OneByte<'a>(&'a [u8]);
+
+    impl<'a> Read for OneByte<'a> {
+        fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+            match (self.0.split_first(), buf.first_mut()) {
+                (Some((&c, rest)), Some(b)) => {
+                    *b = c;
+                    self.0 = rest;
+                    Ok(1)
+                }
+                _ => Ok(0),
+            }
+        }
+    }
+
+    fn one_byte_reader(input: &[u8]) -> SexpReader<BufReader<OneByte<'_>>> {
+        SexpReader::new(BufReader::with_capacity(1, OneByte(input)))
+    }
+
+    // A reader that returns at most 1000 bytes per read call.
+    struct Chunks<'a>(&'a [u8]);
+
+    impl<'a> Read for Chunks<'a> {
+        fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+            let len = self.0.len().min(buf.len()).min(1000);
+            buf[..len].copy_from_slice(&self.0[..len]);
+            self.0 = &self.0[len..];
+            Ok(len)
+        }
+    }
+
+    // A line comment longer than the refills.
+    fn long_comment_input() -> Vec<u8> {
+        [b"(a) ;".as_slice(), &vec![b'x'; 20000], b" comment tail\n(b)"].concat()
+    }
+
+    #[test]
+    fn one_byte_at_a_time() {
+        let inputs: [&[u8]; 8] = [
+            b"",
+            b"  ; only a comment",
+            b"foo",
+            b"(foo bar) baz \"quoted atom\" (nested (list \"\\n\"))",
+            b"abc;comment\ndef #|block\n|# ghi #;(datum comment) jkl",
+            b"(a b)(c d)e f\"g\"h",
+            b"(0123456789 0123456789 0123456789 0123456789) 0123456789",
+            b"  \n(x)  \n",
+        ];
+        for input in inputs {
+            let expected = from_slice_multi(input).unwrap();
+            let sexps: Vec<_> = one_byte_reader(input).collect::<Result<_, _>>().unwrap();
+            assert_eq!(sexps, expected);
+            let sexps: Vec<_> = SexpReader::new(input).collect::<Result<_, _>>().unwrap();
+            assert_eq!(sexps, expected);
+        }
+    }
+
+    #[test]
+    fn long_comment() {
+        let input = long_comment_input();
+        let expected = from_slice_multi(&input).unwrap();
+        assert_eq!(expected.len(), 2);
+        let reader = SexpReader::new(BufReader::new(Chunks(&input)));
+        assert_eq!(reader.collect::<Result<Vec<_>, _>>(), Ok(expected.clone()));
+        let sexps: Vec<_> = one_byte_reader(&input).collect::<Result<_, _>>().unwrap();
+        assert_eq!(sexps, expected);
+        // A datum comment with a long atom.
+        let input = [b"(a) #;".as_slice(), &vec![b'x'; 20000], b" (b)"].concat();
+        let reader = SexpReader::new(BufReader::new(Chunks(&input)));
+        assert_eq!(reader.collect::<Result<Vec<_>, _>>(), from_slice_multi(&input));
+        // The same with the comment at the end of the input.
+        let input = [b"(a) ;".as_slice(), &vec![b'x'; 20000]].concat();
+        let reader = SexpReader::new(BufReader::new(Chunks(&input)));
+        assert_eq!(
+            reader.collect::<Result<Vec<_>, _>>(),
+            Ok(vec![crate::list(&[crate::atom(b"a")])])
+        );
+    }
+
+    #[test]
+    fn large_input() {
+        let input: Vec<u8> = (0..10000).flat_map(|i| format!("(foo {i}) ").into_bytes()).collect();
+        let expected = from_slice_multi(&input).unwrap();
+        let sexps: Vec<_> = one_byte_reader(&input).collect::<Result<_, _>>().unwrap();
+        assert_eq!(sexps, expected);
+    }
+
+    #[test]
+    fn errors() {
+        let mut reader = one_byte_reader(b"(a b) (c \"d");
+        assert_eq!(reader.next_sexp().unwrap().unwrap().to_bytes(), b"(a b)");
+        assert_eq!(reader.next_sexp(), Err(Error::UnexpectedEofInString { offset: 9 }));
+        let mut reader = one_byte_reader(b"(a b) (c d");
+        assert_eq!(reader.next_sexp().unwrap().unwrap().to_bytes(), b"(a b)");
+        assert_eq!(reader.next_sexp(), Err(Error::UnexpectedEof { offset: 10 }));
+        let mut reader = one_byte_reader(b"a b ) c");
+        assert_eq!(reader.next_sexp().unwrap().unwrap().to_bytes(), b"a");
+        assert_eq!(reader.next_sexp().unwrap().unwrap().to_bytes(), b"b");
+        assert_eq!(reader.next_sexp(), Err(Error::EmptyAtom { offset: 4 }));
+        assert_eq!(reader.next_sexp(), Ok(None));
+        assert_eq!(one_byte_reader(b"a ) b").count(), 2);
+    }
+
+    #[test]
+    fn validate_multi() {
+        let input: Vec<u8> = (0..1000).flat_map(|i| format!("(foo {i}) ").into_bytes()).collect();
+        assert_eq!(validate_multi_from_reader(&input[..]), Ok(1000));
+        let reader = BufReader::with_capacity(1, OneByte(&input));
+        assert_eq!(validate_multi_from_reader(reader), Ok(1000));
+        assert_eq!(validate_multi_from_reader(&b" ; nothing\n"[..]), Ok(0));
+        let reader = BufReader::with_capacity(1, OneByte(b"(a b) c ; comment\n(d (e) f"));
+        assert_eq!(validate_multi_from_reader(reader), Err(Error::UnexpectedEof { offset: 26 }));
+        // Comments spanning multiple refills are not counted as atoms.
+        let input = long_comment_input();
+        assert_eq!(validate_multi_from_reader(BufReader::new(Chunks(&input))), Ok(2));
+    }
+
+    #[test]
+    fn transform() {
+        let input: Vec<u8> =
+            (0..1000).flat_map(|i| format!("(foo {i}) bar{i} ").into_bytes()).collect();
+        let mut output = Vec::new();
+        let wrap = |s| crate::list(&[crate::atom(b"outer"), s]);
+        let reader = OneByte(&input);
+        assert_eq!(transform_stream(reader, &mut output, wrap, StreamFormat::Line), Ok(2000));
+        let sexps = from_slice_multi(&input).unwrap();
+        let expected: Vec<u8> = sexps
+            .iter()
+            .flat_map(|s| [wrap(s.clone()).to_bytes(), b"\n".to_vec()].concat())
+            .collect();
+        assert_eq!(output, expected);
+
+        let mut output = Vec::new();
+        let count =
+            transform_stream(&b"((a b) (c d)) e"[..], &mut output, |s| s, StreamFormat::Hum);
+        assert_eq!(count, Ok(2));
+        assert_eq!(output, b"((a b) (c d))\ne\n");
+
+        let mut count = 0;
+        let res = transform_stream(
+            &b"a b (c"[..],
+            std::io::sink(),
+            |s: Sexp| {
+                count += 1;
+                s
+            },
+            StreamFormat::Line,
+        );
+        assert_eq!(res, Err(Error::UnexpectedEof { offset: 6 }));
+        assert_eq!(count, 2);
+
+        let mut output = [0u8; 8];
+        let res = transform_stream(&b"abc defgh"[..], &mut output[..], |s| s, StreamFormat::Line);
+        assert!(matches!(res, Err(Error::Io { offset: 9, .. })));
+        assert_eq!(&output, b"abc\ndefg");
+        // Comments spanning multiple refills are not written as atoms.
+        let mut output = Vec::new();
+        let count =
+            transform_stream(Chunks(&long_comment_input()), &mut output, |s| s, StreamFormat::Line);
+        assert_eq!(count, Ok(2));
+        assert_eq!(output, b"(a)\n(b)\n");
+    }
+}
@@ -0,0 +1,359 @@
+// Conversion from types implementing serde::Serialize to Sexp, the generated
+// sexps follow the same conventions as the SexpOf deriver.
+use crate::{atom, Sexp};
+use serde::ser::{self, Serialize};
+
+/// Errors that could be generated when serializing a value to a Sexp.
+#[derive(Debug, PartialEq, Eq)]
+pub enum SerError {
+    Custom(String),
+}
+
+impl std::fmt::Display for SerError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{self:?}")
+    }
+}
+
+impl std::error::Error for SerError {}
+
+impl ser::Error for SerError {
+    fn custom<T: std::fmt::Display>(msg: T) -> Self {
+        SerError::Custom(msg.to_string())
+    }
+}
+
+/// Converts a value implementing `serde::Serialize` to a Sexp. Structs are
+/// represented as lists of `(field value)` pairs, maps as lists of
+/// `(key value)` pairs, and enum variants use their constructor name, similar
+/// to the `SexpOf` deriver.
+///
+/// # Example
+///
+/// ```
+///     let sexp = rsexp::to_sexp(&(1, "foo", Some(vec![2.5]))).unwrap();
+///     assert_eq!(sexp.to_bytes(), b"(1 foo ((2.5)))");
+/// ```
+pub fn to_sexp<T: Serialize + ?Sized>(value: &T) -> Result<Sexp, SerError> {
+    value.serialize(Serializer)
+}
+
+struct Serializer;
+
+fn atom_of_display<T: std::fmt::Display>(v: T) -> Result<Sexp, SerError> {
+    Ok(atom(v.to_string().as_bytes()))
+}
+
+// Used for sequences, tuples, and tuple variants. The constructor is only set
+// for tuple variants.
+struct SerializeList {
+    cstor: Option<Sexp>,
+    elems: Vec<Sexp>,
+}
+
+impl SerializeList {
+    fn new(cstor: Option<Sexp>, len: Option<usize>) -> Self {
+        let mut elems = Vec::with_capacity(1 + len.unwrap_or(0));
+        elems.extend(cstor.clone());
+        SerializeList { cstor, elems }
+    }
+
+    fn push<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), SerError> {
+        self.elems.push(to_sexp(value)?);
+        Ok(())
+    }
+
+    fn end(self) -> Result<Sexp, SerError> {
+        match self.cstor {
+            // Variants without arguments only use the constructor name.
+            Some(cstor) if self.elems.len() == 1 => Ok(cstor),
+            _ => Ok(Sexp::List(self.elems)),
+        }
+    }
+
+    fn push_field<T: Serialize + ?Sized>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<(), SerError> {
+        self.elems.push(Sexp::List(vec![atom(key.as_bytes()), to_sexp(value)?]));
+        Ok(())
+    }
+}
+
+struct SerializeMap {
+    key: Option<Sexp>,
+    elems: Vec<Sexp>,
+}
+
+impl ser::Serializer for Serializer {
+    type Ok = Sexp;
+    type Error = SerError;
+    type SerializeSeq = SerializeList;
+    type SerializeTuple = SerializeList;
+    type SerializeTupleStruct = SerializeList;
+    type SerializeTupleVariant = SerializeList;
+    type SerializeMap = SerializeMap;
+    type SerializeStruct = SerializeList;
+    type SerializeStructVariant = SerializeList;
+
+    fn serialize_bool(self, v: bool) -> Result<Sexp, SerError> {
+        atom_of_display(v)
+    }
+
+    fn serialize_i8(self, v: i8) -> Result<Sexp, SerError> {
+        atom_of_display(v)
+    }
+
+    fn serialize_i16(self, v: i16) -> Result<Sexp, SerError> {
+        atom_of_display(v)
+    }
+
+    fn serialize_i32(self, v: i32) -> Result<Sexp, SerError> {
+        atom_of_display(v)
+    }
+
+    fn serialize_i64(self, v: i64) -> Result<Sexp, SerError> {
+        atom_of_display(v)
+    }
+
+    fn serialize_i128(self, v: i128) -> Result<Sexp, SerError> {
+        atom_of_display(v)
+    }
+
+    fn serialize_u8(self, v: u8) -> Result<Sexp, SerError> {
+        atom_of_display(v)
+    }
+
+    fn serialize_u16(self, v: u16) -> Result<Sexp, SerError> {
+        atom_of_display(v)
+    }
+
+    fn serialize_u32(self, v: u32) -> Result<Sexp, SerError> {
+        atom_of_display(v)
+    }
+
+    fn serialize_u64(self, v: u64) -> Result<Sexp, SerError> {
+        atom_of_display(v)
+    }
+
+    fn serialize_u128(self, v: u128) -> Result<Sexp, SerError> {
+        atom_of_display(v)
+    }
+
+    fn serialize_f32(self, v: f32) -> Result<Sexp, SerError> {
+        atom_of_display(v)
+    }
+
+    fn serialize_f64(self, v: f64) -> Result<Sexp, SerError> {
+        atom_of_display(v)
+    }
+
+    fn serialize_char(self, v: char) -> Result<Sexp, SerError> {
+        atom_of_display(v)
+    }
+
+    fn serialize_str(self, v: &str) -> Result<Sexp, SerError> {
+        Ok(atom(v.as_bytes()))
+    }
+
+    fn serialize_bytes(self, v: &[u8]) -> Result<Sexp, SerError> {
+        Ok(atom(v))
+    }
+
+    fn serialize_none(self) -> Result<Sexp, SerError> {
+        Ok(Sexp::List(vec![]))
+    }
+
+    fn serialize_some<T: Serialize + ?Sized>(self, value: &T) -> Result<Sexp, SerError> {
+        Ok(Sexp::List(vec![to_sexp(value)?]))
+    }
+
+    fn serialize_unit(self) -> Result<Sexp, SerError> {
+        Ok(Sexp::List(vec![]))
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<Sexp, SerError> {
+        Ok(Sexp::List(vec![]))
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+    ) -> Result<Sexp, SerError> {
+        Ok(atom(variant.as_bytes()))
+    }
+
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        value: &T,
+    ) -> Result<Sexp, SerError> {
+        Ok(Sexp::List(vec![to_sexp(value)?]))
+    }
+
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        value: &T,
+    ) -> Result<Sexp, SerError> {
+        Ok(Sexp::List(vec![atom(variant.as_bytes()), to_sexp(value)?]))
+    }
+
+    fn serialize_seq(self, len: Option<usize>) -> Result<SerializeList, SerError> {
+        Ok(SerializeList::new(None, len))
+    }
+
+    fn serialize_tuple(self, len: usize) -> Result<SerializeList, SerError> {
+        Ok(SerializeList::new(None, Some(len)))
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        _name: &'static str,
+        len: usize,
+    ) -> Result<SerializeList, SerError> {
+        Ok(SerializeList::new(None, Some(len)))
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        len: usize,
+    ) -> Result<SerializeList, SerError> {
+        Ok(SerializeList::new(Some(atom(variant.as_bytes())), Some(len)))
+    }
+
+    fn serialize_map(self, len: Option<usize>) -> Result<SerializeMap, SerError> {
+        Ok(SerializeMap { key: None, elems: Vec::with_capacity(len.unwrap_or(0)) })
+    }
+
+    fn serialize_struct(self, _name: &'static str, len: usize) -> Result<SerializeList, SerError> {
+        Ok(SerializeList::new(None, Some(len)))
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        len: usize,
+    ) -> Result<SerializeList, SerError> {
+        Ok(SerializeList::new(Some(atom(variant.as_bytes())), Some(len)))
+    }
+}
+
+impl ser::SerializeSeq for SerializeList {
+    type Ok = Sexp;
+    type Error = SerError;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), SerError> {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<Sexp, SerError> {
+        SerializeList::end(self)
+    }
+}
+
+impl ser::SerializeTuple for SerializeList {
+    type Ok = Sexp;
+    type Error = SerError;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), SerError> {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<Sexp, SerError> {
+        SerializeList::end(self)
+    }
+}
+
+impl ser::SerializeTupleStruct for SerializeList {
+    type Ok = Sexp;
+    type Error = SerError;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), SerError> {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<Sexp, SerError> {
+        SerializeList::end(self)
+    }
+}
+
+impl ser::SerializeTupleVariant for SerializeList {
+    type Ok = Sexp;
+    type Error = SerError;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), SerError> {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<Sexp, SerError> {
+        SerializeList::end(self)
+    }
+}
+
+impl ser::SerializeStruct for SerializeList {
+    type Ok = Sexp;
+    type Error = SerError;
+
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<(), SerError> {
+        self.push_field(key, value)
+    }
+
+    fn end(self) -> Result<Sexp, SerError> {
+        SerializeList::end(self)
+    }
+}
+
+impl ser::SerializeStructVariant for SerializeList {
+    type Ok = Sexp;
+    type Error = SerError;
+
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<(), SerError> {
+        self.push_field(key, value)
+    }
+
+    fn end(self) -> Result<Sexp, SerError> {
+        SerializeList::end(self)
+    }
+}
+
+impl ser::SerializeMap for SerializeMap {
+    type Ok = Sexp;
+    type Error = SerError;
+
+    fn serialize_key<T: Serialize + ?Sized>(&mut self, key: &T) -> Result<(), SerError> {
+        self.key = Some(to_sexp(key)?);
+        Ok(())
+    }
+
+    fn serialize_value<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), SerError> {
+        let key = match self.key.take() {
+            Some(key) => key,
+            None => return Err(ser::Error::custom("serialize_value called before serialize_key")),
+        };
+        self.elems.push(Sexp::List(vec![key, to_sexp(value)?]));
+        Ok(())
+    }
+
+    fn end(self) -> Result<Sexp, SerError> {
+        Ok(Sexp::List(self.elems))
+    }
+}
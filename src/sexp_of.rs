@@ -1,4 +1,8 @@
-use crate::{atom, list, BytesSlice, Sexp, UseToString};
+use crate::{atom, list, BytesSlice, ElispBool, Sexp, UseToString};
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+#[cfg(feature = "std")]
+use std::path::{Path, PathBuf};
 
 pub trait SexpOf {
     fn sexp_of(&self) -> Sexp;
@@ -10,6 +14,43 @@ impl<T: ToString + UseToString> SexpOf for T {
     }
 }
 
+/// Implements `SexpOf` for string-like types implementing `AsRef<str>`, e.g.
+/// interned strings, the value is serialized as a single atom like `String`.
+/// A marker trait with a blanket impl similar to `UseToString` cannot be used
+/// here as it would overlap with the `UseToString` one.
+///
+/// # Example
+///
+/// ```
+///     use rsexp::SexpOf;
+///     struct Istr(std::rc::Rc<str>);
+///     impl AsRef<str> for Istr {
+///         fn as_ref(&self) -> &str {
+///             &self.0
+///         }
+///     }
+///     rsexp::impl_sexp_of_str!(Istr);
+///     assert_eq!(Istr("foo bar".into()).sexp_of().to_bytes(), b"\"foo bar\"");
+/// ```
+#[macro_export]
+macro_rules! impl_sexp_of_str {
+    ($($ty:ty),+ $(,)?) => {
+        $(
+            impl $crate::SexpOf for $ty {
+                fn sexp_of(&self) -> $crate::Sexp {
+                    $crate::atom(::core::convert::AsRef::<str>::as_ref(self).as_bytes())
+                }
+            }
+        )+
+    };
+}
+
+impl SexpOf for Sexp {
+    fn sexp_of(&self) -> Sexp {
+        self.clone()
+    }
+}
+
 impl SexpOf for String {
     fn sexp_of(&self) -> Sexp {
         atom(self.as_bytes())
@@ -22,12 +63,55 @@ impl SexpOf for &str {
     }
 }
 
+#[cfg(feature = "std")]
+fn path_bytes(path: &Path) -> std::borrow::Cow<'_, [u8]> {
+    #[cfg(unix)]
+    {
+        use std::os::unix::ffi::OsStrExt;
+        std::borrow::Cow::Borrowed(path.as_os_str().as_bytes())
+    }
+    #[cfg(not(unix))]
+    {
+        match path.to_string_lossy() {
+            std::borrow::Cow::Borrowed(s) => std::borrow::Cow::Borrowed(s.as_bytes()),
+            std::borrow::Cow::Owned(s) => std::borrow::Cow::Owned(s.into_bytes()),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl SexpOf for PathBuf {
+    fn sexp_of(&self) -> Sexp {
+        atom(&path_bytes(self))
+    }
+}
+
+#[cfg(feature = "std")]
+impl SexpOf for &Path {
+    fn sexp_of(&self) -> Sexp {
+        atom(&path_bytes(self))
+    }
+}
+
 impl<'a> SexpOf for BytesSlice<'a> {
     fn sexp_of(&self) -> Sexp {
         atom(self.0)
     }
 }
 
+#[cfg(feature = "bytes")]
+impl SexpOf for bytes::Bytes {
+    fn sexp_of(&self) -> Sexp {
+        atom(self)
+    }
+}
+
+impl SexpOf for ElispBool {
+    fn sexp_of(&self) -> Sexp {
+        atom(if self.0 { b"t" } else { b"nil" })
+    }
+}
+
 impl<T> SexpOf for [T]
 where
     T: SexpOf,
@@ -37,6 +121,17 @@ where
     }
 }
 
+// Needed when vectors are nested in other types such as `Option<Vec<T>>`, a
+// bare `Vec<T>` would otherwise use the slice implementation via auto-deref.
+impl<T> SexpOf for Vec<T>
+where
+    T: SexpOf,
+{
+    fn sexp_of(&self) -> Sexp {
+        self.as_slice().sexp_of()
+    }
+}
+
 macro_rules! tuple_impls {
     ( $( $name:ident )+ ) => {
         impl<$($name: SexpOf),+> SexpOf for ($($name,)+)
@@ -61,18 +156,31 @@ tuple_impls! { A B C D E F G }
 tuple_impls! { A B C D E F G H }
 tuple_impls! { A B C D E F G H I }
 tuple_impls! { A B C D E F G H I J }
+tuple_impls! { A B C D E F G H I J K }
+tuple_impls! { A B C D E F G H I J K L }
+tuple_impls! { A B C D E F G H I J K L M }
+tuple_impls! { A B C D E F G H I J K L M N }
+tuple_impls! { A B C D E F G H I J K L M N O }
+tuple_impls! { A B C D E F G H I J K L M N O P }
 
+#[cfg(feature = "std")]
 impl<K, V> SexpOf for std::collections::HashMap<K, V>
 where
     K: SexpOf,
     V: SexpOf,
 {
     fn sexp_of(&self) -> Sexp {
-        Sexp::List(self.iter().map(|(k, v)| list(&[k.sexp_of(), v.sexp_of()])).collect())
+        #[allow(unused_mut)]
+        let mut entries: Vec<(Sexp, &V)> = self.iter().map(|(k, v)| (k.sexp_of(), v)).collect();
+        // With the deterministic feature, the entries are sorted by their
+        // serialized keys rather than using the hash map iteration order.
+        #[cfg(feature = "deterministic")]
+        entries.sort_by_cached_key(|(k, _)| k.to_bytes());
+        Sexp::List(entries.into_iter().map(|(k, v)| list(&[k, v.sexp_of()])).collect())
     }
 }
 
-impl<K, V> SexpOf for std::collections::BTreeMap<K, V>
+impl<K, V> SexpOf for alloc::collections::BTreeMap<K, V>
 where
     K: SexpOf,
     V: SexpOf,
@@ -94,8 +202,103 @@ where
     }
 }
 
+// Uses the OCaml representation, (Ok v) or (Error e).
+impl<T, E> SexpOf for Result<T, E>
+where
+    T: SexpOf,
+    E: SexpOf,
+{
+    fn sexp_of(&self) -> Sexp {
+        match self {
+            Ok(v) => list(&[atom(b"Ok"), v.sexp_of()]),
+            Err(e) => list(&[atom(b"Error"), e.sexp_of()]),
+        }
+    }
+}
+
 impl SexpOf for () {
     fn sexp_of(&self) -> Sexp {
         list(&[])
     }
 }
+
+// The integer wrappers use the representation of the wrapped value.
+impl<T: SexpOf> SexpOf for core::num::Wrapping<T> {
+    fn sexp_of(&self) -> Sexp {
+        self.0.sexp_of()
+    }
+}
+
+impl<T: SexpOf> SexpOf for core::num::Saturating<T> {
+    fn sexp_of(&self) -> Sexp {
+        self.0.sexp_of()
+    }
+}
+
+macro_rules! from_impls {
+    ($($ty:ty),+ $(,)?) => {
+        $(
+            impl From<$ty> for Sexp {
+                fn from(value: $ty) -> Sexp {
+                    value.sexp_of()
+                }
+            }
+        )+
+    };
+}
+
+// There is no conversion from strings as `TryFrom<&str>` parses the string,
+// `atom` or the `sexp!` macro can be used to build string atoms.
+from_impls!(u128, u64, u32, u16, u8, usize, i128, i64, i32, i16, i8, f64, f32, bool);
+
+impl From<Vec<Sexp>> for Sexp {
+    fn from(list: Vec<Sexp>) -> Sexp {
+        Sexp::List(list)
+    }
+}
+
+/// Builds a Sexp from a literal syntax. Identifiers, string literals, and
+/// number or boolean literals become atoms, a parenthesized group becomes a
+/// list, and a Rust expression in braces is converted using `SexpOf`.
+///
+/// A `-` followed by a number literal is a negative number. The atoms that
+/// are not identifiers or literals, e.g. `us-east-1`, can be written as string
+/// literals.
+///
+/// # Example
+///
+/// ```
+///     let port = 8080;
+///     let sexp = rsexp::sexp!((server (host "my host") (port {port}) (region "us-east-1") (retries -1)));
+///     assert_eq!(
+///         sexp.to_bytes(),
+///         b"(server (host \"my host\") (port 8080) (region us-east-1) (retries -1))"
+///     );
+/// ```
+#[macro_export]
+macro_rules! sexp {
+    (@list [$($out:expr),*]) => {
+        $crate::list(&[$($out),*])
+    };
+    (@list [$($out:expr),*] - $lit:literal $($rest:tt)*) => {
+        $crate::sexp!(@list [$($out,)* $crate::sexp!(- $lit)] $($rest)*)
+    };
+    (@list [$($out:expr),*] $elem:tt $($rest:tt)*) => {
+        $crate::sexp!(@list [$($out,)* $crate::sexp!($elem)] $($rest)*)
+    };
+    (($($elems:tt)*)) => {
+        $crate::sexp!(@list [] $($elems)*)
+    };
+    ({$($e:tt)*}) => {
+        $crate::SexpOf::sexp_of(&{ $($e)* })
+    };
+    (- $lit:literal) => {
+        $crate::SexpOf::sexp_of(&-$lit)
+    };
+    ($lit:literal) => {
+        $crate::SexpOf::sexp_of(&$lit)
+    };
+    ($atom:ident) => {
+        $crate::atom(::core::stringify!($atom).as_bytes())
+    };
+}
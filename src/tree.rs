@@ -0,0 +1,79 @@
+// Conversion to Sexp for arbitrary tree types, without having to implement
+// `SexpOf` on each of the node types.
+use crate::Sexp;
+use alloc::boxed::Box;
+use alloc::{vec, vec::Vec};
+
+type Children<'a> = Box<dyn Iterator<Item = &'a dyn SexpTree> + 'a>;
+
+/// A view of a tree node, either an atom or a list of children.
+pub enum SexpNode<'a> {
+    Atom(&'a [u8]),
+    List(Children<'a>),
+}
+
+/// Tree types that can be converted to a Sexp using `build_sexp`.
+///
+/// # Example
+///
+/// ```
+///     use rsexp::{SexpNode, SexpTree};
+///     enum Tree {
+///         Leaf(String),
+///         Node(Vec<Tree>),
+///     }
+///     impl SexpTree for Tree {
+///         fn as_sexp_node(&self) -> SexpNode<'_> {
+///             match self {
+///                 Tree::Leaf(s) => SexpNode::Atom(s.as_bytes()),
+///                 Tree::Node(c) => SexpNode::List(Box::new(c.iter().map(|t| t as &dyn SexpTree))),
+///             }
+///         }
+///     }
+///     let tree = Tree::Node(vec![Tree::Leaf("a".to_string()), Tree::Node(vec![])]);
+///     assert_eq!(rsexp::build_sexp(&tree).to_bytes(), b"(a ())");
+/// ```
+pub trait SexpTree {
+    fn as_sexp_node(&self) -> SexpNode<'_>;
+}
+
+impl SexpTree for Sexp {
+    fn as_sexp_node(&self) -> SexpNode<'_> {
+        match self {
+            Sexp::Atom(atom) => SexpNode::Atom(atom),
+            Sexp::List(list) => SexpNode::List(Box::new(list.iter().map(|s| s as &dyn SexpTree))),
+        }
+    }
+}
+
+/// Builds a Sexp from a tree. The tree is walked using an explicit stack so
+/// deeply nested trees do not overflow the stack.
+pub fn build_sexp<T: SexpTree + ?Sized>(tree: &T) -> Sexp {
+    let mut stack: Vec<(Children<'_>, Vec<Sexp>)> = vec![];
+    let mut node = tree.as_sexp_node();
+    loop {
+        // The sexp completed by this step, if any.
+        let mut sexp = match node {
+            SexpNode::Atom(atom) => Some(Sexp::Atom(atom.to_vec())),
+            SexpNode::List(children) => {
+                stack.push((children, vec![]));
+                None
+            }
+        };
+        loop {
+            let (children, elems) = match stack.last_mut() {
+                // Lists are pushed on the stack so the root is complete here.
+                None => return sexp.unwrap(),
+                Some(last) => last,
+            };
+            elems.extend(sexp.take());
+            match children.next() {
+                Some(child) => {
+                    node = child.as_sexp_node();
+                    break;
+                }
+                None => sexp = stack.pop().map(|(_, elems)| Sexp::List(elems)),
+            }
+        }
+    }
+}
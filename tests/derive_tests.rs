@@ -1,7 +1,12 @@
 #![allow(clippy::approx_constant)]
+extern crate quickcheck;
+#[macro_use(quickcheck)]
+extern crate quickcheck_macros;
+
+use quickcheck::{Arbitrary, Gen};
 use rsexp::{IntoSexpError, OfSexp, SexpOf};
 use rsexp_derive::{OfSexp, SexpOf};
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, HashMap};
 
 fn test_bytes<T: SexpOf>(t: T, str: &str) {
     let b = t.sexp_of().to_bytes();
@@ -67,7 +72,7 @@ fn unknown_constructor(type_: &'static str, constructor: &str) -> IntoSexpError
     IntoSexpError::UnknownConstructorForEnum { type_, constructor: constructor.to_string() }
 }
 
-#[derive(OfSexp, SexpOf, Debug, PartialEq, Eq)]
+#[derive(OfSexp, SexpOf, Debug, PartialEq, Eq, Clone)]
 struct Pancakes(i64);
 
 #[test]
@@ -78,26 +83,27 @@ fn breakfast1() {
     test_err::<Pancakes>("()", length_mismatch("Pancakes", 1, 0));
     test_err::<Pancakes>("(1 2)", length_mismatch("Pancakes", 1, 2));
     test_err::<Pancakes>("(1 2 3 4)", length_mismatch("Pancakes", 1, 4));
-    test_err::<Pancakes>("(())", expected_atom_got_list("stringable", 0));
-    test_err::<Pancakes>("((1))", expected_atom_got_list("stringable", 1));
-    test_err::<Pancakes>("((1 2))", expected_atom_got_list("stringable", 2));
+    test_err::<Pancakes>("(())", expected_atom_got_list("stringable", 0).with_index(0));
+    test_err::<Pancakes>("((1))", expected_atom_got_list("stringable", 1).with_index(0));
+    test_err::<Pancakes>("((1 2))", expected_atom_got_list("stringable", 2).with_index(0));
     test_err::<Pancakes>(
         "(a)",
-        IntoSexpError::StringConversionError { err: "invalid digit found in string".to_string() },
+        IntoSexpError::StringConversionError { err: "invalid digit found in string".to_string() }
+            .with_index(0),
     );
 }
 
-#[derive(OfSexp, SexpOf, Debug, PartialEq)]
+#[derive(OfSexp, SexpOf, Debug, PartialEq, Clone)]
 struct MorePancakes(i64, f64, Option<i64>);
 
 #[test]
 fn breakfast2() {
     test_rt_no_eq(MorePancakes(12, 3.141592, Some(1234567890123)), "(12 3.141592 (1234567890123))");
-    test_rt_no_eq(MorePancakes(12, std::f64::NAN, None), "(12 NaN ())");
-    test_rt_no_eq(MorePancakes(12, std::f64::NEG_INFINITY, None), "(12 -inf ())");
+    test_rt_no_eq(MorePancakes(12, f64::NAN, None), "(12 NaN ())");
+    test_rt_no_eq(MorePancakes(12, f64::NEG_INFINITY, None), "(12 -inf ())");
     test_err::<MorePancakes>("()", length_mismatch("MorePancakes", 3, 0));
-    test_err::<MorePancakes>("(1 2 3)", expected_list_got_atom("option"));
-    test_err::<MorePancakes>("(1 2 (3 4))", length_mismatch("option", 1, 2));
+    test_err::<MorePancakes>("(1 2 3)", expected_list_got_atom("option").with_index(2));
+    test_err::<MorePancakes>("(1 2 (3 4))", length_mismatch("option", 1, 2).with_index(2));
 }
 
 #[derive(OfSexp, SexpOf, Debug, PartialEq)]
@@ -135,7 +141,7 @@ fn breakfast3() {
     );
 }
 
-#[derive(OfSexp, SexpOf, Debug, PartialEq, Eq)]
+#[derive(OfSexp, SexpOf, Debug, PartialEq, Eq, Clone)]
 struct BreakfastsEq {
     pancakes: Pancakes,
     more_pancakes: Option<String>,
@@ -187,7 +193,7 @@ fn breakfast4() {
 // utop # C 42 |> sexp_of_t |> Sexp.to_string;;
 // - : string = "(C 42)"
 
-#[derive(OfSexp, SexpOf, Debug, PartialEq, Eq)]
+#[derive(OfSexp, SexpOf, Debug, PartialEq, Eq, Clone)]
 struct PairInt(i64, i64);
 
 #[derive(SexpOf, Debug, PartialEq)]
@@ -224,14 +230,14 @@ fn my_enum() {
     test_bytes(MyEnum::H("foo", " needs escaping\n"), "(H foo \" needs escaping\\n\")");
 }
 
-#[derive(OfSexp, SexpOf, Debug, PartialEq, Eq)]
+#[derive(OfSexp, SexpOf, Debug, PartialEq, Eq, Clone)]
 struct StructXYZ {
     x: i64,
     y: Option<(i32, i32)>,
     z: String,
 }
 
-#[derive(OfSexp, SexpOf, Debug, PartialEq, Eq)]
+#[derive(OfSexp, SexpOf, Debug, PartialEq, Eq, Clone)]
 enum MyEnum2 {
     A,
     AEmptyTuple(),
@@ -268,6 +274,111 @@ fn my_enum2() {
     test_err::<MyEnum2>("(Z foo)", unknown_constructor("MyEnum2", "Z"));
 }
 
+#[derive(SexpOf)]
+struct Wrap<'a, const N: usize> {
+    xs: [i64; N],
+    name: &'a str,
+}
+
+#[derive(SexpOf)]
+enum WrapEnum<'a, T, const N: usize> {
+    Borrowed(&'a T),
+    Array([T; N]),
+}
+
+#[derive(OfSexp, SexpOf, Debug, PartialEq, Eq)]
+struct Vector<T, const N: usize>(Vec<T>);
+
+#[test]
+fn lifetime_and_const_generics() {
+    let name = "foo bar".to_string();
+    test_bytes(Wrap { xs: [1, 2, 3], name: &name }, "((xs (1 2 3)) (name \"foo bar\"))");
+    test_bytes(Wrap::<0> { xs: [], name: "" }, "((xs ()) (name \"\"))");
+    test_bytes(WrapEnum::<i64, 2>::Borrowed(&42), "(Borrowed 42)");
+    test_bytes(WrapEnum::<_, 2>::Array([1, 2]), "(Array (1 2))");
+    test_rt(Vector::<i64, 3>(vec![1, 2]), "((1 2))");
+}
+
+#[derive(OfSexp, SexpOf, Debug, PartialEq, Eq)]
+#[sexp(all_errors)]
+struct WithWhere<T>
+where
+    T: Default + PartialEq,
+{
+    value: T,
+    others: Vec<T>,
+}
+
+impl<T> WithWhere<T>
+where
+    T: Default + PartialEq,
+{
+    fn is_default(&self) -> bool {
+        self.value == T::default()
+    }
+}
+
+#[derive(OfSexp, SexpOf, Debug, PartialEq, Eq)]
+enum EnumWithWhere<T, U>
+where
+    T: Clone,
+    U: Into<T>,
+{
+    T(T),
+    U(U),
+}
+
+#[test]
+fn where_clause() {
+    let value = WithWhere { value: 0i64, others: vec![1, 2] };
+    assert!(value.is_default());
+    test_rt(value, "((value 0) (others (1 2)))");
+    let value = WithWhere::<String>::of_sexp_all_errors(
+        &rsexp::from_slice("((value foo) (others ()))").unwrap(),
+    );
+    assert!(!value.unwrap().is_default());
+    test_rt(EnumWithWhere::<i64, i32>::T(1), "(T 1)");
+    test_rt(EnumWithWhere::<i64, i32>::U(2), "(U 2)");
+}
+
+// The same constructors as MyEnum2 in a different order, the constructors are
+// matched by name so both enums use the same representation.
+#[derive(OfSexp, SexpOf, Debug, PartialEq, Eq)]
+enum MyEnum2Reordered {
+    G(StructXYZ),
+    F { x: i64, y: String },
+    E(PairInt),
+    D(i64, i64),
+    C(i64),
+    B(()),
+    AEmptyStruct {},
+    AEmptyTuple(),
+    A,
+    H { single: i64 },
+}
+
+#[test]
+fn enum_constructor_names() {
+    let values = [
+        (MyEnum2::A, MyEnum2Reordered::A),
+        (MyEnum2::AEmptyTuple(), MyEnum2Reordered::AEmptyTuple()),
+        (MyEnum2::AEmptyStruct {}, MyEnum2Reordered::AEmptyStruct {}),
+        (MyEnum2::B(()), MyEnum2Reordered::B(())),
+        (MyEnum2::C(42), MyEnum2Reordered::C(42)),
+        (MyEnum2::D(1, 2), MyEnum2Reordered::D(1, 2)),
+        (MyEnum2::E(PairInt(3, 4)), MyEnum2Reordered::E(PairInt(3, 4))),
+        (MyEnum2::F { x: 5, y: "y".to_string() }, MyEnum2Reordered::F { x: 5, y: "y".to_string() }),
+    ];
+    for (value, reordered) in values {
+        assert_eq!(value.sexp_of(), reordered.sexp_of());
+        assert_eq!(value.sexp_of().of_sexp(), Ok(reordered));
+    }
+    test_rt(MyEnum2Reordered::H { single: 1 }, "(H (single 1))");
+    test_err::<MyEnum2Reordered>("0", unknown_constructor("MyEnum2Reordered", "0"));
+    test_err::<MyEnum2Reordered>("(A 1)", length_mismatch("MyEnum2Reordered :: A", 0, 1));
+    test_err::<MyEnum2Reordered>("C", length_mismatch("MyEnum2Reordered :: C", 1, 0));
+}
+
 #[derive(OfSexp, SexpOf, Debug, PartialEq, Eq)]
 struct WithVec {
     x: Vec<(String, i32)>,
@@ -300,3 +411,978 @@ fn with_vec() {
         "((x ((\"\\000\" 1337) (xyz123 42))) (y ()) (z (a bcd)) (m ((bar (12 23)) (foo (1 2)) (\"foo bar\" (123 234)))))"
     );
 }
+
+#[derive(OfSexp, SexpOf, Debug, PartialEq, Eq)]
+struct WithIntMap {
+    name: String,
+    m: HashMap<i64, (i32, i32)>,
+}
+
+#[test]
+fn with_int_map() {
+    let mut m = HashMap::new();
+    test_rt(WithIntMap { name: "foo".to_string(), m: m.clone() }, "((name foo) (m ()))");
+    m.insert(-42, (1, 2));
+    test_rt(WithIntMap { name: "foo".to_string(), m: m.clone() }, "((name foo) (m ((-42 (1 2)))))");
+    // The HashMap iteration order is not deterministic so only check that the
+    // value round-trips.
+    for i in 0..100 {
+        m.insert(i * 1337, (i as i32, -(i as i32)));
+    }
+    let wim = WithIntMap { name: "bar".to_string(), m };
+    let sexp = wim.sexp_of();
+    let wim2: WithIntMap = sexp.of_sexp().unwrap();
+    assert_eq!(wim, wim2);
+    let wim2: WithIntMap = rsexp::from_slice(&sexp.to_bytes_hum()).unwrap().of_sexp().unwrap();
+    assert_eq!(wim, wim2);
+    test_err::<WithIntMap>(
+        "((name foo) (m ((1 (1 2)) (1 (3 4)))))",
+        IntoSexpError::DuplicateKeyInMap { type_: "HashMap", key: None }
+            .with_field("WithIntMap", "m"),
+    );
+    test_err::<WithIntMap>(
+        "((name foo) (m ((a (1 2)))))",
+        IntoSexpError::StringConversionError { err: "invalid digit found in string".to_string() }
+            .with_map_key(&rsexp::atom(b"a"))
+            .with_field("WithIntMap", "m"),
+    );
+}
+
+#[derive(OfSexp, SexpOf, Debug, PartialEq, Eq)]
+struct WithPath {
+    path: std::path::PathBuf,
+}
+
+#[test]
+fn with_path() {
+    let path = std::path::PathBuf::from("/tmp/some dir/file.sexp");
+    test_rt(path.clone(), "\"/tmp/some dir/file.sexp\"");
+    assert_eq!(path.as_path().sexp_of(), path.sexp_of());
+    test_rt(WithPath { path }, "((path \"/tmp/some dir/file.sexp\"))");
+    test_err::<WithPath>(
+        "((path ()))",
+        expected_atom_got_list("PathBuf", 0).with_field("WithPath", "path"),
+    );
+}
+
+#[derive(OfSexp, SexpOf, Debug, PartialEq, Eq)]
+struct Counters {
+    hits: std::num::Wrapping<u32>,
+    level: std::num::Saturating<i8>,
+}
+
+#[test]
+fn wrapping_and_saturating() {
+    use std::num::{Saturating, Wrapping};
+    test_rt(Wrapping(u32::MAX), "4294967295");
+    test_rt(Saturating(-128i8), "-128");
+    let mut counters = Counters { hits: Wrapping(u32::MAX), level: Saturating(i8::MAX) };
+    test_rt(Counters { ..counters }, "((hits 4294967295) (level 127))");
+    counters.hits += 1;
+    counters.level += 1;
+    test_rt(counters, "((hits 0) (level 127))");
+    test_err::<Counters>(
+        "((hits -1) (level 0))",
+        IntoSexpError::StringConversionError { err: "invalid digit found in string".to_string() }
+            .with_field("Counters", "hits"),
+    );
+}
+
+#[derive(OfSexp, SexpOf, Debug, PartialEq, Eq)]
+#[sexp(all_errors)]
+struct WithExtra {
+    name: String,
+    #[sexp(collect_extra)]
+    extra: BTreeMap<String, rsexp::Sexp>,
+}
+
+#[derive(OfSexp, SexpOf, Debug, PartialEq, Eq)]
+enum EnumWithExtra {
+    Named {
+        x: i64,
+        #[sexp(collect_extra)]
+        others: BTreeMap<String, i64>,
+    },
+}
+
+#[test]
+fn collect_extra() {
+    test_rt(WithExtra { name: "foo".to_string(), extra: BTreeMap::new() }, "((name foo))");
+    let mut extra = BTreeMap::new();
+    extra.insert("a".to_string(), rsexp::atom(b"1"));
+    extra.insert("b".to_string(), rsexp::list(&[rsexp::atom(b"x"), rsexp::atom(b"y")]));
+    test_rt(WithExtra { name: "foo".to_string(), extra }, "((name foo) (a 1) (b (x y)))");
+    test_err::<WithExtra>("((a 1))", missing_fields("WithExtra", "name"));
+    let mut others = BTreeMap::new();
+    others.insert("y".to_string(), 2);
+    others.insert("z".to_string(), 3);
+    test_rt(EnumWithExtra::Named { x: 1, others }, "(Named (x 1) (y 2) (z 3))");
+    test_err::<EnumWithExtra>(
+        "(Named(x 1)(y ()))",
+        expected_atom_got_list("stringable", 0)
+            .with_map_key(&rsexp::atom(b"y"))
+            .with_field("EnumWithExtra :: Named", "others"),
+    );
+}
+
+#[derive(OfSexp, SexpOf, Debug, PartialEq, Eq)]
+struct WithElispBool {
+    enabled: rsexp::ElispBool,
+    flags: Vec<rsexp::ElispBool>,
+}
+
+#[test]
+fn elisp_bool() {
+    use rsexp::ElispBool;
+    test_rt(ElispBool(true), "t");
+    test_rt(ElispBool(false), "nil");
+    for (input, expected) in [("t", true), ("nil", false), ("()", false)] {
+        let sexp = rsexp::from_slice(input).unwrap();
+        assert_eq!(ElispBool::of_sexp(&sexp), Ok(ElispBool(expected)));
+    }
+    test_rt(
+        WithElispBool { enabled: ElispBool(true), flags: vec![ElispBool(false), ElispBool(true)] },
+        "((enabled t) (flags (nil t)))",
+    );
+    let sexp = rsexp::from_slice("((enabled ()) (flags (t () nil)))").unwrap();
+    let expected = WithElispBool {
+        enabled: ElispBool(false),
+        flags: vec![ElispBool(true), ElispBool(false), ElispBool(false)],
+    };
+    assert_eq!(WithElispBool::of_sexp(&sexp), Ok(expected));
+    test_err::<ElispBool>("(t)", expected_atom_got_list("ElispBool", 1));
+    test_err::<ElispBool>(
+        "true",
+        IntoSexpError::StringConversionError { err: "expected t or nil, got true".to_string() },
+    );
+}
+
+#[derive(OfSexp, SexpOf, Debug, PartialEq, Eq)]
+#[sexp(all_errors)]
+struct WithFlags {
+    name: String,
+    #[sexp(flag)]
+    verbose: bool,
+    #[sexp(flag)]
+    dry_run: bool,
+}
+
+#[derive(OfSexp, SexpOf, Debug, PartialEq, Eq)]
+enum EnumWithFlags {
+    Run {
+        #[sexp(flag)]
+        force: bool,
+    },
+}
+
+#[test]
+fn flags() {
+    test_rt(
+        WithFlags { name: "foo".to_string(), verbose: true, dry_run: false },
+        "((name foo) (verbose))",
+    );
+    test_rt(
+        WithFlags { name: "foo".to_string(), verbose: false, dry_run: true },
+        "((name foo) (dry_run))",
+    );
+    test_rt(WithFlags { name: "foo".to_string(), verbose: false, dry_run: false }, "((name foo))");
+    let sexp = rsexp::from_slice("((dry_run) (name bar) (verbose))").unwrap();
+    let expected = WithFlags { name: "bar".to_string(), verbose: true, dry_run: true };
+    assert_eq!(WithFlags::of_sexp(&sexp), Ok(expected));
+    test_rt(EnumWithFlags::Run { force: true }, "(Run (force))");
+    test_rt(EnumWithFlags::Run { force: false }, "(Run)");
+    test_err::<WithFlags>(
+        "((name foo) (verbose) (verbose))",
+        IntoSexpError::DuplicateKeyInMap { type_: "WithFlags", key: Some("verbose".to_string()) },
+    );
+    test_err::<WithFlags>(
+        "((name foo) (quiet))",
+        IntoSexpError::ExpectedPairForMapGotList { type_: "WithFlags", list_len: 1 },
+    );
+    test_err::<WithFlags>("((verbose))", missing_fields("WithFlags", "name"));
+}
+
+#[derive(OfSexp, SexpOf, Debug, PartialEq, Eq)]
+#[sexp(variant_style = "inline")]
+enum InlineVariants {
+    A,
+    B(),
+    C(i64),
+    D(i64, String),
+    E { x: i64 },
+}
+
+#[derive(OfSexp, SexpOf, Debug, PartialEq, Eq)]
+#[sexp(variant_style = "wrapped")]
+enum WrappedVariants {
+    A,
+    B(),
+    C(i64),
+    D(i64, String),
+    E { x: i64 },
+    F {},
+}
+
+#[test]
+fn variant_style() {
+    test_rt(InlineVariants::A, "A");
+    test_rt(InlineVariants::B(), "B");
+    test_rt(InlineVariants::C(42), "(C 42)");
+    test_rt(InlineVariants::D(1, "foo".to_string()), "(D 1 foo)");
+    test_rt(InlineVariants::E { x: 3 }, "(E (x 3))");
+    test_rt(WrappedVariants::A, "(A)");
+    test_rt(WrappedVariants::B(), "(B)");
+    test_rt(WrappedVariants::C(42), "((C) 42)");
+    test_rt(WrappedVariants::D(1, "foo".to_string()), "((D) 1 foo)");
+    test_rt(WrappedVariants::E { x: 3 }, "((E) (x 3))");
+    test_rt(WrappedVariants::F {}, "(F)");
+    test_err::<WrappedVariants>("A", expected_list_got_atom("WrappedVariants"));
+    test_err::<WrappedVariants>(
+        "(C 42)",
+        IntoSexpError::ExpectedWrappedConstructor { type_: "WrappedVariants" },
+    );
+    test_err::<WrappedVariants>(
+        "((C 42))",
+        IntoSexpError::ExpectedWrappedConstructor { type_: "WrappedVariants" },
+    );
+    test_err::<WrappedVariants>(
+        "()",
+        IntoSexpError::ExpectedConstructorGotEmptyList { type_: "WrappedVariants" },
+    );
+    test_err::<WrappedVariants>("((G) 1)", unknown_constructor("WrappedVariants", "G"));
+    test_err::<WrappedVariants>("((C) 1 2)", length_mismatch("WrappedVariants :: C", 1, 2));
+}
+
+#[derive(OfSexp, SexpOf, Debug, PartialEq, Eq)]
+enum Renamed {
+    #[sexp(alias = "Old")]
+    New,
+    #[sexp(alias = "OldValue", alias = "OlderValue")]
+    Value(i64),
+    #[sexp(alias = "OldRecord")]
+    Record { x: i64 },
+}
+
+#[test]
+fn variant_aliases() {
+    test_rt(Renamed::New, "New");
+    test_rt(Renamed::Value(1), "(Value 1)");
+    test_rt(Renamed::Record { x: 2 }, "(Record (x 2))");
+    let of_str = |s: &str| Renamed::of_sexp(&rsexp::from_slice(s).unwrap());
+    assert_eq!(of_str("Old"), Ok(Renamed::New));
+    assert_eq!(of_str("(OldValue 1)"), Ok(Renamed::Value(1)));
+    assert_eq!(of_str("(OlderValue 1)"), Ok(Renamed::Value(1)));
+    assert_eq!(of_str("(OldRecord (x 2))"), Ok(Renamed::Record { x: 2 }));
+    assert_eq!(Renamed::Value(1).sexp_of().to_string(), "(Value 1)");
+    test_err::<Renamed>("(OldValue 1 2)", length_mismatch("Renamed :: Value", 1, 2));
+    test_err::<Renamed>("Older", unknown_constructor("Renamed", "Older"));
+}
+
+#[derive(OfSexp, SexpOf, Debug, PartialEq, Eq)]
+enum Status {
+    #[sexp(rename = "in-progress")]
+    InProgress,
+    #[sexp(rename = "done", alias = "Done")]
+    Done(i64),
+    Failed {
+        reason: String,
+    },
+}
+
+#[derive(OfSexp, SexpOf, Debug, PartialEq, Eq)]
+#[sexp(variant_style = "wrapped")]
+enum WrappedStatus {
+    #[sexp(rename = "in progress")]
+    InProgress,
+    #[sexp(rename = "done")]
+    Done(i64),
+}
+
+#[test]
+fn variant_rename() {
+    test_rt(Status::InProgress, "in-progress");
+    test_rt(Status::Done(3), "(done 3)");
+    test_rt(Status::Failed { reason: "oops".to_string() }, "(Failed (reason oops))");
+    test_err::<Status>("InProgress", unknown_constructor("Status", "InProgress"));
+    assert_eq!(rsexp::from_slice("(Done 3)").unwrap().of_sexp(), Ok(Status::Done(3)));
+    test_err::<Status>("(done ())", expected_atom_got_list("stringable", 0).with_index(0));
+    test_rt(WrappedStatus::InProgress, "(\"in progress\")");
+    test_rt(WrappedStatus::Done(1), "((done) 1)");
+    test_err::<WrappedStatus>("((Done) 1)", unknown_constructor("WrappedStatus", "Done"));
+}
+
+#[derive(OfSexp, SexpOf, Debug, PartialEq, Eq, Clone, Copy)]
+#[sexp(by_index)]
+enum Color {
+    Red,
+    Green,
+    Blue,
+}
+
+#[test]
+fn by_index() {
+    test_rt(Color::Red, "0");
+    test_rt(Color::Green, "1");
+    test_rt(Color::Blue, "2");
+    let pixels = [Color::Blue, Color::Blue, Color::Red, Color::Green];
+    let sexp = rsexp::list(&pixels.map(|c| c.sexp_of()));
+    assert_eq!(sexp.to_string(), "(2 2 0 1)");
+    assert_eq!(sexp.of_sexp::<Vec<Color>>(), Ok(pixels.to_vec()));
+    test_err::<Color>("3", unknown_constructor("Color", "3"));
+    test_err::<Color>(
+        "Green",
+        IntoSexpError::StringConversionError { err: "invalid digit found in string".to_string() },
+    );
+    test_err::<Color>("(1)", expected_atom_got_list("stringable", 1));
+}
+
+mod hex {
+    pub fn sexp_of(bytes: &[u8]) -> rsexp::Sexp {
+        let hex: String = bytes.iter().map(|b| format!("{b:02x}")).collect();
+        rsexp::atom(hex.as_bytes())
+    }
+
+    pub fn of_sexp(sexp: &rsexp::Sexp) -> Result<Vec<u8>, rsexp::IntoSexpError> {
+        let hex: String = rsexp::OfSexp::of_sexp(sexp)?;
+        let err = |_| rsexp::IntoSexpError::StringConversionError { err: format!("bad hex {hex}") };
+        (0..hex.len())
+            .step_by(2)
+            .map(|i| {
+                hex.get(i..i + 2).map_or(Err(()), |h| u8::from_str_radix(h, 16).map_err(|_| ()))
+            })
+            .collect::<Result<_, ()>>()
+            .map_err(err)
+    }
+}
+
+fn upper_case(s: &str) -> rsexp::Sexp {
+    rsexp::atom(s.to_uppercase().as_bytes())
+}
+
+fn lower_case(sexp: &rsexp::Sexp) -> Result<String, rsexp::IntoSexpError> {
+    let s: String = rsexp::OfSexp::of_sexp(sexp)?;
+    Ok(s.to_lowercase())
+}
+
+#[derive(OfSexp, SexpOf, Debug, PartialEq, Eq)]
+#[sexp(all_errors)]
+struct WithCustomConversions {
+    #[sexp(with = "hex")]
+    digest: Vec<u8>,
+    #[sexp(serialize_with = "upper_case", deserialize_with = "lower_case")]
+    name: String,
+    #[sexp(serialize_with = "upper_case")]
+    tag: String,
+}
+
+#[derive(OfSexp, SexpOf, Debug, PartialEq, Eq)]
+struct HexTuple(#[sexp(with = "hex")] Vec<u8>, u8);
+
+#[derive(OfSexp, SexpOf, Debug, PartialEq, Eq)]
+enum HexEnum {
+    Digest(#[sexp(with = "hex")] Vec<u8>),
+    Named {
+        #[sexp(with = "hex")]
+        digest: Vec<u8>,
+    },
+}
+
+#[test]
+fn with() {
+    let value = WithCustomConversions {
+        digest: vec![0xde, 0xad, 0x00, 0x42],
+        name: "foo".to_string(),
+        tag: "BAR".to_string(),
+    };
+    test_rt(value, "((digest dead0042) (name FOO) (tag BAR))");
+    let value: WithCustomConversions =
+        rsexp::from_slice("((digest \"\") (name FoO) (tag bar))").unwrap().of_sexp().unwrap();
+    assert_eq!(value.digest, b"");
+    assert_eq!(value.name, "foo");
+    assert_eq!(value.tag, "bar");
+    test_err::<WithCustomConversions>(
+        "((digest xyz) (name a) (tag b))",
+        IntoSexpError::StringConversionError { err: "bad hex xyz".to_string() }
+            .with_field("WithCustomConversions", "digest"),
+    );
+    let errors = WithCustomConversions::of_sexp_all_errors(
+        &rsexp::from_slice("((digest 0) (name ()) (tag b))").unwrap(),
+    )
+    .unwrap_err();
+    assert_eq!(
+        errors,
+        [
+            IntoSexpError::StringConversionError { err: "bad hex 0".to_string() }
+                .with_field("WithCustomConversions", "digest"),
+            expected_atom_got_list("String", 0).with_field("WithCustomConversions", "name"),
+        ]
+    );
+    test_rt(HexTuple(vec![1, 255], 255), "(01ff 255)");
+    test_rt(HexEnum::Digest(vec![171]), "(Digest ab)");
+    test_rt(HexEnum::Named { digest: vec![171] }, "(Named (digest ab))");
+}
+
+#[derive(OfSexp, SexpOf, Debug, PartialEq, Eq)]
+#[sexp(transparent)]
+struct UserId(u64);
+
+#[derive(OfSexp, SexpOf, Debug, PartialEq, Eq)]
+#[sexp(transparent)]
+struct Email {
+    address: String,
+}
+
+#[derive(OfSexp, SexpOf, Debug, PartialEq, Eq)]
+struct User {
+    id: UserId,
+    emails: Vec<Email>,
+}
+
+#[test]
+fn transparent() {
+    test_rt(UserId(42), "42");
+    assert_eq!(UserId(42).sexp_of().to_bytes(), 42u64.sexp_of().to_bytes());
+    test_rt(Email { address: "foo@bar.com".to_string() }, "foo@bar.com");
+    let user = User { id: UserId(1), emails: vec![Email { address: "a b".to_string() }] };
+    test_rt(user, "((id 1) (emails (\"a b\")))");
+    test_err::<UserId>("(42)", expected_atom_got_list("stringable", 1));
+    test_err::<Email>("()", expected_atom_got_list("String", 0));
+}
+
+// Newer versions of this record added the trailing fields, older data without
+// them can still be read.
+#[derive(OfSexp, SexpOf, Debug, PartialEq, Eq)]
+#[sexp(positional)]
+struct PositionalRecord {
+    name: String,
+    count: i64,
+    #[sexp(default)]
+    tags: Vec<String>,
+    #[sexp(default)]
+    priority: Option<u8>,
+}
+
+#[derive(OfSexp, SexpOf, Debug, PartialEq, Eq)]
+struct TupleWithDefault(i64, #[sexp(default)] String);
+
+#[derive(OfSexp, SexpOf, Debug, PartialEq, Eq)]
+enum EnumWithDefault {
+    A(i64, #[sexp(default)] i64),
+}
+
+#[test]
+fn positional_defaults() {
+    let record = |tags: &[&str], priority| PositionalRecord {
+        name: "foo".to_string(),
+        count: 3,
+        tags: tags.iter().map(|t| t.to_string()).collect(),
+        priority,
+    };
+    test_rt(record(&["a", "b"], Some(1)), "(foo 3 (a b) (1))");
+    test_rt(record(&[], None), "(foo 3 () ())");
+    let of_str = |s: &str| PositionalRecord::of_sexp(&rsexp::from_slice(s).unwrap());
+    assert_eq!(of_str("(foo 3)"), Ok(record(&[], None)));
+    assert_eq!(of_str("(foo 3 (a))"), Ok(record(&["a"], None)));
+    test_err::<PositionalRecord>("(foo)", length_mismatch("PositionalRecord", 4, 1));
+    test_err::<PositionalRecord>("(foo 3 () () x)", length_mismatch("PositionalRecord", 4, 5));
+    test_err::<PositionalRecord>(
+        "(foo bar)",
+        IntoSexpError::StringConversionError { err: "invalid digit found in string".to_string() }
+            .with_field("PositionalRecord", "count"),
+    );
+    test_err::<PositionalRecord>(
+        "(foo 3 a)",
+        expected_list_got_atom("Vec").with_field("PositionalRecord", "tags"),
+    );
+
+    test_rt(TupleWithDefault(1, "x".to_string()), "(1 x)");
+    assert_eq!(rsexp::from_slice("(1)").unwrap().of_sexp(), Ok(TupleWithDefault(1, String::new())));
+    test_err::<TupleWithDefault>("()", length_mismatch("TupleWithDefault", 2, 0));
+    test_rt(EnumWithDefault::A(1, 2), "(A 1 2)");
+    assert_eq!(rsexp::from_slice("(A 1)").unwrap().of_sexp(), Ok(EnumWithDefault::A(1, 0)));
+}
+
+// Matches the following OCaml type.
+// type t = { id : int; comment : string option [@sexp.option] } [@@deriving sexp]
+#[derive(OfSexp, SexpOf, Debug, PartialEq, Eq)]
+#[sexp(all_errors)]
+struct WithSexpOption {
+    id: i64,
+    #[sexp(option)]
+    comment: Option<String>,
+}
+
+#[derive(OfSexp, SexpOf, Debug, PartialEq, Eq)]
+enum EnumWithSexpOption {
+    Retry {
+        #[sexp(option)]
+        max_attempts: Option<u32>,
+    },
+}
+
+#[test]
+fn sexp_option() {
+    test_rt(
+        WithSexpOption { id: 1, comment: Some("foo bar".to_string()) },
+        r#"((id 1) (comment "foo bar"))"#,
+    );
+    test_rt(WithSexpOption { id: 2, comment: None }, "((id 2))");
+    test_rt(EnumWithSexpOption::Retry { max_attempts: Some(3) }, "(Retry (max_attempts 3))");
+    test_rt(EnumWithSexpOption::Retry { max_attempts: None }, "(Retry)");
+    test_err::<WithSexpOption>("((comment foo))", missing_fields("WithSexpOption", "id"));
+    test_err::<WithSexpOption>(
+        "((id 1) (comment (foo)))",
+        expected_atom_got_list("String", 1).with_field("WithSexpOption", "comment"),
+    );
+    let of_str = |s: &str| WithSexpOption::of_sexp_all_errors(&rsexp::from_slice(s).unwrap());
+    assert_eq!(of_str("((id 3))"), Ok(WithSexpOption { id: 3, comment: None }));
+    assert_eq!(
+        of_str("((id x) (comment ()))"),
+        Err(vec![
+            IntoSexpError::StringConversionError {
+                err: "invalid digit found in string".to_string()
+            }
+            .with_field("WithSexpOption", "id"),
+            expected_atom_got_list("String", 0).with_field("WithSexpOption", "comment"),
+        ])
+    );
+}
+
+// Vectors nested in other types used to lack a SexpOf implementation.
+#[derive(OfSexp, SexpOf, Debug, PartialEq, Eq)]
+struct X {
+    v: Option<Vec<String>>,
+}
+
+#[derive(OfSexp, SexpOf, Debug, PartialEq, Eq)]
+struct NestedVecs {
+    #[sexp(option)]
+    tags: Option<Vec<String>>,
+    matrix: Vec<Vec<i32>>,
+    pairs: Vec<(String, Vec<u8>)>,
+}
+
+#[test]
+fn nested_vecs() {
+    test_rt(X { v: None }, "((v ()))");
+    test_rt(X { v: Some(vec![]) }, "((v (())))");
+    test_rt(X { v: Some(vec!["a".to_string(), "b c".to_string()]) }, r#"((v ((a "b c"))))"#);
+    test_err::<X>(
+        "((v ((a (b)))))",
+        expected_atom_got_list("String", 1).with_list_index(1).with_field("X", "v"),
+    );
+    test_rt(
+        NestedVecs {
+            tags: Some(vec!["x".to_string()]),
+            matrix: vec![vec![1, 2], vec![]],
+            pairs: vec![("p".to_string(), vec![3])],
+        },
+        "((tags (x)) (matrix ((1 2) ())) (pairs ((p (3)))))",
+    );
+    test_rt(NestedVecs { tags: None, matrix: vec![], pairs: vec![] }, "((matrix ()) (pairs ()))");
+}
+
+// Matches the following OCaml type.
+// type t = { name : string; deps : string list [@sexp.list] } [@@deriving sexp]
+#[derive(OfSexp, SexpOf, Debug, PartialEq, Eq)]
+#[sexp(all_errors)]
+struct WithFlattenList {
+    name: String,
+    #[sexp(flatten_list)]
+    deps: Vec<String>,
+    nested: Vec<i32>,
+}
+
+#[derive(OfSexp, SexpOf, Debug, PartialEq, Eq)]
+enum EnumWithFlattenList {
+    Build {
+        #[sexp(flatten_list)]
+        targets: Vec<PairInt>,
+    },
+}
+
+#[test]
+fn flatten_list() {
+    test_rt(
+        WithFlattenList {
+            name: "foo".to_string(),
+            deps: vec!["bar".to_string(), "baz qux".to_string()],
+            nested: vec![1, 2],
+        },
+        r#"((name foo) (deps bar "baz qux") (nested (1 2)))"#,
+    );
+    // As in OCaml, the field is omitted when empty and defaults to an empty
+    // vector when missing.
+    let empty = WithFlattenList { name: "foo".to_string(), deps: vec![], nested: vec![] };
+    test_rt(empty, "((name foo) (nested ()))");
+    assert_eq!(
+        WithFlattenList::of_sexp(&rsexp::from_slice("((name foo) (deps) (nested ()))").unwrap()),
+        Ok(WithFlattenList { name: "foo".to_string(), deps: vec![], nested: vec![] })
+    );
+    test_rt(EnumWithFlattenList::Build { targets: vec![PairInt(1, 2)] }, "(Build (targets (1 2)))");
+    test_rt(EnumWithFlattenList::Build { targets: vec![] }, "(Build)");
+    test_err::<WithFlattenList>(
+        "((name foo) (deps a) (deps b) (nested ()))",
+        IntoSexpError::DuplicateKeyInMap {
+            type_: "WithFlattenList",
+            key: Some("deps".to_string()),
+        },
+    );
+    test_err::<WithFlattenList>(
+        "((name foo) (deps a (b)) (nested ()))",
+        expected_atom_got_list("String", 1)
+            .with_list_index(1)
+            .with_field("WithFlattenList", "deps"),
+    );
+    test_err::<EnumWithFlattenList>(
+        "(Build (targets (1 2) 3))",
+        expected_list_got_atom("PairInt")
+            .with_list_index(1)
+            .with_field("EnumWithFlattenList :: Build", "targets"),
+    );
+    let of_str = |s: &str| WithFlattenList::of_sexp_all_errors(&rsexp::from_slice(s).unwrap());
+    assert_eq!(
+        of_str("((deps a b) (nested ()))"),
+        Err(vec![missing_fields("WithFlattenList", "name")])
+    );
+    assert_eq!(
+        of_str("((name foo) (nested (1 2)))"),
+        Ok(WithFlattenList { name: "foo".to_string(), deps: vec![], nested: vec![1, 2] })
+    );
+}
+
+#[derive(OfSexp, SexpOf, Debug, PartialEq, Eq)]
+#[sexp(flat_kv, all_errors)]
+struct FlatKv {
+    host: String,
+    port: u16,
+    #[sexp(option)]
+    user: Option<String>,
+}
+
+#[test]
+fn flat_kv() {
+    test_rt(
+        FlatKv { host: "localhost".to_string(), port: 8080, user: Some("root".to_string()) },
+        "(host localhost port 8080 user root)",
+    );
+    test_rt(
+        FlatKv { host: "localhost".to_string(), port: 22, user: None },
+        "(host localhost port 22)",
+    );
+    let sexp = rsexp::from_slice("(port 1 host foo)").unwrap();
+    let expected = FlatKv { host: "foo".to_string(), port: 1, user: None };
+    assert_eq!(FlatKv::of_sexp(&sexp), Ok(expected));
+    test_err::<FlatKv>(
+        "(host foo port)",
+        IntoSexpError::ExpectedEvenLengthForFlatKv { type_: "FlatKv", list_len: 3 },
+    );
+    test_err::<FlatKv>("(host foo)", missing_fields("FlatKv", "port"));
+    test_err::<FlatKv>("(host foo port 1 other 2)", extra_fields("FlatKv", &["other"]));
+    let of_str = |s: &str| FlatKv::of_sexp_all_errors(&rsexp::from_slice(s).unwrap());
+    assert_eq!(
+        of_str("(host foo port 1 user)"),
+        Err(vec![IntoSexpError::ExpectedEvenLengthForFlatKv { type_: "FlatKv", list_len: 5 }])
+    );
+    assert_eq!(
+        of_str("(port x user (a))"),
+        Err(vec![
+            missing_fields("FlatKv", "host"),
+            IntoSexpError::StringConversionError {
+                err: "invalid digit found in string".to_string()
+            }
+            .with_field("FlatKv", "port"),
+            expected_atom_got_list("String", 1).with_field("FlatKv", "user"),
+        ])
+    );
+}
+
+mod validate {
+    pub fn non_zero_port(port: &u16) -> Result<(), String> {
+        if *port == 0 {
+            return Err("port must be in 1..=65535".to_string());
+        }
+        Ok(())
+    }
+
+    pub fn non_empty(s: &str) -> Result<(), String> {
+        if s.is_empty() {
+            return Err("empty string".to_string());
+        }
+        Ok(())
+    }
+
+    pub fn non_empty_opt(s: &Option<String>) -> Result<(), String> {
+        s.as_deref().map_or(Ok(()), non_empty)
+    }
+}
+
+#[derive(OfSexp, SexpOf, Debug, PartialEq, Eq)]
+#[sexp(all_errors)]
+struct Validated {
+    #[sexp(validate = "validate::non_empty")]
+    host: String,
+    #[sexp(validate = "validate::non_zero_port")]
+    port: u16,
+    #[sexp(option, validate = "validate::non_empty_opt")]
+    user: Option<String>,
+}
+
+#[test]
+fn validate() {
+    test_rt(
+        Validated { host: "localhost".to_string(), port: 8080, user: None },
+        "((host localhost) (port 8080))",
+    );
+    let validation_failed = |field, msg: &str| IntoSexpError::ValidationFailed {
+        type_: "Validated",
+        field,
+        msg: msg.to_string(),
+    };
+    test_err::<Validated>(
+        "((host localhost) (port 0))",
+        validation_failed("port", "port must be in 1..=65535"),
+    );
+    test_err::<Validated>(
+        "((host localhost) (port 22) (user \"\"))",
+        validation_failed("user", "empty string"),
+    );
+    let of_str = |s: &str| Validated::of_sexp_all_errors(&rsexp::from_slice(s).unwrap());
+    assert_eq!(
+        of_str("((host \"\") (port 0) (user root))"),
+        Err(vec![
+            validation_failed("host", "empty string"),
+            validation_failed("port", "port must be in 1..=65535"),
+        ])
+    );
+    // Fields that failed to convert are not validated.
+    assert_eq!(
+        of_str("((host \"\") (port x))"),
+        Err(vec![
+            IntoSexpError::StringConversionError {
+                err: "invalid digit found in string".to_string()
+            }
+            .with_field("Validated", "port"),
+            validation_failed("host", "empty string"),
+        ])
+    );
+}
+
+#[derive(OfSexp, Debug, PartialEq)]
+#[sexp(all_errors)]
+struct Nested {
+    name: String,
+    points: Vec<(i32, (f64, f64))>,
+    breakfasts: BTreeMap<String, Vec<Breakfasts>>,
+}
+
+#[test]
+fn nested_error_context() {
+    let breakfast = "((pancakes (1)) (more_pancakes ()) (value1 2) (value2 (1.5 2.5)))";
+    let nested = format!("((name n) (points ((1 (2 3)))) (breakfasts ((a ({breakfast})))))");
+    let nested = Nested::of_sexp(&rsexp::from_slice(&nested).unwrap()).unwrap();
+    assert_eq!(nested.breakfasts["a"][0].value2, (1.5, 2.5));
+    let err = |s: &str| Nested::of_sexp(&rsexp::from_slice(s).unwrap()).unwrap_err();
+    let invalid_float =
+        || IntoSexpError::StringConversionError { err: "invalid float literal".to_string() };
+    let e = err("((name n) (points ((1 (2 3)) (4 (5 x)))) (breakfasts ()))");
+    assert_eq!(
+        e,
+        invalid_float()
+            .with_index(1)
+            .with_index(1)
+            .with_list_index(1)
+            .with_field("Nested", "points")
+    );
+    assert_eq!(
+        e.to_string(),
+        "field points of Nested: list element 1: tuple element 1: tuple element 1: \
+         StringConversionError { err: \"invalid float literal\" }"
+    );
+    let breakfast = "((pancakes (1)) (more_pancakes ()) (value1 2) (value2 (1.5 y)))";
+    let e = err(&format!("((name n) (points ()) (breakfasts ((a ()) (b ({breakfast})))))"));
+    assert_eq!(
+        e,
+        invalid_float()
+            .with_index(1)
+            .with_field("Breakfasts", "value2")
+            .with_list_index(0)
+            .with_map_key(&rsexp::atom(b"b"))
+            .with_field("Nested", "breakfasts")
+    );
+    assert_eq!(
+        e.to_string(),
+        "field breakfasts of Nested: map entry b: list element 0: field value2 of Breakfasts: \
+         tuple element 1: StringConversionError { err: \"invalid float literal\" }"
+    );
+    let e = Nested::of_sexp_all_errors(
+        &rsexp::from_slice("((name ()) (points ((1 (2 3)) (4 5))) (breakfasts ()))").unwrap(),
+    );
+    assert_eq!(
+        e.unwrap_err(),
+        [
+            expected_atom_got_list("String", 0).with_field("Nested", "name"),
+            expected_list_got_atom("tuple")
+                .with_index(1)
+                .with_list_index(1)
+                .with_field("Nested", "points"),
+        ]
+    );
+}
+
+#[derive(OfSexp, SexpOf, Debug, PartialEq, Eq)]
+#[sexp(all_errors)]
+struct Form {
+    name: String,
+    age: u8,
+    email: String,
+    tags: Vec<String>,
+}
+
+#[test]
+fn all_errors() {
+    let of_str = |s: &str| Form::of_sexp_all_errors(&rsexp::from_slice(s).unwrap());
+    assert_eq!(
+        of_str("((name foo) (age 42) (email foo@bar.com) (tags (a b)))"),
+        Ok(Form {
+            name: "foo".to_string(),
+            age: 42,
+            email: "foo@bar.com".to_string(),
+            tags: vec!["a".to_string(), "b".to_string()]
+        })
+    );
+    assert_eq!(
+        of_str("((name foo) (tags ()))"),
+        Err(vec![missing_fields("Form", "age"), missing_fields("Form", "email")])
+    );
+    assert_eq!(
+        of_str("((name (foo)) (age 1234) (tags ()) (extra 1))"),
+        Err(vec![
+            expected_atom_got_list("String", 1).with_field("Form", "name"),
+            IntoSexpError::StringConversionError {
+                err: "number too large to fit in target type".to_string()
+            }
+            .with_field("Form", "age"),
+            missing_fields("Form", "email"),
+            extra_fields("Form", &["extra"]),
+        ])
+    );
+    // Errors about the overall shape of the sexp are reported alone.
+    assert_eq!(of_str("foo"), Err(vec![expected_list_got_atom("Form")]));
+    assert_eq!(
+        of_str("((name foo) (name bar))"),
+        Err(vec![IntoSexpError::DuplicateKeyInMap {
+            type_: "Form",
+            key: Some("name".to_string())
+        }])
+    );
+    let sexp = rsexp::from_slice("((name foo) (verbose) (other 1))").unwrap();
+    assert_eq!(
+        WithFlags::of_sexp_all_errors(&sexp),
+        Err(vec![extra_fields("WithFlags", &["other"])])
+    );
+    let sexp = rsexp::from_slice("((name foo) (verbose))").unwrap();
+    assert_eq!(
+        WithFlags::of_sexp_all_errors(&sexp),
+        WithFlags::of_sexp(&sexp).map_err(|e| vec![e])
+    );
+    let sexp = rsexp::from_slice("((a 1) (b (2)))").unwrap();
+    assert_eq!(
+        WithExtra::of_sexp_all_errors(&sexp),
+        Err(vec![missing_fields("WithExtra", "name")])
+    );
+
+    // The errors for the extra fields are reported in the input order.
+    #[derive(OfSexp, Debug, PartialEq)]
+    #[sexp(all_errors)]
+    struct Counts {
+        name: String,
+        #[sexp(collect_extra)]
+        counts: BTreeMap<String, i64>,
+    }
+    let count_err = |key: &str, value: &str| {
+        i64::of_sexp(&rsexp::atom(value.as_bytes()))
+            .unwrap_err()
+            .with_map_key(&rsexp::atom(key.as_bytes()))
+            .with_field("Counts", "counts")
+    };
+    let sexp = rsexp::from_slice("((d x) (name foo) (b y) (c 3) (a z))").unwrap();
+    assert_eq!(
+        Counts::of_sexp_all_errors(&sexp),
+        Err(vec![count_err("d", "x"), count_err("b", "y"), count_err("a", "z")])
+    );
+    assert_eq!(Counts::of_sexp(&sexp), Err(count_err("d", "x")));
+}
+
+impl Arbitrary for Pancakes {
+    fn arbitrary(g: &mut Gen) -> Self {
+        Pancakes(i64::arbitrary(g))
+    }
+}
+
+impl Arbitrary for MorePancakes {
+    fn arbitrary(g: &mut Gen) -> Self {
+        MorePancakes(i64::arbitrary(g), f64::arbitrary(g), Option::arbitrary(g))
+    }
+}
+
+impl Arbitrary for BreakfastsEq {
+    fn arbitrary(g: &mut Gen) -> Self {
+        BreakfastsEq {
+            pancakes: Pancakes::arbitrary(g),
+            more_pancakes: Option::arbitrary(g),
+            value1: i32::arbitrary(g),
+            value2: <(i64, i64)>::arbitrary(g),
+        }
+    }
+}
+
+impl Arbitrary for MyEnum2 {
+    fn arbitrary(g: &mut Gen) -> Self {
+        match u8::arbitrary(g) % 9 {
+            0 => MyEnum2::A,
+            1 => MyEnum2::AEmptyTuple(),
+            2 => MyEnum2::AEmptyStruct {},
+            3 => MyEnum2::B(()),
+            4 => MyEnum2::C(i64::arbitrary(g)),
+            5 => MyEnum2::D(i64::arbitrary(g), i64::arbitrary(g)),
+            6 => MyEnum2::E(PairInt(i64::arbitrary(g), i64::arbitrary(g))),
+            7 => MyEnum2::F { x: i64::arbitrary(g), y: String::arbitrary(g) },
+            _ => MyEnum2::G(StructXYZ {
+                x: i64::arbitrary(g),
+                y: Option::arbitrary(g),
+                z: String::arbitrary(g),
+            }),
+        }
+    }
+}
+
+// Converts to a sexp and back, both directly and via the serialized forms.
+fn round_trips<T: SexpOf + OfSexp>(t: &T) -> Vec<T> {
+    let sexp = t.sexp_of();
+    let of_bytes = |bytes: Vec<u8>| rsexp::from_slice(&bytes).unwrap().of_sexp().unwrap();
+    vec![sexp.of_sexp().unwrap(), of_bytes(sexp.to_bytes()), of_bytes(sexp.to_bytes_hum())]
+}
+
+#[quickcheck]
+fn round_trip_pancakes(t: Pancakes) -> bool {
+    round_trips(&t).iter().all(|t2| *t2 == t)
+}
+
+#[quickcheck]
+fn round_trip_more_pancakes(t: MorePancakes) -> bool {
+    // NaN values are not equal to themselves, other floats are compared
+    // bitwise to distinguish 0 from -0.
+    let float_eq = |f1: f64, f2: f64| f1.to_bits() == f2.to_bits() || f1.is_nan() && f2.is_nan();
+    round_trips(&t).iter().all(|t2| t2.0 == t.0 && float_eq(t2.1, t.1) && t2.2 == t.2)
+}
+
+#[quickcheck]
+fn round_trip_breakfasts_eq(t: BreakfastsEq) -> bool {
+    round_trips(&t).iter().all(|t2| *t2 == t)
+}
+
+#[quickcheck]
+fn round_trip_my_enum2(t: MyEnum2) -> bool {
+    round_trips(&t).iter().all(|t2| *t2 == t)
+}
@@ -0,0 +1,6 @@
+// Checks the compile errors reported by the derive macros.
+#[test]
+fn ui() {
+    let t = trybuild::TestCases::new();
+    t.compile_fail("tests/ui/*.rs");
+}
@@ -0,0 +1,159 @@
+#![cfg(feature = "serde")]
+use rsexp::{DeError, IntoSexpError, OfSexp, SexpOf};
+use rsexp_derive::{OfSexp, SexpOf};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+fn test_same<T>(t: T, str: &str)
+where
+    T: SexpOf + OfSexp + Serialize + serde::de::DeserializeOwned + std::fmt::Debug + PartialEq,
+{
+    let sexp = rsexp::to_sexp(&t).unwrap();
+    assert_eq!(sexp, t.sexp_of());
+    assert_eq!(sexp.to_string(), str);
+    let sexp = rsexp::from_slice(str).unwrap();
+    assert_eq!(rsexp::from_sexp::<T>(&sexp).unwrap(), T::of_sexp(&sexp).unwrap());
+}
+
+#[derive(SexpOf, OfSexp, Serialize, Deserialize, Debug, PartialEq)]
+struct Pancakes(i64);
+
+#[derive(SexpOf, OfSexp, Serialize, Deserialize, Debug, PartialEq)]
+struct MorePancakes(i64, f64, Option<i64>);
+
+#[derive(SexpOf, OfSexp, Serialize, Deserialize, Debug, PartialEq)]
+enum Topping {
+    Butter,
+    Syrup(String),
+    Fruits(String, i32),
+    Cream {},
+    Custom { name: String, quantity: Option<u8> },
+}
+
+#[derive(SexpOf, OfSexp, Serialize, Deserialize, Debug, PartialEq)]
+struct Breakfast {
+    pancakes: Pancakes,
+    more_pancakes: Option<MorePancakes>,
+    toppings: Vec<Topping>,
+    prices: BTreeMap<String, (f64, bool)>,
+    unit: (),
+}
+
+#[test]
+fn serde_matches_derive() {
+    test_same(Pancakes(12345), "(12345)");
+    test_same(MorePancakes(12, 3.5, None), "(12 3.5 ())");
+    test_same(Topping::Butter, "Butter");
+    test_same(Topping::Syrup("maple".to_string()), "(Syrup maple)");
+    test_same(Topping::Fruits("kiwi".to_string(), 3), "(Fruits kiwi 3)");
+    test_same(Topping::Cream {}, "Cream");
+    test_same(
+        Topping::Custom { name: "choco late".to_string(), quantity: Some(2) },
+        "(Custom (name \"choco late\") (quantity (2)))",
+    );
+    let mut prices = BTreeMap::new();
+    prices.insert("pancakes".to_string(), (4.5, true));
+    prices.insert("syrup".to_string(), (f64::INFINITY, false));
+    test_same(
+        Breakfast {
+            pancakes: Pancakes(3),
+            more_pancakes: Some(MorePancakes(1, -2.25, Some(42))),
+            toppings: vec![Topping::Butter, Topping::Syrup("maple".to_string())],
+            prices,
+            unit: (),
+        },
+        "((pancakes (3)) (more_pancakes ((1 -2.25 (42)))) (toppings (Butter (Syrup maple))) (prices ((pancakes (4.5 true)) (syrup (inf false)))) (unit ()))",
+    );
+}
+
+fn to_string<T: Serialize>(t: T) -> String {
+    rsexp::to_sexp(&t).unwrap().to_string()
+}
+
+#[test]
+fn serde_values() {
+    assert_eq!(to_string("foo bar"), "\"foo bar\"");
+    assert_eq!(to_string('x'), "x");
+    assert_eq!(to_string(u128::MAX), "340282366920938463463374607431768211455");
+    assert_eq!(to_string(vec![Some(1), None]), "((1) ())");
+    assert_eq!(to_string([(1, 2), (3, 4)]), "((1 2) (3 4))");
+    let mut map = BTreeMap::new();
+    map.insert((1, 2), "a");
+    assert_eq!(to_string(map), "(((1 2) a))");
+}
+
+fn from_str<T: serde::de::DeserializeOwned>(s: &str) -> Result<T, DeError> {
+    rsexp::from_sexp(&rsexp::from_slice(s).unwrap())
+}
+
+#[test]
+fn serde_round_trip() {
+    let mut prices = BTreeMap::new();
+    prices.insert("pancakes".to_string(), (4.5, true));
+    let breakfast = Breakfast {
+        pancakes: Pancakes(3),
+        more_pancakes: None,
+        toppings: vec![
+            Topping::Cream {},
+            Topping::Fruits("kiwi".to_string(), 2),
+            Topping::Custom { name: "choco".to_string(), quantity: None },
+        ],
+        prices,
+        unit: (),
+    };
+    let sexp = rsexp::to_sexp(&breakfast).unwrap();
+    assert_eq!(rsexp::from_sexp::<Breakfast>(&sexp), Ok(breakfast));
+    let sexp = rsexp::to_sexp(&vec![Some((1u8, 'c')), None]).unwrap();
+    assert_eq!(rsexp::from_sexp::<Vec<Option<(u8, char)>>>(&sexp), Ok(vec![Some((1, 'c')), None]));
+}
+
+#[test]
+fn serde_errors() {
+    assert_eq!(
+        from_str::<MorePancakes>("(1 2)"),
+        Err(DeError::IntoSexpError(IntoSexpError::ListLengthMismatch {
+            type_: "MorePancakes",
+            expected_len: 3,
+            list_len: 2
+        }))
+    );
+    assert_eq!(
+        from_str::<Pancakes>("(x)"),
+        Err(DeError::IntoSexpError(IntoSexpError::StringConversionError {
+            err: "invalid digit found in string".to_string()
+        }))
+    );
+    assert_eq!(
+        from_str::<Option<i32>>("(1 2)"),
+        Err(DeError::IntoSexpError(IntoSexpError::ListLengthMismatch {
+            type_: "option",
+            expected_len: 1,
+            list_len: 2
+        }))
+    );
+    assert_eq!(
+        from_str::<Topping>("(Custom (quantity ()))"),
+        Err(DeError::Custom("missing field `name`".to_string()))
+    );
+    // Following serde conventions, missing optional fields are set to None.
+    assert_eq!(
+        from_str::<Topping>("(Custom (name foo))"),
+        Ok(Topping::Custom { name: "foo".to_string(), quantity: None })
+    );
+    assert_eq!(
+        from_str::<Topping>("(Custom (name foo bar))"),
+        Err(DeError::IntoSexpError(IntoSexpError::ExpectedPairForMapGotList {
+            type_: "Topping",
+            list_len: 3
+        }))
+    );
+    assert_eq!(
+        from_str::<Topping>("(Butter 1)"),
+        Err(DeError::IntoSexpError(IntoSexpError::ListLengthMismatch {
+            type_: "Topping",
+            expected_len: 0,
+            list_len: 1
+        }))
+    );
+    assert!(matches!(from_str::<Topping>("Sugar"), Err(DeError::Custom(_))));
+}
@@ -2,7 +2,7 @@ extern crate quickcheck;
 #[macro_use(quickcheck)]
 extern crate quickcheck_macros;
 
-use rsexp::{from_slice, Sexp};
+use rsexp::{from_slice, IntoSexpError, Sexp, SexpOf};
 
 use quickcheck::Arbitrary;
 
@@ -24,6 +24,27 @@ fn arbitrary_(g: &mut quickcheck::Gen, max_depth: u8) -> Sexp {
 #[derive(Debug, PartialEq, Eq, Clone)]
 struct QSexp(Sexp);
 
+// Sexps whose atoms only use the bytes that are significant to the parser, so
+// that sequences such as `#|`, `|#` or `#;` get generated frequently.
+#[derive(Debug, PartialEq, Eq, Clone)]
+struct QSpecialSexp(Sexp);
+
+impl quickcheck::Arbitrary for QSpecialSexp {
+    fn arbitrary(g: &mut quickcheck::Gen) -> Self {
+        fn arbitrary_(g: &mut quickcheck::Gen, max_depth: u8) -> Sexp {
+            if max_depth == 0 || bool::arbitrary(g) {
+                let len = usize::arbitrary(g) % 6;
+                let special = b"#|;\"() \t\n\r\\xa0\x00\xff";
+                Sexp::Atom((0..len).map(|_| *g.choose(special).unwrap()).collect())
+            } else {
+                let len = usize::arbitrary(g) % 6;
+                Sexp::List((0..len).map(|_| arbitrary_(g, max_depth - 1)).collect())
+            }
+        }
+        QSpecialSexp(arbitrary_(g, 3))
+    }
+}
+
 impl quickcheck::Arbitrary for QSexp {
     fn arbitrary(g: &mut quickcheck::Gen) -> Self {
         QSexp(arbitrary_(g, 4))
@@ -46,6 +67,1086 @@ fn round_trip(sexp: QSexp) -> bool {
         && from_slice(&sexp.to_bytes_hum()).unwrap() == sexp
 }
 
+#[quickcheck]
+fn round_trip_special_bytes(sexp: QSpecialSexp) -> bool {
+    let sexp = sexp.0;
+    from_slice(&sexp.to_bytes()).unwrap() == sexp
+        && from_slice(&sexp.to_bytes_hum()).unwrap() == sexp
+        && from_slice(&sexp.to_bytes_mach()).unwrap() == sexp
+        && rsexp::from_slice_ref(&sexp.to_bytes_mach()).unwrap().to_owned() == sexp
+}
+
+#[cfg(feature = "quickcheck")]
+#[quickcheck]
+fn round_trip_arbitrary(sexp: Sexp) -> bool {
+    from_slice(&sexp.to_bytes()) == Ok(sexp.clone())
+        && from_slice(&sexp.to_bytes_hum()) == Ok(sexp.clone())
+        && from_slice(&sexp.to_bytes_mach()) == Ok(sexp)
+}
+
+#[cfg(feature = "quickcheck")]
+#[test]
+fn shrink() {
+    let sexp = from_slice("(a (b \"c d\") ())").unwrap();
+    let shrunk: Vec<Sexp> = sexp.shrink().collect();
+    // The elements come first, followed by the smaller lists.
+    assert_eq!(shrunk[..3], *sexp.as_list().unwrap());
+    for smaller in ["()", "((b \"c d\") ())", "(a ())", "(a (b \"c d\"))", "(a (\"c d\") ())"] {
+        assert!(shrunk.contains(&from_slice(smaller).unwrap()), "{smaller}");
+    }
+    assert!(!shrunk.contains(&sexp));
+    assert_eq!(rsexp::atom(b"").shrink().count(), 0);
+    assert_eq!(rsexp::list(&[]).shrink().count(), 0);
+    // The shrunk values round-trip, this recursively goes down to the empty
+    // atom and list.
+    let mut to_visit = shrunk;
+    while let Some(sexp) = to_visit.pop() {
+        assert_eq!(from_slice(&sexp.to_bytes()), Ok(sexp.clone()));
+        to_visit.extend(sexp.shrink().take(3));
+    }
+}
+
+#[cfg(feature = "proptest")]
+proptest::proptest! {
+    #[test]
+    fn proptest_round_trip(sexp in rsexp::sexp_strategy()) {
+        proptest::prop_assert_eq!(from_slice(&sexp.to_bytes()), Ok(sexp.clone()));
+        proptest::prop_assert_eq!(from_slice(&sexp.to_bytes_hum()), Ok(sexp));
+    }
+
+    #[test]
+    fn proptest_limits(sexp in rsexp::sexp_strategy_with_limits(2, 3)) {
+        fn depth(sexp: &Sexp) -> usize {
+            sexp.iter().map(|s| 1 + depth(s)).max().unwrap_or(usize::from(sexp.is_list()))
+        }
+        proptest::prop_assert!(depth(&sexp) <= 2, "{}", sexp);
+        let max_atom_len = sexp.fold(0, |acc, s| acc.max(s.as_atom().map_or(0, |a| a.len())));
+        proptest::prop_assert!(max_atom_len <= 3, "{}", sexp);
+    }
+}
+
+#[quickcheck]
+fn serialized_len(sexp: QSexp) -> bool {
+    let sexp = sexp.0;
+    sexp.serialized_len() == sexp.to_bytes().len()
+}
+
+#[quickcheck]
+fn round_trip_ref(sexp: QSexp) -> bool {
+    let sexp = sexp.0;
+    let bytes = sexp.to_bytes_hum();
+    rsexp::from_slice_ref(&bytes).unwrap().to_owned() == sexp
+}
+
+#[quickcheck]
+fn round_trip_canonical(sexp: QSexp) -> bool {
+    let sexp = sexp.0;
+    rsexp::from_slice_canonical(&sexp.to_bytes_canonical()).unwrap() == sexp
+}
+
+#[test]
+fn canonical() {
+    let sexp = from_slice(r#"(foo (bar "") ("a (b) c" "x\000y\n"))"#).unwrap();
+    let bytes = sexp.to_bytes_canonical();
+    assert_eq!(bytes, b"(3:foo(3:bar0:)(7:a (b) c4:x\0y\n))");
+    assert_eq!(rsexp::from_slice_canonical(&bytes), Ok(sexp));
+    assert_eq!(rsexp::from_slice_canonical("()"), Ok(Sexp::List(vec![])));
+    assert_eq!(rsexp::from_slice_canonical("2:()"), Ok(rsexp::atom(b"()")));
+    let long_atom = vec![b')'; 1234];
+    let sexp = Sexp::List(vec![rsexp::atom(&long_atom)]);
+    assert!(sexp.to_bytes_canonical().starts_with(b"(1234:)))"));
+    assert_eq!(rsexp::from_slice_canonical(&sexp.to_bytes_canonical()), Ok(sexp));
+
+    let err = |s: &str| rsexp::from_slice_canonical(s).unwrap_err();
+    assert_eq!(err(""), rsexp::Error::UnexpectedEof { offset: 0 });
+    assert_eq!(err("(3:foo"), rsexp::Error::UnexpectedEof { offset: 6 });
+    assert_eq!(err("(4:foo)"), rsexp::Error::UnexpectedEof { offset: 7 });
+    assert_eq!(err("3:foo)"), rsexp::Error::UnexpectedEof { offset: 5 });
+    assert_eq!(err("99999999999999999999999:a"), rsexp::Error::UnexpectedEof { offset: 25 });
+    assert_eq!(err("(foo)"), rsexp::Error::UnexpectedChar { c: b'f', offset: 1 });
+    assert_eq!(err("(3:foo 3:bar)"), rsexp::Error::UnexpectedChar { c: b' ', offset: 6 });
+    assert_eq!(err("03:foo"), rsexp::Error::UnexpectedChar { c: b'0', offset: 0 });
+    assert_eq!(err(")"), rsexp::Error::UnexpectedChar { c: b')', offset: 0 });
+}
+
+#[test]
+fn canonical_transport() {
+    for (raw, transport) in [
+        ("(3:foo(3:bar0:))", "{KDM6Zm9vKDM6YmFyMDopKQ==}"),
+        ("(7:a (b) c4:x\0y\n)", "{KDc6YSAoYikgYzQ6eAB5Cik=}"),
+        ("(7:a (b) c4:x\0y\n)", "{KDc6YSAo\n YikgYzQ6\n eAB5Cik}"),
+        ("3:abc", "{MzphYmM=}"),
+        ("()", "{KCk=}"),
+    ] {
+        let sexp = rsexp::from_slice_canonical(raw).unwrap();
+        assert_eq!(rsexp::from_slice_canonical(transport), Ok(sexp), "{transport}");
+    }
+    let err = |s: &str| rsexp::from_slice_canonical(s).unwrap_err();
+    assert_eq!(err("{KCk="), rsexp::Error::UnexpectedEof { offset: 5 });
+    assert_eq!(err("{KCk=} "), rsexp::Error::UnexpectedEof { offset: 6 });
+    assert_eq!(err("{KC*k=}"), rsexp::Error::UnexpectedChar { c: b'*', offset: 3 });
+    assert_eq!(err("{KC=k}"), rsexp::Error::UnexpectedChar { c: b'k', offset: 4 });
+    // Errors in the decoded representation use offsets in the decoded bytes.
+    assert_eq!(err("{KDM6Zm9v}"), rsexp::Error::UnexpectedEof { offset: 6 });
+}
+
+#[quickcheck]
+fn max_line_width_hum(sexp: QSexp, wrap_width: u8) -> bool {
+    let sexp = sexp.0;
+    let wrap_width = wrap_width as usize;
+    let mut buffer = Vec::new();
+    let cfg = rsexp::HumConfig { max_line_width: wrap_width, ..Default::default() };
+    sexp.write_hum_with_config(&cfg, &mut buffer).unwrap();
+    let expected = buffer.split(|&c| c == b'\n').map(|line| line.len()).max().unwrap();
+    sexp.max_line_width_hum(wrap_width) == expected
+}
+
+#[quickcheck]
+fn byte_chunks(sexp: QSexp, chunk_size: u8) -> bool {
+    let sexp = sexp.0;
+    let chunk_size = 1 + chunk_size as usize;
+    let chunks: Vec<Vec<u8>> = sexp.byte_chunks_with_size(chunk_size).collect();
+    let (last, chunks_but_last) = chunks.split_last().unwrap();
+    !last.is_empty()
+        && last.len() <= chunk_size
+        && chunks_but_last.iter().all(|c| c.len() == chunk_size)
+        && chunks.concat() == sexp.to_bytes()
+}
+
+#[test]
+fn byte_chunks_large() {
+    let row = rsexp::list(&[rsexp::atom(b"foo"), rsexp::atom(b"needs escaping\n")]);
+    let sexp = rsexp::list(&vec![row; 10000]);
+    let chunks: Vec<Vec<u8>> = sexp.byte_chunks().collect();
+    assert!(chunks.len() > 1);
+    assert_eq!(chunks.concat(), sexp.to_bytes());
+    assert_eq!(rsexp::atom(b"").byte_chunks().collect::<Vec<_>>(), vec![b"\"\"".to_vec()]);
+}
+
+fn quoting_numbers(s: &str) -> String {
+    let sexp = from_slice(s).unwrap();
+    let mut buffer = Vec::new();
+    sexp.write_quoting_numbers(&mut buffer).unwrap();
+    assert_eq!(from_slice(&buffer).unwrap(), sexp);
+    String::from_utf8_lossy(&buffer).to_string()
+}
+
+#[test]
+fn write_quoting_numbers() {
+    assert_eq!(quoting_numbers("42"), "\"42\"");
+    assert_eq!(quoting_numbers("3.14"), "\"3.14\"");
+    assert_eq!(quoting_numbers("foo"), "foo");
+    assert_eq!(
+        quoting_numbers("(foo 42 (3.14 \"bar baz\"))"),
+        "(foo \"42\" (\"3.14\" \"bar baz\"))"
+    );
+    for number in ["-42", "+1", "1.", ".5", "1e10", "-2.5E-3", "0x1F", "0o17", "-0b101"] {
+        assert_eq!(quoting_numbers(number), format!("\"{number}\""));
+    }
+    for not_number in
+        ["-", ".", "1e", "e10", "1.2.3", "0x", "0xG", "0b2", "12a", "--1", "inf", "nan"]
+    {
+        assert_eq!(quoting_numbers(not_number), not_number);
+    }
+}
+
+#[test]
+fn join_atoms() {
+    let join = |s: &str, sep: &[u8]| from_slice(s).unwrap().join_atoms(sep);
+    assert_eq!(join("(hello world)", b" "), Some(b"hello world".to_vec()));
+    assert_eq!(join("(a \"b c\" \"\" d)", b", "), Some(b"a, b c, , d".to_vec()));
+    assert_eq!(join("(hello)", b" "), Some(b"hello".to_vec()));
+    assert_eq!(join("()", b" "), Some(vec![]));
+    assert_eq!(join("(a b)", b""), Some(b"ab".to_vec()));
+    assert_eq!(join("(hello (world))", b" "), None);
+    assert_eq!(join("hello", b" "), None);
+}
+
+#[test]
+fn ordering() {
+    let sexp = |s: &str| from_slice(s).unwrap();
+    assert!(sexp("a") < sexp("b"));
+    assert!(sexp("a") < sexp("ab"));
+    assert!(sexp("\"\"") < sexp("a"));
+    assert!(sexp("B") < sexp("a"));
+    assert!(sexp("()") < sexp("(a)"));
+    assert!(sexp("(a)") < sexp("(a b)"));
+    assert!(sexp("(a b)") < sexp("(b)"));
+    assert!(sexp("(a (b))") > sexp("(a b c)"));
+    assert!(sexp("zzz") < sexp("()"));
+    assert!(sexp("(a b)") < sexp("((a))"));
+    assert_eq!(sexp("(a (b c))").cmp(&sexp("(a (b c))")), std::cmp::Ordering::Equal);
+    let mut sexps: Vec<Sexp> = ["(b)", "a", "()", "(a b)", "b", "(a)"].map(sexp).to_vec();
+    sexps.sort();
+    assert_eq!(sexps, ["a", "b", "()", "(a)", "(a b)", "(b)"].map(sexp));
+    let map: std::collections::BTreeMap<Sexp, i32> = [(sexp("(x)"), 1), (sexp("x"), 2)].into();
+    assert_eq!(map.keys().collect::<Vec<_>>(), [&sexp("x"), &sexp("(x)")]);
+}
+
+#[test]
+fn sort_children_by() {
+    let age = |s: &Sexp| match s {
+        Sexp::List(l) => match l.as_slice() {
+            [_name, Sexp::Atom(age)] => std::str::from_utf8(age).unwrap().parse::<u32>().unwrap(),
+            _ => panic!("unexpected sexp {s}"),
+        },
+        Sexp::Atom(_) => panic!("unexpected atom {s}"),
+    };
+    let mut sexp = from_slice("((alice 42) (bob 7) (carol 100) (dave 7))").unwrap();
+    sexp.sort_children_by(|s1, s2| age(s1).cmp(&age(s2)));
+    assert_eq!(sexp.to_string(), "((bob 7) (dave 7) (alice 42) (carol 100))");
+    sexp.sort_children_by(|s1, s2| age(s2).cmp(&age(s1)));
+    assert_eq!(sexp.to_string(), "((carol 100) (alice 42) (bob 7) (dave 7))");
+    let mut sexp = from_slice("atom").unwrap();
+    sexp.sort_children_by(|_, _| panic!("no children to compare"));
+    assert_eq!(sexp, rsexp::atom(b"atom"));
+}
+
+#[test]
+fn float_spelling() {
+    use rsexp::{float_atom, FloatSpelling, InfSpelling, NanSpelling};
+    for nan in [NanSpelling::NaN, NanSpelling::Lowercase] {
+        for inf in [InfSpelling::Inf, InfSpelling::Infinity, InfSpelling::PlusInf] {
+            let spelling = FloatSpelling { nan, inf };
+            for value in [f64::INFINITY, f64::NEG_INFINITY, 0., -1.5, 2.25, 1e300] {
+                let sexp = float_atom(value, spelling);
+                assert_eq!(sexp.of_sexp::<f64>(), Ok(value));
+                assert_eq!(from_slice(&sexp.to_bytes()).unwrap(), sexp);
+            }
+            assert!(float_atom(f64::NAN, spelling).of_sexp::<f64>().unwrap().is_nan());
+            assert!(float_atom(f64::NAN, spelling).of_sexp::<f32>().unwrap().is_nan());
+            assert_eq!(
+                float_atom(f64::NEG_INFINITY, spelling).of_sexp::<f32>(),
+                Ok(f32::NEG_INFINITY)
+            );
+        }
+    }
+    let spelled = |value: f64, nan, inf| float_atom(value, FloatSpelling { nan, inf }).to_string();
+    assert_eq!(spelled(f64::NAN, NanSpelling::NaN, InfSpelling::Inf), "NaN");
+    assert_eq!(spelled(f64::NAN, NanSpelling::Lowercase, InfSpelling::Inf), "nan");
+    assert_eq!(spelled(f64::INFINITY, NanSpelling::NaN, InfSpelling::Inf), "inf");
+    assert_eq!(spelled(f64::INFINITY, NanSpelling::NaN, InfSpelling::Infinity), "Infinity");
+    assert_eq!(spelled(f64::INFINITY, NanSpelling::NaN, InfSpelling::PlusInf), "+inf");
+    assert_eq!(spelled(f64::NEG_INFINITY, NanSpelling::NaN, InfSpelling::Inf), "-inf");
+    assert_eq!(spelled(f64::NEG_INFINITY, NanSpelling::NaN, InfSpelling::Infinity), "-Infinity");
+    assert_eq!(spelled(f64::NEG_INFINITY, NanSpelling::NaN, InfSpelling::PlusInf), "-inf");
+    // The default spelling matches the one used by SexpOf.
+    for value in [f64::NAN, f64::INFINITY, f64::NEG_INFINITY, 2.5] {
+        assert_eq!(float_atom(value, FloatSpelling::default()), value.sexp_of());
+    }
+    for s in ["NaN", "nan", "NAN", "inf", "+inf", "-inf", "Infinity", "-Infinity", "infinity"] {
+        assert!(rsexp::atom(s.as_bytes()).of_sexp::<f64>().is_ok(), "{s}");
+    }
+}
+
+// The float formatting uses the shortest representation that parses back to
+// the same value, so the round-trip preserves the bits of all non-NaN values.
+#[quickcheck]
+fn round_trip_float(x: f64, bits: u64, bits32: u32) -> bool {
+    let rt64 = |x: f64| {
+        let y: f64 = from_slice(&x.sexp_of().to_bytes()).unwrap().of_sexp().unwrap();
+        if x.is_nan() {
+            y.is_nan()
+        } else {
+            x.to_bits() == y.to_bits()
+        }
+    };
+    let x32 = f32::from_bits(bits32);
+    let y32: f32 = x32.sexp_of().of_sexp().unwrap();
+    rt64(x) && rt64(f64::from_bits(bits)) && (x32.is_nan() && y32.is_nan() || x32 == y32)
+}
+
+#[test]
+fn float_bits() {
+    for x in [0., -0., f64::MIN_POSITIVE, 5e-324, f64::MAX, f64::MIN, f64::EPSILON, 0.1 + 0.2] {
+        let y: f64 = x.sexp_of().of_sexp().unwrap();
+        assert_eq!(x.to_bits(), y.to_bits(), "{x}");
+    }
+    assert_eq!((-0f64).sexp_of().to_string(), "-0");
+    assert_eq!((0.1 + 0.2f64).sexp_of().to_string(), "0.30000000000000004");
+    assert_eq!(5e-324f64.sexp_of().of_sexp::<f64>(), Ok(5e-324));
+}
+
+#[test]
+fn accessors() {
+    let sexp = from_slice("(foo (bar \"b a z\") () \"\\xff\")").unwrap();
+    assert_eq!(sexp.as_atom(), None);
+    assert_eq!(sexp.as_list().map(|l| l.len()), Some(4));
+    assert_eq!(sexp.get(0), Some(&rsexp::atom(b"foo")));
+    assert_eq!(sexp.get(0).and_then(Sexp::as_atom), Some(b"foo".as_slice()));
+    assert_eq!(sexp.get(0).and_then(Sexp::as_list), None);
+    assert_eq!(sexp.get(1).and_then(|s| s.get(1)).and_then(Sexp::atom_str), Some("b a z"));
+    assert_eq!(sexp.get(1).and_then(|s| s.get(2)), None);
+    assert_eq!(sexp.get(2).and_then(Sexp::as_list), Some([].as_slice()));
+    assert_eq!(sexp.get(2).and_then(|s| s.get(0)), None);
+    assert_eq!(sexp.get(3).and_then(Sexp::as_atom), Some(b"\xff".as_slice()));
+    assert_eq!(sexp.get(3).and_then(Sexp::atom_str), None);
+    assert_eq!(sexp.get(4), None);
+    assert_eq!(sexp.atom_str(), None);
+    assert_eq!(rsexp::atom(b"foo").get(0), None);
+    assert_eq!(rsexp::atom(b"").atom_str(), Some(""));
+}
+
+#[test]
+fn get_path() {
+    let sexp = from_slice("((a (b (c 42))) d)").unwrap();
+    assert_eq!(sexp.get_path(&[]), Some(&sexp));
+    assert_eq!(sexp.get_path(&[0, 1, 1, 1]), Some(&rsexp::atom(b"42")));
+    assert_eq!(sexp.get_path(&[0, 1, 1]).map(|s| s.to_string()), Some("(c 42)".to_string()));
+    assert_eq!(sexp.get_path(&[1]), Some(&rsexp::atom(b"d")));
+    // Out of bounds indexes.
+    assert_eq!(sexp.get_path(&[2]), None);
+    assert_eq!(sexp.get_path(&[0, 1, 1, 2]), None);
+    // Indexing an atom.
+    assert_eq!(sexp.get_path(&[1, 0]), None);
+    assert_eq!(sexp.get_path(&[0, 0, 0, 0]), None);
+    assert_eq!(rsexp::atom(b"foo").get_path(&[0]), None);
+}
+
+#[test]
+fn predicates() {
+    let sexp = from_slice("(foo (bar \"b a z\") () \"\")").unwrap();
+    assert!(sexp.is_list() && !sexp.is_atom());
+    assert_eq!((sexp.len(), sexp.is_empty()), (4, false));
+    let lens: Vec<_> = sexp.as_list().unwrap().iter().map(|s| (s.is_atom(), s.len())).collect();
+    assert_eq!(lens, [(true, 3), (false, 2), (false, 0), (true, 0)]);
+    // Both the empty atom and the empty list are empty.
+    assert!(sexp.get(2).unwrap().is_empty());
+    assert!(sexp.get(3).unwrap().is_empty());
+    assert!(!sexp.get(0).unwrap().is_empty());
+    assert_eq!(rsexp::atom("été".as_bytes()).len(), 5);
+}
+
+#[test]
+fn iter() {
+    let sexp = from_slice("(foo (bar baz) ())").unwrap();
+    let mut children = vec![];
+    for child in &sexp {
+        children.push(child.to_string())
+    }
+    assert_eq!(children, ["foo", "(bar baz)", "()"]);
+    assert_eq!(sexp.iter().next_back(), Some(&rsexp::list(&[])));
+    assert_eq!(
+        sexp.get(1).unwrap().iter().collect::<Vec<_>>(),
+        [&rsexp::atom(b"bar"), &rsexp::atom(b"baz")]
+    );
+    // Atoms and empty lists have no children.
+    assert_eq!(rsexp::atom(b"foo").iter().next(), None);
+    assert_eq!((&rsexp::atom(b"")).into_iter().count(), 0);
+    assert_eq!(sexp.get(2).unwrap().iter().count(), 0);
+}
+
+#[test]
+fn require() {
+    let sexp =
+        from_slice("((name foo) (size 42) (nested (a b)) ignored (x y z) (size 43))").unwrap();
+    assert_eq!(sexp.require(b"name", "config"), Ok(&rsexp::atom(b"foo")));
+    assert_eq!(sexp.require(b"size", "config"), Ok(&rsexp::atom(b"42")));
+    assert_eq!(sexp.require(b"nested", "config").unwrap().to_string(), "(a b)");
+    let missing =
+        |key: &str| IntoSexpError::MissingKeyInList { type_: "config", key: key.to_string() };
+    assert_eq!(sexp.require(b"color", "config"), Err(missing("color")));
+    assert_eq!(sexp.require(b"ignored", "config"), Err(missing("ignored")));
+    assert_eq!(sexp.require(b"x", "config"), Err(missing("x")));
+    assert_eq!(
+        from_slice("foo").unwrap().require(b"foo", "config"),
+        Err(IntoSexpError::ExpectedListGotAtom { type_: "config" })
+    );
+}
+
+#[test]
+fn alist_fields() {
+    let sexp =
+        from_slice("((name foo) (dep a) ignored (dep) (dep b c) ((dep) x) (dep (d e)))").unwrap();
+    assert_eq!(sexp.field(b"name"), Some(&rsexp::atom(b"foo")));
+    assert_eq!(sexp.fields(b"name").count(), 1);
+    assert_eq!(sexp.field(b"dep"), Some(&rsexp::atom(b"a")));
+    let deps: Vec<_> = sexp.fields(b"dep").map(|s| s.to_string()).collect();
+    assert_eq!(deps, ["a", "(d e)"]);
+    assert_eq!(sexp.field(b"color"), None);
+    assert_eq!(sexp.fields(b"color").count(), 0);
+    assert_eq!(sexp.field(b"ignored"), None);
+    assert_eq!(rsexp::atom(b"name").field(b"name"), None);
+    assert_eq!(rsexp::atom(b"name").fields(b"name").count(), 0);
+}
+
+#[test]
+fn is_record() {
+    let is_record = |s: &str| from_slice(s).unwrap().is_record();
+    assert!(is_record("((name foo) (size 42) (nested (a (b c))) (name bar))"));
+    assert!(is_record("()"));
+    assert!(!is_record("((name foo) size)"));
+    assert!(!is_record("((name foo) (size))"));
+    assert!(!is_record("((name foo) (size 4 2))"));
+    assert!(!is_record("(((name) foo))"));
+    assert!(!is_record("(a b)"));
+    assert!(!is_record("foo"));
+    assert!(!is_record("\"\""));
+}
+
+// A downstream type implementing Display and FromStr can provide its own
+// conversions without conflicting with the blanket UseToString ones.
+#[derive(Debug, PartialEq)]
+struct Version {
+    major: u32,
+    minor: u32,
+}
+
+impl std::fmt::Display for Version {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{}.{}", self.major, self.minor)
+    }
+}
+
+impl std::str::FromStr for Version {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (major, minor) = s.split_once('.').ok_or_else(|| format!("no dot in {s}"))?;
+        let parse = |v: &str| v.parse().map_err(|_| format!("invalid number {v}"));
+        Ok(Version { major: parse(major)?, minor: parse(minor)? })
+    }
+}
+
+impl SexpOf for Version {
+    fn sexp_of(&self) -> Sexp {
+        rsexp::list(&[self.major.sexp_of(), self.minor.sexp_of()])
+    }
+}
+
+impl rsexp::OfSexp for Version {
+    fn of_sexp(s: &Sexp) -> Result<Self, IntoSexpError> {
+        let (major, minor) = s.of_sexp()?;
+        Ok(Version { major, minor })
+    }
+}
+
+#[test]
+fn custom_scalar() {
+    let version = Version { major: 1, minor: 12 };
+    assert_eq!(version.to_string().parse(), Ok(Version { major: 1, minor: 12 }));
+    let sexp = version.sexp_of();
+    assert_eq!(sexp.to_string(), "(1 12)");
+    assert_eq!(sexp.of_sexp(), Ok(version));
+    // The type composes with the built-in implementations.
+    let versions = vec![Some(Version { major: 0, minor: 1 }), None];
+    let sexp = (versions.sexp_of(), "name".to_string()).sexp_of();
+    assert_eq!(sexp.to_string(), "((((0 1)) ()) name)");
+    let (versions2, name): (Vec<Option<Version>>, String) = sexp.of_sexp().unwrap();
+    assert_eq!((versions2, name.as_str()), (versions, "name"));
+}
+
+#[test]
+fn extract_scalars() {
+    let conversion_error =
+        |err: &str| IntoSexpError::StringConversionError { err: err.to_string() };
+    let sexp = from_slice("(foo -12 2.5e3 true \"\\xff\" ())").unwrap();
+    let elems = sexp.as_list().unwrap();
+    assert_eq!(elems[0].extract_atom_str("name"), Ok("foo"));
+    assert_eq!(elems[1].extract_i64(), Ok(-12));
+    assert_eq!(elems[2].extract_f64(), Ok(2500.));
+    assert_eq!(elems[1].extract_f64(), Ok(-12.));
+    assert_eq!(elems[3].extract_bool(), Ok(true));
+    assert_eq!(rsexp::atom(b"false").extract_bool(), Ok(false));
+    // Parsing failures.
+    assert_eq!(elems[0].extract_i64(), Err(conversion_error("invalid digit found in string")));
+    assert_eq!(elems[2].extract_i64(), Err(conversion_error("invalid digit found in string")));
+    assert_eq!(
+        rsexp::atom(b"99999999999999999999").extract_i64(),
+        Err(conversion_error("number too large to fit in target type"))
+    );
+    assert_eq!(elems[0].extract_f64(), Err(conversion_error("invalid float literal")));
+    assert_eq!(
+        elems[0].extract_bool(),
+        Err(conversion_error("provided string was not `true` or `false`"))
+    );
+    // Invalid UTF-8.
+    assert!(matches!(elems[4].extract_atom_str("name"), Err(IntoSexpError::Utf8Error(_))));
+    assert!(matches!(elems[4].extract_i64(), Err(IntoSexpError::Utf8Error(_))));
+    // Lists.
+    let got_list = |type_| IntoSexpError::ExpectedAtomGotList { type_, list_len: 0 };
+    assert_eq!(elems[5].extract_atom_str("name"), Err(got_list("name")));
+    assert_eq!(elems[5].extract_i64(), Err(got_list("i64")));
+    assert_eq!(elems[5].extract_f64(), Err(got_list("f64")));
+    assert_eq!(elems[5].extract_bool(), Err(got_list("bool")));
+}
+
+#[test]
+fn tuple_element_errors() {
+    let of_str = |s: &str| from_slice(s).unwrap().of_sexp::<(i32, String, (u8, bool))>();
+    assert_eq!(of_str("(1 foo (2 true))"), Ok((1, "foo".to_string(), (2, true))));
+    let err = of_str("(1 (foo) (2 true))").unwrap_err();
+    assert_eq!(
+        err,
+        IntoSexpError::ExpectedAtomGotList { type_: "String", list_len: 1 }.with_index(1)
+    );
+    assert_eq!(
+        err.to_string(),
+        "tuple element 1: ExpectedAtomGotList { type_: \"String\", list_len: 1 }"
+    );
+    let err = of_str("(1 foo (2 yes))").unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "tuple element 2: tuple element 1: StringConversionError { err: \"provided string was not `true` or `false`\" }"
+    );
+    let err = of_str("(1 foo)").unwrap_err();
+    assert!(matches!(err, IntoSexpError::ListLengthMismatch { expected_len: 3, list_len: 2, .. }));
+}
+
+#[test]
+fn large_tuples() {
+    type T12 = (u8, i16, u32, i64, usize, bool, String, f64, Option<u8>, (), (u8, u8), u16);
+    let t: T12 = (1, -2, 3, -4, 5, true, "six".to_string(), 7.5, Some(8), (), (9, 10), 11);
+    let sexp = t.sexp_of();
+    assert_eq!(sexp.to_string(), "(1 -2 3 -4 5 true six 7.5 (8) () (9 10) 11)");
+    assert_eq!(sexp.of_sexp::<T12>(), Ok(t));
+    let err = from_slice("(1 -2 3 -4 5 true six 7.5 (8) () (9 10))").unwrap().of_sexp::<T12>();
+    assert!(matches!(
+        err,
+        Err(IntoSexpError::ListLengthMismatch { expected_len: 12, list_len: 11, .. })
+    ));
+    let err = from_slice("(1 -2 3 -4 5 true six 7.5 (8) () (9 x) 11)").unwrap().of_sexp::<T12>();
+    assert_eq!(err.unwrap_err().to_string(), "tuple element 10: tuple element 1: StringConversionError { err: \"invalid digit found in string\" }");
+    type T16 = (u8, u8, u8, u8, u8, u8, u8, u8, u8, u8, u8, u8, u8, u8, u8, u8);
+    let t16: T16 = (0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15);
+    let sexp = t16.sexp_of();
+    assert_eq!(sexp.to_string(), "(0 1 2 3 4 5 6 7 8 9 10 11 12 13 14 15)");
+    // Tuples of more than 12 elements do not implement PartialEq.
+    assert_eq!(sexp.of_sexp::<T16>().unwrap().sexp_of(), sexp);
+}
+
+#[quickcheck]
+fn intern_round_trip(sexp: QSexp) -> bool {
+    sexp.0.intern().to_sexp() == sexp.0
+}
+
+#[test]
+fn intern() {
+    use rsexp::InternedSexp;
+    fn atom_bytes(s: &Sexp) -> usize {
+        match s {
+            Sexp::Atom(atom) => atom.capacity(),
+            Sexp::List(list) => list.iter().map(atom_bytes).sum(),
+        }
+    }
+    // Counts each shared buffer only once.
+    fn interned_atom_bytes(
+        s: &InternedSexp,
+        seen: &mut std::collections::HashSet<*const u8>,
+    ) -> usize {
+        match s {
+            InternedSexp::Atom(atom) if seen.insert(atom.as_ptr()) => atom.len(),
+            InternedSexp::Atom(_) => 0,
+            InternedSexp::List(list) => list.iter().map(|s| interned_atom_bytes(s, seen)).sum(),
+        }
+    }
+    let records: String = (0..1000)
+        .map(|i| format!("((hostname server-{}) (status running) (region us-east-1))", i % 10))
+        .collect();
+    let sexp = from_slice(&format!("({records})")).unwrap();
+    let interned = sexp.intern();
+    assert_eq!(interned.to_sexp(), sexp);
+    let bytes = atom_bytes(&sexp);
+    let interned_bytes = interned_atom_bytes(&interned, &mut Default::default());
+    assert!(bytes >= 1000 * 44, "{bytes}");
+    // hostname status region running us-east-1 and the 10 server names.
+    assert_eq!(interned_bytes, 8 + 6 + 6 + 7 + 9 + 10 * 8);
+    let (first, second) = match &interned {
+        InternedSexp::List(list) => (&list[0], &list[10]),
+        InternedSexp::Atom(_) => panic!("expected a list"),
+    };
+    assert_eq!(first, second);
+}
+
+#[test]
+fn check_no_duplicate_keys() {
+    let check = |s: &str| from_slice(s).unwrap().check_no_duplicate_keys();
+    let dup = |key: &str, path: &str| {
+        Err(IntoSexpError::DuplicateKeyAtPath { key: key.to_string(), path: path.to_string() })
+    };
+    assert_eq!(check("((a 1) (b ((a 2) (c 3))))"), Ok(()));
+    assert_eq!(check("(a a b)"), Ok(()));
+    assert_eq!(check("((a 1) (a 2))"), dup("a", ""));
+    assert_eq!(check("((server ((tls ((cert a) (key b) (cert c))))))"), dup("cert", "server/tls"));
+    assert_eq!(check("((hosts (((name a)) ((name b) (name c)))))"), dup("name", "hosts/1"));
+    // Lists that are not records can contain duplicate pairs.
+    assert_eq!(check("((a 1) (a 2) b)"), Ok(()));
+}
+
+#[test]
+fn serialized_len_escaping() {
+    for s in
+        ["a", "\"\"", "()", "(())", "(a b c)", "(\"a\\\\b\\n\\t\\r\\b\\\"\" (\"\\255\\000|#\"))"]
+    {
+        let sexp = from_slice(s).unwrap();
+        assert_eq!(sexp.serialized_len(), sexp.to_bytes().len(), "{s}");
+        assert_eq!(sexp.serialized_len(), s.len(), "{s}");
+    }
+}
+
+#[test]
+fn comments() {
+    let input = r#";; Server configuration.
+;; Edited by hand.
+((server
+  ;; The display name.
+  (name "web server")
+  ;; Ports
+  ;; in use.
+  (ports (80 443))
+  ;; (tls true)
+  )
+ (debug false)
+ ;; Retries
+ (retries
+  (; none yet
+   )))"#;
+    let sexp = rsexp::from_slice_with_comments(input).unwrap();
+    assert_eq!(sexp.to_sexp(), from_slice(input).unwrap());
+    assert_eq!(
+        sexp.comments,
+        [b";; Server configuration.".to_vec(), b";; Edited by hand.".to_vec()]
+    );
+    assert_eq!(String::from_utf8(sexp.to_bytes_hum_with_comments()).unwrap(), input);
+    // The layout is normalized, comments on the same line as a value are
+    // attached to the following value.
+    let input = "(a ; first\n  b (c d) ; last\n) ; dropped\n";
+    let sexp = rsexp::from_slice_with_comments(input).unwrap();
+    let expected = "(a\n ; first\n b\n (c d)\n ; last\n )";
+    assert_eq!(String::from_utf8(sexp.to_bytes_hum_with_comments()).unwrap(), expected);
+    let sexp2 = rsexp::from_slice_with_comments(expected).unwrap();
+    assert_eq!(sexp2, sexp);
+    // Without comments, lists are written on a single line.
+    let sexp = rsexp::CommentedSexp::from(from_slice("(a (b \"c d\"))").unwrap());
+    assert_eq!(sexp.to_bytes_hum_with_comments(), b"(a (b \"c d\"))");
+    let sexp = rsexp::from_slice_with_comments("; atom\nfoo").unwrap();
+    assert_eq!(sexp.to_bytes_hum_with_comments(), b"; atom\nfoo");
+    assert_eq!(
+        rsexp::from_slice_with_comments("(a ; b\n"),
+        Err(rsexp::Error::UnexpectedEof { offset: 7 })
+    );
+    assert_eq!(
+        rsexp::from_slice_with_comments("a b"),
+        Err(rsexp::Error::UnexpectedEof { offset: 2 })
+    );
+}
+
+#[test]
+fn write_with_atom_fmt() {
+    use std::io::Write;
+    let sexp = from_slice("(foo (bar \"b a z\") () \"\")").unwrap();
+    let mut buffer = Vec::new();
+    let fmt = |atom: &[u8], w: &mut Vec<u8>| {
+        w.push(b'<');
+        w.extend_from_slice(atom);
+        w.write_all(b">")
+    };
+    sexp.write_with_atom_fmt(fmt, &mut buffer).unwrap();
+    assert_eq!(String::from_utf8(buffer).unwrap(), "(<foo> (<bar> <b a z>) () <>)");
+    let mut buffer = Vec::new();
+    let mut atoms = 0;
+    sexp.write_with_atom_fmt(
+        |atom, w| {
+            atoms += 1;
+            write!(w, "{}", atom.len())
+        },
+        &mut buffer,
+    )
+    .unwrap();
+    assert_eq!((buffer.as_slice(), atoms), (&b"(3 (3 5) () 0)"[..], 4));
+    let mut buffer = Vec::new();
+    rsexp::atom(b"a b").write_with_atom_fmt(fmt, &mut buffer).unwrap();
+    assert_eq!(buffer, b"<a b>");
+}
+
+#[test]
+fn write_deep() {
+    let depth = 1_000_000;
+    let mut deep = Sexp::List(vec![rsexp::atom(b"a b"), rsexp::atom(b"c")]);
+    for _ in 0..depth {
+        deep = Sexp::List(vec![rsexp::atom(b"x"), deep, rsexp::list(&[])]);
+    }
+    let bytes = deep.to_bytes();
+    let mut expected = "(x ".repeat(depth).into_bytes();
+    expected.extend_from_slice(b"(\"a b\" c)");
+    expected.extend_from_slice(" ())".repeat(depth).as_bytes());
+    assert_eq!(bytes, expected);
+    // Dropping is recursive so the nested lists are dismantled beforehand.
+    while let Sexp::List(mut list) = deep {
+        deep = list.swap_remove(1)
+    }
+}
+
+#[test]
+fn parse_deep() {
+    let depth = 500_000;
+    let input = format!("{}a ; comment\n{}", "( #| x |# b ".repeat(depth), ")".repeat(depth));
+    let mut sexp = rsexp::from_slice_with_depth_limit(&input, usize::MAX).unwrap();
+    let expected = format!("{}a{}", "(b ".repeat(depth), ")".repeat(depth));
+    assert_eq!(sexp.to_bytes(), expected.as_bytes());
+    assert_eq!(
+        rsexp::from_slice_with_depth_limit(&input[..input.len() - depth], usize::MAX),
+        Err(rsexp::Error::UnexpectedEof { offset: input.len() - depth })
+    );
+    // Dropping is recursive so the nested lists are dismantled beforehand.
+    while let Sexp::List(mut list) = sexp {
+        sexp = list.pop().unwrap()
+    }
+}
+
+#[test]
+fn parse_deep_datum_comments() {
+    struct AtomCount(usize);
+    impl rsexp::SexpVisitor for AtomCount {
+        fn atom(&mut self, _bytes: &[u8]) {
+            self.0 += 1
+        }
+        fn begin_list(&mut self) {}
+        fn end_list(&mut self) {}
+    }
+    let depth = 500_000;
+    let opts = rsexp::ParseOptions { max_depth: usize::MAX, ..Default::default() };
+    // Each list discards the following nested one.
+    let nested = format!("{}a{}", "(#;".repeat(depth), ")".repeat(depth));
+    // A single datum comment discarding a deeply nested list.
+    let single = format!("#;{}a{} b", "(".repeat(depth), ")".repeat(depth));
+    let chained = format!("{}{}b", "#;".repeat(depth), "a ".repeat(depth));
+    for (input, expected, atoms) in [
+        (nested, rsexp::list(&[]), 0),
+        (single, rsexp::atom(b"b"), 1),
+        (chained, rsexp::atom(b"b"), 1),
+    ] {
+        assert_eq!(rsexp::from_slice_with_options(&input, &opts), Ok(expected.clone()));
+        assert_eq!(rsexp::from_slice_multi_with_options(&input, &opts), Ok(vec![expected]));
+        let mut count = AtomCount(0);
+        rsexp::parse_visit(input.as_bytes(), &mut count).unwrap();
+        assert_eq!(count.0, atoms);
+    }
+}
+
+#[test]
+fn default_depth_limit() {
+    let depth = rsexp::DEFAULT_MAX_DEPTH;
+    let input = format!("{}a{}", "(b ".repeat(depth), ")".repeat(depth));
+    assert_eq!(from_slice(&input).unwrap().to_bytes(), input.as_bytes());
+    assert!(rsexp::from_slice_ref(&input).is_ok());
+    // Deeper inputs are rejected rather than overflowing the stack when the
+    // result is dropped.
+    let deeper = format!("({input})");
+    let err = rsexp::Error::MaxDepthExceeded { depth, offset: 3 * depth - 2 };
+    assert_eq!(from_slice(&deeper), Err(err.clone()));
+    assert_eq!(rsexp::from_slice_ref(&deeper), Err(err));
+}
+
+#[test]
+fn map_atoms_and_fold() {
+    let sexp = from_slice("((name foo) (tags (a \"b c\")) () x)").unwrap();
+    let upper = sexp.map_atoms(|a| a.to_ascii_uppercase());
+    assert_eq!(upper.to_string(), "((NAME FOO) (TAGS (A \"B C\")) () X)");
+    assert_eq!(rsexp::atom(b"foo").map_atoms(|a| a.repeat(2)), rsexp::atom(b"foofoo"));
+    let mut seen = vec![];
+    sexp.map_atoms(|a| {
+        seen.push(String::from_utf8_lossy(a).to_string());
+        vec![]
+    });
+    assert_eq!(seen, ["name", "foo", "tags", "a", "b c", "x"]);
+    let (lists, atoms) = sexp.fold((0, 0), |(lists, atoms), s| match s {
+        Sexp::Atom(_) => (lists, atoms + 1),
+        Sexp::List(_) => (lists + 1, atoms),
+    });
+    assert_eq!((lists, atoms), (5, 6));
+    let order = sexp.fold(String::new(), |acc, s| match s.atom_str() {
+        Some(a) => acc + a + ",",
+        None => acc + "(",
+    });
+    assert_eq!(order, "((name,foo,(tags,(a,b c,(x,");
+    // Deeply nested inputs do not overflow the stack.
+    let mut deep = rsexp::atom(b"a");
+    for _ in 0..1_000_000 {
+        deep = Sexp::List(vec![deep]);
+    }
+    assert_eq!(deep.fold(0, |acc, _| acc + 1), 1_000_001);
+    let mut mapped = deep.map_atoms(|_| b"b".to_vec());
+    assert_eq!(
+        mapped.fold(vec![], |acc, s| [acc, s.as_atom().unwrap_or_default().to_vec()].concat()),
+        b"b"
+    );
+    // Dropping is recursive so the nested lists are dismantled beforehand.
+    for sexp in [&mut deep, &mut mapped] {
+        while let Sexp::List(mut list) = std::mem::replace(sexp, rsexp::atom(b"")) {
+            *sexp = list.pop().unwrap()
+        }
+    }
+}
+
+#[test]
+fn table() {
+    let sexp = from_slice("((name price qty) (apple 1.5 3) (\"dried fig\" 4 (12 24)))").unwrap();
+    let (headers, rows) = sexp.to_table().unwrap();
+    assert_eq!(headers, [b"name".to_vec(), b"price".to_vec(), b"qty".to_vec()]);
+    assert_eq!(rows.len(), 2);
+    assert_eq!(rows[0][1].of_sexp::<f64>(), Ok(1.5));
+    assert_eq!(rows[1][0], rsexp::atom(b"dried fig"));
+    assert_eq!(rows[1][2].to_string(), "(12 24)");
+    assert_eq!(Sexp::of_table(headers, rows), sexp);
+    assert_eq!(
+        from_slice("((a b))").unwrap().to_table(),
+        Ok((vec![b"a".to_vec(), b"b".to_vec()], vec![]))
+    );
+    assert_eq!(from_slice("()").unwrap().to_table(), Ok((vec![], vec![])));
+    assert_eq!(
+        from_slice("((a b) (1 2) (3) (4 5))").unwrap().to_table(),
+        Err(IntoSexpError::RowWidthMismatch { row: 1, expected_len: 2, row_len: 1 })
+    );
+    assert_eq!(
+        from_slice("((a (b)) (1 2))").unwrap().to_table(),
+        Err(IntoSexpError::ExpectedAtomGotList { type_: "table", list_len: 1 })
+    );
+    assert_eq!(
+        from_slice("((a b) 1)").unwrap().to_table(),
+        Err(IntoSexpError::ExpectedListGotAtom { type_: "table" })
+    );
+}
+
+#[test]
+fn to_bytes_table() {
+    let sexp = from_slice("((alice 42) (bob 7) (\"charlie brown\" 1234))").unwrap();
+    let table = sexp.to_bytes_table().unwrap();
+    assert_eq!(
+        String::from_utf8(table.clone()).unwrap(),
+        "((alice           42)\n (bob             7)\n (\"charlie brown\" 1234))"
+    );
+    assert_eq!(from_slice(&table), Ok(sexp));
+    let to_table =
+        |s| from_slice(s).unwrap().to_bytes_table().map(|t| String::from_utf8(t).unwrap());
+    assert_eq!(
+        to_table("((a (b c) d) (ee f (g)))").as_deref(),
+        Some("((a  (b c) d)\n (ee f     (g)))")
+    );
+    assert_eq!(to_table("(() ())").as_deref(), Some("(()\n ())"));
+    assert_eq!(to_table("()").as_deref(), Some("()"));
+    assert_eq!(to_table("((a b) (c))"), None);
+    assert_eq!(to_table("((a b) c)"), None);
+    assert_eq!(to_table("a"), None);
+}
+
+#[test]
+fn sexp_macro() {
+    use rsexp::{atom, list, sexp};
+    assert_eq!(Sexp::from(42i64), atom(b"42"));
+    assert_eq!(Sexp::from(-1.5), atom(b"-1.5"));
+    assert_eq!(Sexp::from(true), atom(b"true"));
+    assert_eq!(Sexp::from(vec![atom(b"a"), atom(b"b")]), list(&[atom(b"a"), atom(b"b")]));
+
+    assert_eq!(sexp!(foo), atom(b"foo"));
+    assert_eq!(sexp!("foo bar"), atom(b"foo bar"));
+    assert_eq!(sexp!(42), atom(b"42"));
+    assert_eq!(sexp!(-42), atom(b"-42"));
+    assert_eq!(sexp!(()), list(&[]));
+    let x = 1.5;
+    let inner = list(&[atom(b"c")]);
+    assert_eq!(
+        sexp!((a (b -2 "c d") {x} {inner} (nested (deeper (deepest false))))),
+        list(&[
+            atom(b"a"),
+            list(&[atom(b"b"), atom(b"-2"), atom(b"c d")]),
+            atom(b"1.5"),
+            list(&[atom(b"c")]),
+            list(&[
+                atom(b"nested"),
+                list(&[atom(b"deeper"), list(&[atom(b"deepest"), atom(b"false")])])
+            ]),
+        ])
+    );
+    assert_eq!(sexp!(({ 1 + 2 } {"x".to_string()})), list(&[atom(b"3"), atom(b"x")]));
+
+    assert_eq!(sexp!((offset - 2)), list(&[atom(b"offset"), atom(b"-2")]));
+    // String literals are used as is, e.g. for hyphenated atoms.
+    assert_eq!(
+        sexp!((region "us-east-1" "a-2" "-" "a;b" "")),
+        from_slice("(region us-east-1 a-2 - \"a;b\" \"\")").unwrap()
+    );
+}
+
+enum Tree {
+    Leaf(String),
+    Node(Vec<Tree>),
+}
+
+impl rsexp::SexpTree for Tree {
+    fn as_sexp_node(&self) -> rsexp::SexpNode<'_> {
+        match self {
+            Tree::Leaf(name) => rsexp::SexpNode::Atom(name.as_bytes()),
+            Tree::Node(children) => {
+                rsexp::SexpNode::List(Box::new(children.iter().map(|c| c as &dyn rsexp::SexpTree)))
+            }
+        }
+    }
+}
+
+#[test]
+fn build_sexp() {
+    let leaf = |name: &str| Tree::Leaf(name.to_string());
+    let tree = Tree::Node(vec![leaf("a"), Tree::Node(vec![leaf("b c")]), Tree::Node(vec![])]);
+    assert_eq!(rsexp::build_sexp(&tree).to_bytes(), b"(a (\"b c\") ())");
+    assert_eq!(rsexp::build_sexp(&leaf("a")).to_bytes(), b"a");
+    let sexp = from_slice("((a b) () (c (d (e))) f)").unwrap();
+    assert_eq!(rsexp::build_sexp(&sexp), sexp);
+
+    let mut tree = leaf("leaf");
+    for _ in 0..100_000 {
+        tree = Tree::Node(vec![tree]);
+    }
+    let mut sexp = rsexp::build_sexp(&tree);
+    let mut depth = 0;
+    while let Sexp::List(mut list) = sexp {
+        sexp = list.pop().unwrap();
+        depth += 1
+    }
+    assert_eq!((depth, sexp), (100_000, rsexp::atom(b"leaf")));
+    while let Tree::Node(mut children) = tree {
+        tree = children.pop().unwrap()
+    }
+}
+
+#[test]
+fn atom_from_display_and_list_from_iter() {
+    use rsexp::{atom, atom_from_display, list, list_from_iter};
+    assert_eq!(atom_from_display(&42), atom(b"42"));
+    assert_eq!(atom_from_display("foo bar"), atom(b"foo bar"));
+    assert_eq!(atom_from_display(&format_args!("{}-{}", 1, 2)), atom(b"1-2"));
+    assert_eq!(atom_from_display(""), atom(b""));
+    assert_eq!(list_from_iter(vec![]), list(&[]));
+    assert_eq!(
+        list_from_iter(["a", "b"].iter().map(atom_from_display)),
+        list(&[atom(b"a"), atom(b"b")])
+    );
+    assert_eq!(list_from_iter(Some(atom(b"x"))), list(&[atom(b"x")]));
+}
+
+#[test]
+fn lookup() {
+    let input = "((server ((host localhost) (ports (80 443)) (tls ((cert a.pem)))))
+                  (name \"my app\"))";
+    let lookup = |path| rsexp::lookup(input, path).unwrap().map(|s| s.to_string());
+    assert_eq!(lookup("name").as_deref(), Some("\"my app\""));
+    assert_eq!(lookup("server/host").as_deref(), Some("localhost"));
+    assert_eq!(lookup("server/ports").as_deref(), Some("(80 443)"));
+    assert_eq!(lookup("server/tls/cert").as_deref(), Some("a.pem"));
+    assert_eq!(rsexp::lookup(input, "").unwrap(), Some(from_slice(input).unwrap()));
+    assert_eq!(lookup("server/user"), None);
+    assert_eq!(lookup("server/host/name"), None);
+    assert_eq!(lookup("server/ports/80"), None);
+    assert_eq!(lookup("other/host"), None);
+    assert!(rsexp::lookup("((server (host a))", "server").is_err());
+}
+
+#[test]
+fn from_str() {
+    for input in ["foo", "(foo \"bar baz\")", " ; comment\n(a (b c)) "] {
+        let expected = from_slice(input);
+        assert_eq!(input.parse::<Sexp>(), expected);
+        assert_eq!(Sexp::try_from(input), expected);
+        assert_eq!(Sexp::try_from(input.as_bytes()), expected);
+    }
+    for input in ["(foo) bar", "(foo", "foo)", ""] {
+        let expected = from_slice(input);
+        assert!(expected.is_err());
+        assert_eq!(input.parse::<Sexp>(), expected);
+        assert_eq!(Sexp::try_from(input), expected);
+        assert_eq!(Sexp::try_from(input.as_bytes()), expected);
+    }
+    assert_eq!("(foo) bar".parse::<Sexp>(), Err(rsexp::Error::UnexpectedEof { offset: 6 }));
+}
+
+#[test]
+fn int128() {
+    assert_eq!(u128::MAX.sexp_of(), rsexp::atom(b"340282366920938463463374607431768211455"));
+    for &v in [0, 1, u128::MAX].iter() {
+        assert_eq!(v.sexp_of().of_sexp(), Ok(v));
+    }
+    for &v in [-1, i128::MIN, i128::MAX, -123456789012345678901234567890].iter() {
+        assert_eq!(v.sexp_of().of_sexp(), Ok(v));
+    }
+    assert_eq!(
+        from_slice("340282366920938463463374607431768211456").unwrap().of_sexp::<u128>(),
+        Err(IntoSexpError::StringConversionError {
+            err: "number too large to fit in target type".to_string()
+        })
+    );
+}
+
+#[test]
+fn non_zero() {
+    use std::num::{NonZeroI8, NonZeroU32, NonZeroU64};
+    let id = NonZeroU64::new(12345678901).unwrap();
+    assert_eq!(id.sexp_of(), rsexp::atom(b"12345678901"));
+    assert_eq!(from_slice("12345678901").unwrap().of_sexp(), Ok(id));
+    let ids: Vec<NonZeroI8> = from_slice("(1 -128 127)").unwrap().of_sexp().unwrap();
+    assert_eq!(ids.sexp_of().to_string(), "(1 -128 127)");
+    assert_eq!(
+        from_slice("0").unwrap().of_sexp::<NonZeroU32>(),
+        Err(IntoSexpError::StringConversionError {
+            err: "number would be zero for non-zero type".to_string()
+        })
+    );
+    assert_eq!(
+        from_slice("-1").unwrap().of_sexp::<NonZeroU32>(),
+        Err(IntoSexpError::StringConversionError {
+            err: "invalid digit found in string".to_string()
+        })
+    );
+}
+
+#[test]
+fn result() {
+    type R = Result<(i32, Option<bool>), String>;
+    let rt = |r: R, expected: &str| {
+        let sexp = r.sexp_of();
+        assert_eq!(sexp.to_string(), expected);
+        assert_eq!(from_slice(expected).unwrap().of_sexp::<R>(), Ok(r));
+    };
+    rt(Ok((1, Some(true))), "(Ok (1 (true)))");
+    rt(Ok((2, None)), "(Ok (2 ()))");
+    rt(Err("not found".to_string()), "(Error \"not found\")");
+    let err = |s: &str| from_slice(s).unwrap().of_sexp::<R>().unwrap_err();
+    assert_eq!(
+        err("(Err foo)"),
+        IntoSexpError::UnknownConstructorForEnum {
+            type_: "Result",
+            constructor: "Err".to_string()
+        }
+    );
+    assert_eq!(
+        err("Ok"),
+        IntoSexpError::ListLengthMismatch { type_: "Result", expected_len: 1, list_len: 0 }
+    );
+    assert_eq!(
+        err("(Error a b)"),
+        IntoSexpError::ListLengthMismatch { type_: "Result", expected_len: 1, list_len: 2 }
+    );
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Istr(std::rc::Rc<str>);
+
+impl AsRef<str> for Istr {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+rsexp::impl_sexp_of_str!(Istr);
+
+#[test]
+fn sexp_of_str() {
+    let istr = Istr("foo".into());
+    assert_eq!(istr.sexp_of(), "foo".sexp_of());
+    let values = [(istr.clone(), 1), (Istr("a (b)".into()), 2), (Istr("".into()), 3)];
+    assert_eq!(values.sexp_of().to_string(), r#"((foo 1) ("a (b)" 2) ("" 3))"#);
+    assert_eq!(Some(istr).sexp_of(), from_slice("(foo)").unwrap());
+}
+
 #[test]
 fn roundtrip_sexp() {
     assert_eq!(rt("(    ATOM)"), "(ATOM)");
@@ -84,6 +1185,126 @@ fn roundtrip_sexp_mach() {
     rt_mach("((g)(\" \"a\" \"b c)(e()d()(()a)b))");
 }
 
+#[cfg(feature = "deterministic")]
+#[test]
+fn deterministic_hash_map() {
+    use std::collections::{BTreeMap, HashMap};
+    let keys: Vec<String> = (0..100).map(|i| format!("key{i}")).collect();
+    let map1: HashMap<_, _> = keys.iter().map(|k| (k.clone(), k.len())).collect();
+    let map2: HashMap<_, _> = keys.iter().rev().map(|k| (k.clone(), k.len())).collect();
+    let bytes = map1.sexp_of().to_bytes();
+    assert_eq!(bytes, map2.sexp_of().to_bytes());
+    let btree_map: BTreeMap<_, _> = map1.into_iter().collect();
+    assert_eq!(bytes, btree_map.sexp_of().to_bytes());
+    // The entries are sorted by serialized keys, here "10" comes before "9".
+    let map: HashMap<u32, &str> = [(9, "a"), (10, "b"), (1, "c")].into_iter().collect();
+    assert_eq!(map.sexp_of().to_bytes(), b"((1 c) (10 b) (9 a))");
+}
+
+#[test]
+fn mach_sorted() {
+    use std::collections::HashMap;
+    let sorted =
+        |s: &str| String::from_utf8(from_slice(s).unwrap().to_bytes_mach_sorted()).unwrap();
+    assert_eq!(sorted("((b 1) (a 2) (c 3))"), "((a 2)(b 1)(c 3))");
+    // Nested association lists are sorted, the sort is stable.
+    assert_eq!(sorted("((z ((y 1) (x 2))) (a 3) (z 0))"), "((a 3)(z((x 2)(y 1)))(z 0))");
+    assert_eq!(sorted("(\"b c\" (b 1) (a 2))"), "(\"b c\"(b 1)(a 2))");
+    // Lists that are not association lists keep their order.
+    assert_eq!(sorted("(c b a)"), "(c b a)");
+    assert_eq!(sorted("((b 1) (a 2 3))"), "((b 1)(a 2 3))");
+    assert_eq!(sorted("(((b) 1) ((a) 2))"), "(((b)1)((a)2))");
+    assert_eq!(sorted("((b 1) a)"), "((b 1)a)");
+
+    let keys: Vec<String> = (0..100).map(|i| format!("key{i}")).collect();
+    let mut map1 = HashMap::new();
+    for (i, key) in keys.iter().enumerate() {
+        map1.insert(key.clone(), (i, i % 3 == 0));
+    }
+    let mut map2 = HashMap::new();
+    for (i, key) in keys.iter().enumerate().rev() {
+        map2.insert(key.clone(), (i, i % 3 == 0));
+    }
+    let bytes = map1.sexp_of().to_bytes_mach_sorted();
+    assert_eq!(bytes, map2.sexp_of().to_bytes_mach_sorted());
+    // String keys get sorted in the same way as by a BTreeMap.
+    let btree_map: std::collections::BTreeMap<_, _> = map1.into_iter().collect();
+    assert_eq!(bytes, btree_map.sexp_of().to_bytes_mach());
+}
+
+#[cfg(feature = "json")]
+#[test]
+fn json() {
+    use serde_json::{json, Value};
+    let to_sexp = |value: &Value| Sexp::from(value.clone()).to_string();
+    let value = json!({
+        "name": "some entry",
+        "count": 42,
+        "ratio": -1.5,
+        "enabled": false,
+        "tags": ["a", "b c"],
+        "nested": {"matrix": [[1, 2], [3]], "inner": {"x": "y"}}
+    });
+    assert_eq!(
+        to_sexp(&value),
+        r#"((count 42) (enabled false) (name "some entry") (nested ((inner ((x y))) (matrix ((1 2) (3))))) (ratio -1.5) (tags (a "b c")))"#
+    );
+    let sexp = from_slice(&to_sexp(&value)).unwrap();
+    assert_eq!(Value::try_from(sexp).unwrap(), value);
+    assert_eq!(
+        Value::try_from(from_slice("(1 (2 3) ())").unwrap()).unwrap(),
+        json!([1, [2, 3], []])
+    );
+
+    // Lossy conversions.
+    let round_trip = |value: Value| Value::try_from(Sexp::from(value)).unwrap();
+    assert_eq!(round_trip(json!(["true", "42", "4.2e1"])), json!([true, 42, 4.2e1]));
+    assert_eq!(round_trip(json!([null, {}, []])), json!([[], [], []]));
+    assert_eq!(round_trip(json!([["a", 1], ["b", null]])), json!({"a": 1, "b": []}));
+    assert_eq!(round_trip(json!(["01", "NaN", ""])), json!(["01", "NaN", ""]));
+    // Duplicate keys keep the last value.
+    let sexp = from_slice("((a 1) (b 2) (a 3))").unwrap();
+    assert_eq!(Value::try_from(sexp).unwrap(), json!({"a": 3, "b": 2}));
+
+    let err = Value::try_from(from_slice("(a \"\\255\")").unwrap()).unwrap_err();
+    assert!(matches!(err, IntoSexpError::FromUtf8Error(_)));
+}
+
+#[test]
+fn display_alternate() {
+    let sexp = from_slice(
+        "((name \"some configuration entry\") (values (alpha beta gamma delta epsilon zeta eta theta)))",
+    )
+    .unwrap();
+    assert_eq!(
+        format!("{sexp}"),
+        r#"((name "some configuration entry") (values (alpha beta gamma delta epsilon zeta eta theta)))"#
+    );
+    assert_eq!(
+        format!("{sexp:#}"),
+        r#"((name "some configuration entry")
+ (values (alpha beta gamma delta epsilon zeta eta theta)))"#
+    );
+    assert_eq!(format!("{sexp:#}"), sexp.pretty());
+    let short = from_slice("(foo (bar baz))").unwrap();
+    assert_eq!(format!("{short:#}"), format!("{short}"));
+}
+
+#[test]
+fn pretty_and_compact() {
+    let long = (0..30).map(|i| format!("(entry{i} value{i})")).collect::<Vec<_>>().join(" ");
+    for input in ["foo", "()", "((foo bar) (baz \"b a z\" \"\\255\\n\"))", &format!("({long})")] {
+        let sexp = from_slice(input).unwrap();
+        assert_eq!(sexp.pretty(), String::from_utf8_lossy(&sexp.to_bytes_hum()), "{input}");
+        assert_eq!(sexp.compact(), String::from_utf8_lossy(&sexp.to_bytes_mach()), "{input}");
+        assert_eq!(from_slice(&sexp.pretty()), Ok(sexp.clone()));
+        assert_eq!(from_slice(&sexp.compact()), Ok(sexp));
+    }
+    let sexp = rsexp::list(&[rsexp::atom("été".as_bytes()), rsexp::atom(b"\xff")]);
+    assert_eq!(sexp.pretty(), r#"("\195\169t\195\169" "\255")"#);
+    assert_eq!(sexp.compact(), r#"("\195\169t\195\169""\255")"#);
+}
+
 #[test]
 fn roundtrip_sexp_hum() {
     rt_hum("(ATOM)");
@@ -105,3 +1326,220 @@ fn roundtrip_sexp_hum() {
  (a beuhtaeuntaohutnaouhaonuhaonuthaounao hteounha))"#,
     );
 }
+
+#[test]
+fn write_hum_with_config() {
+    let hum = |sexp: &Sexp, max_line_width: usize, indent_str: &str| {
+        let mut buffer = Vec::new();
+        let indent_str = indent_str.as_bytes().to_vec();
+        let cfg = rsexp::HumConfig {
+            max_line_width,
+            indent: indent_str.len(),
+            indent_str,
+            ..Default::default()
+        };
+        sexp.write_hum_with_config(&cfg, &mut buffer).unwrap();
+        String::from_utf8(buffer).unwrap()
+    };
+    let sexp = from_slice(
+        "((name \"some configuration entry\") (values (alpha beta gamma delta epsilon)) (nested (a (b (c d)))))",
+    )
+    .unwrap();
+    assert_eq!(hum(&sexp, 90, " "), String::from_utf8(sexp.to_bytes_hum()).unwrap());
+    assert_eq!(
+        hum(&sexp, 40, " "),
+        r#"((name "some configuration entry")
+ (values
+  (alpha beta gamma delta epsilon))
+ (nested (a (b (c d)))))"#
+    );
+    assert_eq!(
+        hum(&sexp, 40, "  "),
+        r#"((name "some configuration entry")
+  (values
+    (alpha beta gamma delta epsilon))
+  (nested (a (b (c d)))))"#
+    );
+    assert_eq!(
+        hum(&sexp, 200, " "),
+        r#"((name "some configuration entry") (values (alpha beta gamma delta epsilon)) (nested (a (b (c d)))))"#
+    );
+    for (max_line_width, indent_str) in [(40, " "), (40, "  "), (40, "\t"), (200, " ")] {
+        assert_eq!(from_slice(&hum(&sexp, max_line_width, indent_str)).unwrap(), sexp);
+    }
+}
+
+#[test]
+fn write_hum_indent_str() {
+    let hum = |cfg: &rsexp::HumConfig| {
+        let sexp = from_slice("(config (entries (alpha beta) (gamma delta)))").unwrap();
+        let mut buffer = Vec::new();
+        sexp.write_hum_with_config(cfg, &mut buffer).unwrap();
+        buffer
+    };
+    let two_spaces = rsexp::HumConfig {
+        max_line_width: 24,
+        indent: 2,
+        indent_str: b"  ".to_vec(),
+        ..Default::default()
+    };
+    assert_eq!(hum(&two_spaces), b"(config\n  (entries (alpha beta)\n    (gamma delta)))");
+    let tab = rsexp::HumConfig {
+        max_line_width: 28,
+        indent: 8,
+        indent_str: b"\t".to_vec(),
+        ..Default::default()
+    };
+    assert_eq!(hum(&tab), b"(config\n\t(entries\n\t\t(alpha beta)\n\t\t(gamma delta)))");
+    // The tab is counted with its visual width when splitting lines.
+    let narrow_tab = rsexp::HumConfig { indent: 1, ..tab };
+    assert_eq!(hum(&narrow_tab), b"(config\n\t(entries (alpha beta)\n\t\t(gamma delta)))");
+}
+
+#[test]
+fn write_hum_trailing_newline() {
+    let hum = |sexp: &Sexp, cfg: &rsexp::HumConfig| {
+        let mut buffer = Vec::new();
+        sexp.write_hum_with_config(cfg, &mut buffer).unwrap();
+        String::from_utf8(buffer).unwrap()
+    };
+    let with_newline = rsexp::HumConfig { trailing_newline: true, ..Default::default() };
+    let sexp = from_slice("(foo (bar baz))").unwrap();
+    assert_eq!(hum(&sexp, &rsexp::HumConfig::default()), "(foo (bar baz))");
+    assert_eq!(hum(&sexp, &with_newline), "(foo (bar baz))\n");
+    assert_eq!(String::from_utf8(sexp.to_bytes_hum()).unwrap(), "(foo (bar baz))");
+    assert_eq!(hum(&rsexp::atom(b"foo"), &with_newline), "foo\n");
+    let narrow = rsexp::HumConfig { max_line_width: 10, ..with_newline };
+    assert_eq!(hum(&sexp, &narrow), "(foo\n (bar baz))\n");
+}
+
+#[test]
+fn write_mach_trailing_newline() {
+    let mach = |sexp: &Sexp, cfg: &rsexp::MachConfig| {
+        let mut buffer = Vec::new();
+        sexp.write_mach_with_config(cfg, &mut buffer).unwrap();
+        String::from_utf8(buffer).unwrap()
+    };
+    let with_newline = rsexp::MachConfig { trailing_newline: true, ..Default::default() };
+    let sexp = from_slice("((b (1 2)) (a \"x y\"))").unwrap();
+    assert_eq!(mach(&sexp, &rsexp::MachConfig::default()), "((b(1 2))(a\"x y\"))");
+    assert_eq!(mach(&sexp, &with_newline), "((b(1 2))(a\"x y\"))\n");
+    assert_eq!(mach(&rsexp::atom(b"foo"), &with_newline), "foo\n");
+    let sorted = rsexp::MachConfig { sort_alists: true, ..with_newline };
+    assert_eq!(mach(&sexp, &sorted), "((a\"x y\")(b(1 2)))\n");
+    assert_eq!(String::from_utf8(sexp.to_bytes_mach()).unwrap(), "((b(1 2))(a\"x y\"))");
+}
+
+#[cfg(feature = "regex")]
+#[test]
+fn redact_matching() {
+    let re = regex::bytes::Regex::new(r"\d{4}").unwrap();
+    let sexp = from_slice("((user foo1) (token \"tok 12345\") (codes (1 22 3333 abc)))").unwrap();
+    let redacted = sexp.redact_matching(&re, b"XXX");
+    assert_eq!(
+        String::from_utf8_lossy(&redacted.to_bytes()),
+        "((user foo1) (token XXX) (codes (1 22 XXX abc)))"
+    );
+    let sexp = from_slice("(\"\" ())").unwrap();
+    assert_eq!(sexp.redact_matching(&re, b"XXX"), sexp);
+}
+
+#[test]
+fn needs_escaping() {
+    // Boundary bytes, these follow the OCaml sexplib must_escape rules.
+    for c in [0u8, b'\t', b'\n', b' ', b'"', b'(', b')', b';', b'\\', 127, 128, 255] {
+        assert!(rsexp::needs_escaping(&[c]), "{c}");
+        assert!(rsexp::needs_escaping(&[b'a', c, b'b']), "{c}");
+    }
+    for c in [b'!', b'#', b'\'', b'|', b'~', b'{', b'[', b'0', b'-', b'.'] {
+        assert!(!rsexp::needs_escaping(&[c]), "{c}");
+        assert!(!rsexp::needs_escaping(&[b'a', c, b'b']), "{c}");
+    }
+    assert!(rsexp::needs_escaping(b""));
+    for atom in ["#|", "|#", "a#|b", "a|#b", "#||#", "||#"] {
+        assert!(rsexp::needs_escaping(atom.as_bytes()), "{atom}");
+    }
+    for atom in ["##", "||", "#a|", "|a#", "a#", "#a", "-1.5e3", "foo-bar'"] {
+        assert!(!rsexp::needs_escaping(atom.as_bytes()), "{atom}");
+    }
+    // Unquoted atoms parse back to themselves and serialization only quotes
+    // when required.
+    for c1 in 0..=255u8 {
+        for c2 in 0..=255u8 {
+            let atom = [c1, c2];
+            let sexp = rsexp::atom(&atom);
+            let bytes = sexp.to_bytes();
+            assert_eq!(bytes[0] == b'"', rsexp::needs_escaping(&atom), "{atom:?}");
+            assert_eq!(rsexp::would_quote(&atom), rsexp::needs_escaping(&atom), "{atom:?}");
+            assert_eq!(from_slice(&bytes), Ok(sexp), "{atom:?}");
+        }
+    }
+}
+
+#[test]
+fn escape_atom() {
+    use rsexp::Error;
+    assert_eq!(rsexp::escape_atom(b"foo"), br#""foo""#);
+    assert_eq!(rsexp::escape_atom(b""), br#""""#);
+    assert_eq!(rsexp::escape_atom(b"a \"b\" \\ c"), br#""a \"b\" \\ c""#);
+    assert_eq!(rsexp::escape_atom(b"\n\t\r\x08"), br#""\n\t\r\b""#);
+    assert_eq!(rsexp::escape_atom(b"\x00\x1f\x7f\xff"), br#""\000\031\127\255""#);
+    assert!(rsexp::needs_escaping(b"foo bar"));
+    assert!(rsexp::needs_escaping(b"a#|b"));
+    assert!(!rsexp::needs_escaping(b"foo-bar"));
+    for atom in [&b"foo"[..], b"", b"a \"b\" \\ c", b"\n\t\r\x08", b"\x00\x1f\x7f\xff", b"#|;"] {
+        assert_eq!(rsexp::unescape_atom(&rsexp::escape_atom(atom)), Ok(atom.to_vec()));
+        // Quoted atoms are serialized using the same escaping.
+        if rsexp::needs_escaping(atom) {
+            assert_eq!(rsexp::atom(atom).to_bytes(), rsexp::escape_atom(atom));
+        }
+    }
+    // Decimal and hexadecimal escapes, invalid sequences are kept verbatim.
+    assert_eq!(rsexp::unescape_atom(br#""\065\x42\x6a""#), Ok(b"ABj".to_vec()));
+    assert_eq!(rsexp::unescape_atom(br#""\06\xZ1\q""#), Ok(b"\\06\\xZ1\\q".to_vec()));
+    assert_eq!(rsexp::unescape_atom(b"\"a\\\n   b\""), Ok(b"ab".to_vec()));
+    assert_eq!(rsexp::unescape_atom(b""), Err(Error::UnexpectedEof { offset: 0 }));
+    assert_eq!(rsexp::unescape_atom(b"foo"), Err(Error::UnexpectedChar { c: b'f', offset: 0 }));
+    assert_eq!(rsexp::unescape_atom(br#""foo"#), Err(Error::UnexpectedEofInString { offset: 0 }));
+    assert_eq!(
+        rsexp::unescape_atom(br#""foo"bar"#),
+        Err(Error::UnexpectedChar { c: b'b', offset: 5 })
+    );
+}
+
+#[cfg(feature = "decimal")]
+#[test]
+fn decimal() {
+    use rust_decimal::Decimal;
+    use std::str::FromStr;
+    for s in ["1.50", "1.5", "0.000", "-12345.678900", "79228162514264337593543950335"] {
+        let value = Decimal::from_str(s).unwrap();
+        let sexp = value.sexp_of();
+        assert_eq!(sexp, rsexp::atom(s.as_bytes()));
+        let value2: Decimal = from_slice(&sexp.to_bytes()).unwrap().of_sexp().unwrap();
+        assert_eq!(value2.to_string(), s);
+        assert_eq!(value2.scale(), value.scale());
+    }
+    let values: Vec<Decimal> = from_slice("(1.50 2.000)").unwrap().of_sexp().unwrap();
+    assert_eq!(values.sexp_of().to_string(), "(1.50 2.000)");
+    let err = from_slice("1.5x").unwrap().of_sexp::<Decimal>().unwrap_err();
+    assert!(matches!(err, IntoSexpError::StringConversionError { .. }), "{err:?}");
+    let err = from_slice("(1.5)").unwrap().of_sexp::<Decimal>().unwrap_err();
+    assert_eq!(err, IntoSexpError::ExpectedAtomGotList { type_: "stringable", list_len: 1 });
+}
+
+#[cfg(feature = "bytes")]
+#[test]
+fn bytes() {
+    let blob = bytes::Bytes::from_static(b"\x00\x01 (blob)\n\"\x7f\xff");
+    let sexp = blob.sexp_of();
+    assert_eq!(sexp, rsexp::atom(&blob));
+    let bytes = sexp.to_bytes();
+    assert_eq!(bytes[0], b'"');
+    let blob2: bytes::Bytes = from_slice(&bytes).unwrap().of_sexp().unwrap();
+    assert_eq!(blob2, blob);
+    let blobs: Vec<bytes::Bytes> = from_slice("(a \"\\t\")").unwrap().of_sexp().unwrap();
+    assert_eq!(blobs, [&b"a"[..], b"\t"]);
+    let err = from_slice("(a b)").unwrap().of_sexp::<bytes::Bytes>().unwrap_err();
+    assert_eq!(err, IntoSexpError::ExpectedAtomGotList { type_: "Bytes", list_len: 2 });
+}
@@ -0,0 +1,9 @@
+use rsexp_derive::OfSexp;
+
+#[derive(OfSexp)]
+#[sexp(all_errors)]
+enum Shape {
+    Circle { radius: f64 },
+}
+
+fn main() {}
@@ -0,0 +1,10 @@
+use rsexp_derive::SexpOf;
+
+#[derive(SexpOf)]
+#[sexp(by_index)]
+enum Level {
+    Low = 1,
+    High = 10,
+}
+
+fn main() {}
@@ -0,0 +1,10 @@
+use rsexp_derive::OfSexp;
+
+#[derive(OfSexp)]
+enum Region {
+    #[sexp(rename = "B")]
+    A,
+    B,
+}
+
+fn main() {}
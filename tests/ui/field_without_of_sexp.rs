@@ -0,0 +1,11 @@
+use rsexp_derive::OfSexp;
+
+struct NoConversion;
+
+#[derive(OfSexp)]
+struct Record {
+    x: i64,
+    y: NoConversion,
+}
+
+fn main() {}
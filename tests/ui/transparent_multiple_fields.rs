@@ -0,0 +1,10 @@
+use rsexp_derive::SexpOf;
+
+#[derive(SexpOf)]
+#[sexp(transparent)]
+struct Point {
+    x: i64,
+    y: i64,
+}
+
+fn main() {}
@@ -0,0 +1,15 @@
+use rsexp_derive::{OfSexp, SexpOf};
+
+#[derive(SexpOf)]
+union UnionSexpOf {
+    x: i64,
+    y: f64,
+}
+
+#[derive(OfSexp)]
+union UnionOfSexp {
+    x: i64,
+    y: f64,
+}
+
+fn main() {}
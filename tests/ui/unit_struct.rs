@@ -0,0 +1,9 @@
+use rsexp_derive::{OfSexp, SexpOf};
+
+#[derive(SexpOf)]
+struct UnitSexpOf;
+
+#[derive(OfSexp)]
+struct UnitOfSexp;
+
+fn main() {}
@@ -0,0 +1,21 @@
+use rsexp_derive::{OfSexp, SexpOf};
+
+#[derive(SexpOf)]
+#[sexp(unknown)]
+struct Container {
+    x: i64,
+}
+
+#[derive(OfSexp)]
+struct Field {
+    #[sexp(unknown)]
+    x: i64,
+}
+
+#[derive(OfSexp)]
+enum Variant {
+    #[sexp(unknown)]
+    A,
+}
+
+fn main() {}